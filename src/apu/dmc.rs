@@ -0,0 +1,171 @@
+use crate::nes::Region;
+
+/// Timer periods in CPU cycles, picked by the low nibble of $4010.
+const NTSC_RATES: [u16; 16] = [428, 380, 340, 320, 286, 254, 226, 214, 190, 160, 142, 128, 106, 84, 72, 54];
+const PAL_RATES: [u16; 16] = [398, 354, 316, 298, 276, 236, 210, 198, 176, 148, 132, 118, 98, 78, 66, 50];
+
+const FLAGS_IRQ_ENABLE: u8 = 0x80;
+const FLAGS_LOOP: u8 = 0x40;
+
+const SAMPLE_ADDRESS_BASE: u16 = 0xC000;
+
+/// The delta modulation channel, $4010-$4013. It plays 1 bit deltas from
+/// a sample in CPU memory, each bit moving a 7 bit level up or down by 2.
+///
+/// It can't read memory itself: when its one byte buffer runs dry it asks
+/// for a DMA through `dma_request`, and the bus halts the CPU, reads the
+/// byte and hands it over to `dma_complete`.
+#[derive(Debug)]
+pub(super) struct Dmc {
+    rates: &'static [u16; 16],
+    irq_enabled: bool,
+    looping: bool,
+    rate: u8,
+    timer: u16,
+    pub(super) irq: bool,
+
+    sample_address: u16,
+    sample_length: u16,
+    address: u16,
+    bytes_remaining: u16,
+    buffer: Option<u8>,
+
+    shift_register: u8,
+    bits_remaining: u8,
+    silence: bool,
+    level: u8,
+}
+
+impl Default for Dmc {
+    fn default() -> Self {
+        Dmc {
+            rates: &NTSC_RATES,
+            irq_enabled: false,
+            looping: false,
+            rate: 0,
+            timer: 0,
+            irq: false,
+            sample_address: SAMPLE_ADDRESS_BASE,
+            sample_length: 1,
+            address: SAMPLE_ADDRESS_BASE,
+            bytes_remaining: 0,
+            buffer: None,
+            shift_register: 0x00,
+            bits_remaining: 8,
+            silence: true,
+            level: 0,
+        }
+    }
+}
+
+impl Dmc {
+    pub(super) fn set_region(&mut self, region: Region) {
+        self.rates = match region {
+            Region::Ntsc | Region::Dendy => &NTSC_RATES,
+            Region::Pal => &PAL_RATES,
+        };
+    }
+
+    /// `register` is 0-3.
+    pub(super) fn write(&mut self, register: u16, data: u8) {
+        match register {
+            0 => {
+                self.irq_enabled = data & FLAGS_IRQ_ENABLE != 0;
+                self.looping = data & FLAGS_LOOP != 0;
+                self.rate = data & 0x0F;
+                if !self.irq_enabled {
+                    self.irq = false;
+                }
+            }
+            1 => self.level = data & 0x7F,
+            2 => self.sample_address = SAMPLE_ADDRESS_BASE + data as u16 * 64,
+            _ => self.sample_length = data as u16 * 16 + 1,
+        }
+    }
+
+    /// Bit 4 of $4015: stop the sample, or start it over if it had ended.
+    pub(super) fn set_enabled(&mut self, enabled: bool) {
+        self.irq = false;
+
+        if !enabled {
+            self.bytes_remaining = 0;
+        } else if self.bytes_remaining == 0 {
+            self.restart();
+        }
+    }
+
+    /// Whether there are sample bytes left to fetch.
+    pub(super) fn active(&self) -> bool {
+        self.bytes_remaining > 0
+    }
+
+    pub(super) fn dma_request(&self) -> Option<u16> {
+        match self.buffer.is_none() && self.bytes_remaining > 0 {
+            true => Some(self.address),
+            false => None,
+        }
+    }
+
+    /// The address wraps from $FFFF around to $8000. A fetch that lands
+    /// after $4015 stopped the sample is thrown away.
+    pub(super) fn dma_complete(&mut self, data: u8) {
+        if self.bytes_remaining == 0 {
+            return;
+        }
+
+        self.buffer = Some(data);
+        self.address = self.address.checked_add(1).unwrap_or(0x8000);
+        self.bytes_remaining -= 1;
+
+        if self.bytes_remaining == 0 {
+            if self.looping {
+                self.restart();
+            } else if self.irq_enabled {
+                self.irq = true;
+            }
+        }
+    }
+
+    /// Every CPU cycle; the rates are in CPU cycles.
+    pub(super) fn clock_timer(&mut self) {
+        if self.timer > 0 {
+            self.timer -= 1;
+            return;
+        }
+        self.timer = self.rates[self.rate as usize] - 1;
+
+        if !self.silence {
+            match self.shift_register & 0x01 != 0 {
+                true if self.level <= 125 => self.level += 2,
+                false if self.level >= 2 => self.level -= 2,
+                _ => {}
+            }
+        }
+        self.shift_register >>= 1;
+        self.bits_remaining -= 1;
+
+        if self.bits_remaining == 0 {
+            self.bits_remaining = 8;
+            match self.buffer.take() {
+                Some(data) => {
+                    self.shift_register = data;
+                    self.silence = false;
+                }
+                None => self.silence = true,
+            }
+        }
+    }
+
+    /// 0-127.
+    pub(super) fn output(&self) -> u8 {
+        self.level
+    }
+}
+
+//private
+impl Dmc {
+    fn restart(&mut self) {
+        self.address = self.sample_address;
+        self.bytes_remaining = self.sample_length;
+    }
+}
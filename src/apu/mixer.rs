@@ -0,0 +1,41 @@
+use lazy_static::lazy_static;
+
+use super::Channel;
+
+lazy_static! {
+    /// The pulse DAC, indexed by pulse 1 + pulse 2.
+    static ref PULSE_TABLE: [f32; 31] = {
+        let mut table = [0.0; 31];
+        for (n, level) in table.iter_mut().enumerate().skip(1) {
+            *level = 95.52 / (8128.0 / n as f32 + 100.0);
+        }
+        table
+    };
+
+    /// The triangle, noise and DMC DAC, indexed by 3 * triangle + 2 *
+    /// noise + DMC. The real thing isn't quite linear in that sum, but the
+    /// difference can't be heard.
+    static ref TND_TABLE: [f32; 203] = {
+        let mut table = [0.0; 203];
+        for (n, level) in table.iter_mut().enumerate().skip(1) {
+            *level = 163.67 / (24329.0 / n as f32 + 100.0);
+        }
+        table
+    };
+}
+
+/// The channels' DAC outputs summed the way the 2A03's two resistor
+/// networks do it, from 0.0 to just under 1.0.
+pub(super) fn mix(pulse_1: u8, pulse_2: u8, triangle: u8, noise: u8, dmc: u8) -> f32 {
+    PULSE_TABLE[(pulse_1 + pulse_2) as usize] + TND_TABLE[3 * triangle as usize + 2 * noise as usize + dmc as usize]
+}
+
+/// One channel through its DAC with the others silent.
+pub(super) fn mix_alone(channel: Channel, level: u8) -> f32 {
+    match channel {
+        Channel::Pulse1 | Channel::Pulse2 => mix(level, 0, 0, 0, 0),
+        Channel::Triangle => mix(0, 0, level, 0, 0),
+        Channel::Noise => mix(0, 0, 0, level, 0),
+        Channel::Dmc => mix(0, 0, 0, 0, level),
+    }
+}
@@ -0,0 +1,411 @@
+mod dmc;
+mod mixer;
+mod noise;
+mod pulse;
+mod resampler;
+mod tests;
+mod triangle;
+mod units;
+
+use std::ops::RangeInclusive;
+
+use crate::bus::BusDevice;
+use crate::nes::Region;
+use dmc::Dmc;
+use noise::Noise;
+use pulse::Pulse;
+use resampler::Resampler;
+use triangle::Triangle;
+
+/// What `Apu::new` resamples to until told otherwise.
+pub const DEFAULT_SAMPLE_RATE: u32 = 44_100;
+/// The sample rates `Apu::set_sample_rate` accepts.
+pub const SAMPLE_RATES: RangeInclusive<u32> = 8_000..=384_000;
+
+const PULSE_1_START: u16 = 0x4000;
+const PULSE_1_END: u16 = 0x4003;
+const PULSE_2_START: u16 = 0x4004;
+const PULSE_2_END: u16 = 0x4007;
+const TRIANGLE_START: u16 = 0x4008;
+const TRIANGLE_END: u16 = 0x400B;
+const NOISE_START: u16 = 0x400C;
+const NOISE_END: u16 = 0x400F;
+const DMC_START: u16 = 0x4010;
+const DMC_END: u16 = 0x4013;
+const STATUS: u16 = 0x4015;
+const FRAME_COUNTER: u16 = 0x4017;
+
+const STATUS_PULSE_1: u8 = 0x01;
+const STATUS_PULSE_2: u8 = 0x02;
+const STATUS_TRIANGLE: u8 = 0x04;
+const STATUS_NOISE: u8 = 0x08;
+const STATUS_DMC: u8 = 0x10;
+const STATUS_FRAME_IRQ: u8 = 0x40;
+const STATUS_DMC_IRQ: u8 = 0x80;
+
+const FRAME_COUNTER_FIVE_STEP: u8 = 0x80;
+const FRAME_COUNTER_IRQ_INHIBIT: u8 = 0x40;
+
+/// CPU cycles into the frame counter sequence at which its steps fall: the
+/// first three quarter frames, the end of the 4-step sequence and the end
+/// of the 5-step one. The 4-step sequence raises its IRQ on the cycle
+/// before its last step and the two after, the last of which starts over.
+const NTSC_FRAME_STEPS: [u32; 5] = [7457, 14913, 22371, 29829, 37281];
+const PAL_FRAME_STEPS: [u32; 5] = [8313, 16627, 24939, 33253, 41565];
+
+/// The sound channels, for looking at what each one outputs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Channel {
+    Pulse1,
+    Pulse2,
+    Triangle,
+    Noise,
+    Dmc,
+}
+
+impl Channel {
+    pub const ALL: [Channel; 5] = [Channel::Pulse1, Channel::Pulse2, Channel::Triangle, Channel::Noise, Channel::Dmc];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Channel::Pulse1 => "pulse1",
+            Channel::Pulse2 => "pulse2",
+            Channel::Triangle => "triangle",
+            Channel::Noise => "noise",
+            Channel::Dmc => "dmc",
+        }
+    }
+}
+
+/// Audio samples as 16 bit PCM.
+pub fn to_i16(samples: &[f32]) -> Vec<i16> {
+    samples.iter().map(|sample| (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16).collect()
+}
+
+/// The 2A03's audio half, mapped at $4000-$4017 (minus $4014, the sprite
+/// DMA, and $4016, the controllers). It is clocked once per CPU cycle
+/// through `Bus::clock` and raises the frame counter and DMC IRQs through
+/// `BusDevice::irq`. The DMC's sample fetches go through the bus's DMA
+/// hooks.
+///
+/// The frame counter divides the CPU clock into quarter frames, which clock
+/// the envelopes and the triangle's linear counter, and half frames, which
+/// also clock the length counters and sweeps. Its timing follows the
+/// `Region`; Dendy consoles have NTSC's APU.
+///
+/// The channels are mixed and resampled as they go. Pull what has been
+/// produced with `take_samples` once a frame or so.
+pub struct Apu {
+    region: Region,
+    pulse_1: Pulse,
+    pulse_2: Pulse,
+    triangle: Triangle,
+    noise: Noise,
+    dmc: Dmc,
+    /// CPU cycles so far; the pulse timers run on every other one.
+    cycle: u64,
+
+    frame_steps: [u32; 5],
+    frame_cycle: u32,
+    five_step: bool,
+    irq_inhibit: bool,
+    frame_irq: bool,
+    /// CPU cycles until a $4017 write restarts the sequence.
+    frame_reset_in: Option<u8>,
+
+    sample_rate: u32,
+    resampler: Resampler,
+    /// Each channel on its own, in `Channel::ALL` order, when asked for.
+    stems: Option<Vec<Resampler>>,
+}
+
+impl Default for Apu {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+//public
+impl Apu {
+    pub fn new() -> Self {
+        Apu {
+            region: Region::Ntsc,
+            pulse_1: Pulse::new(true),
+            pulse_2: Pulse::new(false),
+            triangle: Triangle::default(),
+            noise: Noise::default(),
+            dmc: Dmc::default(),
+            cycle: 0,
+            frame_steps: NTSC_FRAME_STEPS,
+            frame_cycle: 0,
+            five_step: false,
+            irq_inhibit: false,
+            frame_irq: false,
+            frame_reset_in: None,
+            sample_rate: DEFAULT_SAMPLE_RATE,
+            resampler: Resampler::new(Region::Ntsc.cpu_clock_rate(), DEFAULT_SAMPLE_RATE),
+            stems: None,
+        }
+    }
+
+    pub fn set_region(&mut self, region: Region) {
+        self.region = region;
+        self.frame_steps = match region {
+            Region::Ntsc | Region::Dendy => NTSC_FRAME_STEPS,
+            Region::Pal => PAL_FRAME_STEPS,
+        };
+        self.noise.set_region(region);
+        self.dmc.set_region(region);
+        self.restart_resamplers();
+    }
+
+    pub fn region(&self) -> Region {
+        self.region
+    }
+
+    /// Starts resampling to `sample_rate` Hz, dropping anything not yet
+    /// taken. Panics if the rate is outside `SAMPLE_RATES`.
+    pub fn set_sample_rate(&mut self, sample_rate: u32) {
+        assert!(SAMPLE_RATES.contains(&sample_rate), "unsupported sample rate: {} Hz", sample_rate);
+        self.sample_rate = sample_rate;
+        self.restart_resamplers();
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// The mixed, filtered audio produced since the last call, mono at
+    /// `sample_rate`. Centred on 0 and within -1.0 to 1.0.
+    pub fn take_samples(&mut self) -> Vec<f32> {
+        self.resampler.take_samples()
+    }
+
+    /// `take_samples` as 16 bit PCM.
+    pub fn take_samples_i16(&mut self) -> Vec<i16> {
+        to_i16(&self.take_samples())
+    }
+
+    /// Also resample every channel on its own, through its own DAC, for
+    /// `take_stem_samples`. The stems don't add up to the mix exactly, as
+    /// the DACs aren't linear.
+    pub fn set_stems_enabled(&mut self, enabled: bool) {
+        self.stems = match enabled {
+            true => Some(Channel::ALL.iter().map(|_| self.new_resampler()).collect()),
+            false => None,
+        };
+    }
+
+    /// `take_samples` for one channel; empty unless stems are enabled.
+    pub fn take_stem_samples(&mut self, channel: Channel) -> Vec<f32> {
+        match &mut self.stems {
+            Some(stems) => stems[channel as usize].take_samples(),
+            None => vec![],
+        }
+    }
+
+    /// Reset silences every channel and restarts the frame counter in the
+    /// mode it was last set to.
+    pub fn reset(&mut self) {
+        self.write(STATUS, 0x00);
+        self.frame_irq = false;
+        self.frame_cycle = 0;
+        self.frame_reset_in = None;
+    }
+
+    /// Whether the frame counter IRQ flag is up. Reading $4015 or setting
+    /// the inhibit bit in $4017 clears it.
+    pub fn frame_irq(&self) -> bool {
+        self.frame_irq
+    }
+
+    /// Whether the DMC has finished a sample with its IRQ enabled. Writing
+    /// $4015 clears it.
+    pub fn dmc_irq(&self) -> bool {
+        self.dmc.irq
+    }
+
+    /// The channel's current level, 0-15, or 0-127 for the DMC.
+    pub fn output(&self, channel: Channel) -> u8 {
+        match channel {
+            Channel::Pulse1 => self.pulse_1.output(),
+            Channel::Pulse2 => self.pulse_2.output(),
+            Channel::Triangle => self.triangle.output(),
+            Channel::Noise => self.noise.output(),
+            Channel::Dmc => self.dmc.output(),
+        }
+    }
+}
+
+//private
+impl Apu {
+    fn read_status(&self) -> u8 {
+        let mut status = 0x00;
+
+        for (active, bit) in [
+            (self.pulse_1.length.active(), STATUS_PULSE_1),
+            (self.pulse_2.length.active(), STATUS_PULSE_2),
+            (self.triangle.length.active(), STATUS_TRIANGLE),
+            (self.noise.length.active(), STATUS_NOISE),
+            (self.dmc.active(), STATUS_DMC),
+            (self.frame_irq, STATUS_FRAME_IRQ),
+            (self.dmc.irq, STATUS_DMC_IRQ),
+        ] {
+            if active {
+                status |= bit;
+            }
+        }
+
+        status
+    }
+
+    /// The sequence restarts 3 or 4 CPU cycles after the write, depending
+    /// on whether it lands on an APU cycle or between two.
+    fn write_frame_counter(&mut self, data: u8) {
+        self.five_step = data & FRAME_COUNTER_FIVE_STEP != 0;
+        self.irq_inhibit = data & FRAME_COUNTER_IRQ_INHIBIT != 0;
+        if self.irq_inhibit {
+            self.frame_irq = false;
+        }
+
+        self.frame_reset_in = Some(if self.cycle.is_multiple_of(2) { 3 } else { 4 });
+    }
+
+    fn clock_frame_counter(&mut self) {
+        if let Some(delay) = self.frame_reset_in {
+            if delay > 1 {
+                self.frame_reset_in = Some(delay - 1);
+            } else {
+                self.frame_reset_in = None;
+                self.frame_cycle = 0;
+                // Switching to 5-step mode clocks everything straight away.
+                if self.five_step {
+                    self.clock_quarter_frame();
+                    self.clock_half_frame();
+                }
+                return;
+            }
+        }
+
+        self.frame_cycle += 1;
+
+        let [quarter_1, half_1, quarter_3, four_step_end, five_step_end] = self.frame_steps;
+        let end = if self.five_step { five_step_end } else { four_step_end };
+
+        match self.frame_cycle {
+            cycle if cycle == quarter_1 || cycle == quarter_3 => self.clock_quarter_frame(),
+            cycle if cycle == half_1 || cycle == end => {
+                self.clock_quarter_frame();
+                self.clock_half_frame();
+            }
+            _ => {}
+        }
+
+        if !self.five_step && (four_step_end - 1..=four_step_end + 1).contains(&self.frame_cycle) && !self.irq_inhibit {
+            self.frame_irq = true;
+        }
+
+        if self.frame_cycle > end {
+            self.frame_cycle = 0;
+        }
+    }
+
+    fn new_resampler(&self) -> Resampler {
+        Resampler::new(self.region.cpu_clock_rate(), self.sample_rate)
+    }
+
+    fn restart_resamplers(&mut self) {
+        self.resampler = self.new_resampler();
+        self.set_stems_enabled(self.stems.is_some());
+    }
+
+    fn mix(&self) -> f32 {
+        mixer::mix(self.pulse_1.output(), self.pulse_2.output(), self.triangle.output(), self.noise.output(), self.dmc.output())
+    }
+
+    fn clock_quarter_frame(&mut self) {
+        self.pulse_1.envelope.clock();
+        self.pulse_2.envelope.clock();
+        self.triangle.clock_quarter_frame();
+        self.noise.envelope.clock();
+    }
+
+    fn clock_half_frame(&mut self) {
+        self.pulse_1.clock_half_frame();
+        self.pulse_2.clock_half_frame();
+        self.triangle.clock_half_frame();
+        self.noise.length.clock();
+    }
+}
+
+impl BusDevice for Apu {
+    /// Only $4015 is readable; the write-only registers read back as 0
+    /// rather than as open bus.
+    fn read(&mut self, addr: u16) -> u8 {
+        let data = self.peek(addr);
+        if addr == STATUS {
+            self.frame_irq = false;
+        }
+        data
+    }
+
+    fn write(&mut self, addr: u16, data: u8) {
+        match addr {
+            PULSE_1_START..=PULSE_1_END => self.pulse_1.write(addr - PULSE_1_START, data),
+            PULSE_2_START..=PULSE_2_END => self.pulse_2.write(addr - PULSE_2_START, data),
+            TRIANGLE_START..=TRIANGLE_END => self.triangle.write(addr - TRIANGLE_START, data),
+            NOISE_START..=NOISE_END => self.noise.write(addr - NOISE_START, data),
+            DMC_START..=DMC_END => self.dmc.write(addr - DMC_START, data),
+            STATUS => {
+                self.pulse_1.length.set_enabled(data & STATUS_PULSE_1 != 0);
+                self.pulse_2.length.set_enabled(data & STATUS_PULSE_2 != 0);
+                self.triangle.length.set_enabled(data & STATUS_TRIANGLE != 0);
+                self.noise.length.set_enabled(data & STATUS_NOISE != 0);
+                self.dmc.set_enabled(data & STATUS_DMC != 0);
+            }
+            FRAME_COUNTER => self.write_frame_counter(data),
+            _ => {}
+        }
+    }
+
+    fn peek(&self, addr: u16) -> u8 {
+        match addr {
+            STATUS => self.read_status(),
+            _ => 0x00,
+        }
+    }
+
+    fn clock(&mut self) {
+        self.clock_frame_counter();
+
+        self.triangle.clock_timer();
+        self.noise.clock_timer();
+        self.dmc.clock_timer();
+        if !self.cycle.is_multiple_of(2) {
+            self.pulse_1.clock_timer();
+            self.pulse_2.clock_timer();
+        }
+        self.resampler.clock(self.mix());
+
+        if self.stems.is_some() {
+            let levels = Channel::ALL.map(|channel| self.output(channel));
+            for ((stem, channel), level) in self.stems.iter_mut().flatten().zip(Channel::ALL).zip(levels) {
+                stem.clock(mixer::mix_alone(channel, level));
+            }
+        }
+
+        self.cycle += 1;
+    }
+
+    fn irq(&self) -> bool {
+        self.frame_irq || self.dmc.irq
+    }
+
+    fn dma_request(&self) -> Option<u16> {
+        self.dmc.dma_request()
+    }
+
+    fn dma_complete(&mut self, data: u8) {
+        self.dmc.dma_complete(data);
+    }
+}
@@ -0,0 +1,89 @@
+use super::units::{Envelope, LengthCounter};
+use crate::nes::Region;
+
+/// Timer periods in CPU cycles, picked by the low nibble of $400E.
+const NTSC_PERIODS: [u16; 16] = [4, 8, 16, 32, 64, 96, 128, 160, 202, 254, 380, 508, 762, 1016, 2034, 4068];
+const PAL_PERIODS: [u16; 16] = [4, 8, 14, 30, 60, 88, 118, 148, 188, 236, 354, 472, 708, 944, 1890, 3778];
+
+const MODE_SHORT: u8 = 0x80;
+
+/// $400C-$400F. A 15 bit linear feedback shift register, tapped at bit 1
+/// for the 32767 step "white" noise, or at bit 6 for the metallic 93 (or
+/// 31) step loop.
+#[derive(Debug)]
+pub(super) struct Noise {
+    periods: &'static [u16; 16],
+    short_mode: bool,
+    period: u8,
+    timer: u16,
+    shift_register: u16,
+    pub(super) envelope: Envelope,
+    pub(super) length: LengthCounter,
+}
+
+impl Default for Noise {
+    fn default() -> Self {
+        Noise {
+            periods: &NTSC_PERIODS,
+            short_mode: false,
+            period: 0,
+            timer: 0,
+            shift_register: 0x0001,
+            envelope: Envelope::default(),
+            length: LengthCounter::default(),
+        }
+    }
+}
+
+impl Noise {
+    pub(super) fn set_region(&mut self, region: Region) {
+        self.periods = match region {
+            Region::Ntsc | Region::Dendy => &NTSC_PERIODS,
+            Region::Pal => &PAL_PERIODS,
+        };
+    }
+
+    /// `register` is 0-3; register 1 isn't connected.
+    pub(super) fn write(&mut self, register: u16, data: u8) {
+        match register {
+            0 => {
+                self.envelope.write(data);
+                self.length.set_halted(data & 0x20 != 0);
+            }
+            1 => {}
+            2 => {
+                self.short_mode = data & MODE_SHORT != 0;
+                self.period = data & 0x0F;
+            }
+            _ => {
+                self.length.load(data);
+                self.envelope.restart();
+            }
+        }
+    }
+
+    /// Every CPU cycle; the periods are in CPU cycles.
+    pub(super) fn clock_timer(&mut self) {
+        if self.timer == 0 {
+            self.timer = self.periods[self.period as usize] - 1;
+            self.shift();
+        } else {
+            self.timer -= 1;
+        }
+    }
+
+    /// 0-15.
+    pub(super) fn output(&self) -> u8 {
+        match self.shift_register & 0x0001 == 0 && self.length.active() {
+            true => self.envelope.output(),
+            false => 0,
+        }
+    }
+
+    pub(super) fn shift(&mut self) {
+        let tap = if self.short_mode { 6 } else { 1 };
+        let feedback = (self.shift_register ^ (self.shift_register >> tap)) & 0x0001;
+
+        self.shift_register = (self.shift_register >> 1) | feedback << 14;
+    }
+}
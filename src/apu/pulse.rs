@@ -0,0 +1,116 @@
+use super::units::{Envelope, LengthCounter};
+
+/// The four duty cycles, one bit per step: 12.5%, 25%, 50% and 25%
+/// inverted.
+const DUTY_SEQUENCES: [u8; 4] = [0b0100_0000, 0b0110_0000, 0b0111_1000, 0b1001_1111];
+
+/// Periods below this are too high to be useful and mute the channel.
+const MIN_PERIOD: u16 = 8;
+const MAX_PERIOD: u16 = 0x07FF;
+
+/// A square wave channel: $4000-$4003 for pulse 1, $4004-$4007 for pulse 2.
+///
+/// The sweep unit bends the period up or down every few half frames. The
+/// two channels negate differently: pulse 1 subtracts one more than pulse
+/// 2 does.
+#[derive(Debug, Default)]
+pub(super) struct Pulse {
+    ones_complement: bool,
+    duty: u8,
+    step: u8,
+    period: u16,
+    timer: u16,
+    pub(super) envelope: Envelope,
+    pub(super) length: LengthCounter,
+
+    sweep_enabled: bool,
+    sweep_period: u8,
+    sweep_negate: bool,
+    sweep_shift: u8,
+    sweep_divider: u8,
+    sweep_reload: bool,
+}
+
+impl Pulse {
+    pub(super) fn new(ones_complement: bool) -> Self {
+        Pulse { ones_complement, ..Default::default() }
+    }
+
+    /// `register` is 0-3, whichever channel this is.
+    pub(super) fn write(&mut self, register: u16, data: u8) {
+        match register {
+            0 => {
+                self.duty = data >> 6;
+                self.envelope.write(data);
+                self.length.set_halted(data & 0x20 != 0);
+            }
+            1 => {
+                self.sweep_enabled = data & 0x80 != 0;
+                self.sweep_period = (data >> 4) & 0x07;
+                self.sweep_negate = data & 0x08 != 0;
+                self.sweep_shift = data & 0x07;
+                self.sweep_reload = true;
+            }
+            2 => self.period = (self.period & 0x0700) | data as u16,
+            _ => {
+                self.period = (self.period & 0x00FF) | ((data & 0x07) as u16) << 8;
+                self.length.load(data);
+                self.step = 0;
+                self.envelope.restart();
+            }
+        }
+    }
+
+    /// Every APU cycle, i.e. every other CPU cycle.
+    pub(super) fn clock_timer(&mut self) {
+        if self.timer == 0 {
+            self.timer = self.period;
+            self.step = (self.step + 1) & 0x07;
+        } else {
+            self.timer -= 1;
+        }
+    }
+
+    pub(super) fn clock_half_frame(&mut self) {
+        self.length.clock();
+
+        if self.sweep_divider == 0 && self.sweep_enabled && self.sweep_shift > 0 && !self.muted() {
+            self.period = self.sweep_target();
+        }
+        if self.sweep_divider == 0 || self.sweep_reload {
+            self.sweep_divider = self.sweep_period;
+            self.sweep_reload = false;
+        } else {
+            self.sweep_divider -= 1;
+        }
+    }
+
+    /// 0-15.
+    pub(super) fn output(&self) -> u8 {
+        let high = DUTY_SEQUENCES[self.duty as usize] & (0x80 >> self.step) != 0;
+
+        match high && self.length.active() && !self.muted() {
+            true => self.envelope.output(),
+            false => 0,
+        }
+    }
+}
+
+//private
+impl Pulse {
+    /// The sweep target is worked out all the time, and mutes the channel
+    /// when out of range even if the sweep is off.
+    fn sweep_target(&self) -> u16 {
+        let change = self.period >> self.sweep_shift;
+
+        match (self.sweep_negate, self.ones_complement) {
+            (false, _) => self.period + change,
+            (true, true) => self.period.saturating_sub(change + 1),
+            (true, false) => self.period.saturating_sub(change),
+        }
+    }
+
+    fn muted(&self) -> bool {
+        self.period < MIN_PERIOD || self.sweep_target() > MAX_PERIOD
+    }
+}
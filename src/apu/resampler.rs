@@ -0,0 +1,150 @@
+use std::collections::VecDeque;
+use std::f64::consts::PI;
+
+use lazy_static::lazy_static;
+
+/// Taps of the band-limited step, and how finely its position between two
+/// output samples is resolved.
+const TAPS: usize = 16;
+const PHASES: usize = 64;
+/// Where the kernel cuts off, as a fraction of the output Nyquist rate.
+const CUTOFF: f64 = 0.9;
+
+lazy_static! {
+    /// Windowed sinc impulses, one per phase, each summing to 1. A change
+    /// in level lands in the output as one of these, integrated.
+    static ref KERNEL: [[f32; TAPS]; PHASES] = {
+        let mut kernel = [[0.0; TAPS]; PHASES];
+
+        for (phase, taps) in kernel.iter_mut().enumerate() {
+            let center = (TAPS / 2) as f64 + phase as f64 / PHASES as f64;
+            let mut sum = 0.0;
+
+            for (tap, value) in taps.iter_mut().enumerate() {
+                let x = tap as f64 - center;
+                let sinc = if x == 0.0 { 1.0 } else { (PI * CUTOFF * x).sin() / (PI * CUTOFF * x) };
+                let blackman = {
+                    let t = (tap as f64 - center) / TAPS as f64 + 0.5;
+                    0.42 - 0.5 * (2.0 * PI * t).cos() + 0.08 * (4.0 * PI * t).cos()
+                };
+
+                *value = (sinc * blackman.max(0.0)) as f32;
+                sum += *value as f64;
+            }
+            for value in taps.iter_mut() {
+                *value = (*value as f64 / sum) as f32;
+            }
+        }
+
+        kernel
+    };
+}
+
+/// A first order RC filter, high-pass or low-pass.
+#[derive(Debug, Clone, Copy)]
+struct Filter {
+    high_pass: bool,
+    alpha: f32,
+    last_input: f32,
+    last_output: f32,
+}
+
+impl Filter {
+    fn new(high_pass: bool, cutoff: f64, sample_rate: f64) -> Self {
+        let rc = 1.0 / (2.0 * PI * cutoff);
+        let dt = 1.0 / sample_rate;
+        let alpha = match high_pass {
+            true => rc / (rc + dt),
+            false => dt / (rc + dt),
+        };
+
+        Filter { high_pass, alpha: alpha as f32, last_input: 0.0, last_output: 0.0 }
+    }
+
+    fn process(&mut self, input: f32) -> f32 {
+        self.last_output = match self.high_pass {
+            true => self.alpha * (self.last_output + input - self.last_input),
+            false => self.last_output + self.alpha * (input - self.last_output),
+        };
+        self.last_input = input;
+        self.last_output
+    }
+}
+
+/// Turns a level sampled every CPU cycle into audio at `sample_rate`.
+///
+/// Rather than picking every 40th or so value, which aliases badly at the
+/// pulse channels' hard edges, each change in level is added to the output
+/// as a band-limited step at its exact position between two samples. The
+/// result then goes through the console's own filters: high-pass at 90Hz
+/// and 440Hz and low-pass at 14kHz.
+#[derive(Debug)]
+pub(super) struct Resampler {
+    /// Output samples per CPU cycle.
+    ratio: f64,
+    /// Where the current CPU cycle falls, in output samples after the
+    /// oldest unfinished one.
+    time: f64,
+    level: f32,
+    /// Changes in level still spreading into the unfinished samples.
+    deltas: VecDeque<f32>,
+    integrator: f32,
+    filters: [Filter; 3],
+    samples: Vec<f32>,
+}
+
+impl Resampler {
+    pub(super) fn new(clock_rate: f64, sample_rate: u32) -> Self {
+        let sample_rate = sample_rate as f64;
+
+        Resampler {
+            ratio: sample_rate / clock_rate,
+            time: 0.0,
+            level: 0.0,
+            deltas: VecDeque::from(vec![0.0; TAPS]),
+            integrator: 0.0,
+            filters: [
+                Filter::new(true, 90.0, sample_rate),
+                Filter::new(true, 440.0, sample_rate),
+                Filter::new(false, 14_000.0, sample_rate),
+            ],
+            samples: vec![],
+        }
+    }
+
+    /// Takes the level for one CPU cycle.
+    pub(super) fn clock(&mut self, level: f32) {
+        if level != self.level {
+            let delta = level - self.level;
+            let phase = ((self.time * PHASES as f64) as usize).min(PHASES - 1);
+
+            for (slot, &tap) in self.deltas.iter_mut().zip(KERNEL[phase].iter()) {
+                *slot += delta * tap;
+            }
+            self.level = level;
+        }
+
+        self.time += self.ratio;
+        while self.time >= 1.0 {
+            self.time -= 1.0;
+            self.finish_sample();
+        }
+    }
+
+    /// Everything finished since the last call.
+    pub(super) fn take_samples(&mut self) -> Vec<f32> {
+        std::mem::take(&mut self.samples)
+    }
+}
+
+//private
+impl Resampler {
+    /// No later step can reach the oldest unfinished sample any more.
+    fn finish_sample(&mut self) {
+        self.integrator += self.deltas.pop_front().unwrap_or(0.0);
+        self.deltas.push_back(0.0);
+
+        let sample = self.filters.iter_mut().fold(self.integrator, |sample, filter| filter.process(sample));
+        self.samples.push(sample);
+    }
+}
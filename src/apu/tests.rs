@@ -0,0 +1,364 @@
+#[cfg(test)]
+mod apu_tests {
+    use crate::apu::*;
+
+    fn run(apu: &mut Apu, cycles: u32) {
+        for _ in 0..cycles {
+            apu.clock();
+        }
+    }
+
+    /// CPU cycles from one rising edge of the channel's output to the next.
+    fn wave_length(apu: &mut Apu, channel: Channel) -> u32 {
+        let mut edges = vec![];
+        let mut high = apu.output(channel) > 0;
+
+        for cycle in 0..10_000 {
+            apu.clock();
+            let now = apu.output(channel) > 0;
+            if now && !high {
+                edges.push(cycle);
+            }
+            high = now;
+        }
+
+        edges[1] - edges[0]
+    }
+
+    /// A pulse 1 square wave at full volume, 50% duty.
+    fn play_square(apu: &mut Apu, period: u16) {
+        apu.write(0x4015, 0x01);
+        apu.write(0x4000, 0xBF);
+        apu.write(0x4002, period as u8);
+        apu.write(0x4003, (period >> 8) as u8 | 0x08);
+    }
+
+    /// Whether the sequence repeats every `period` entries.
+    fn repeats_every(sequence: &[u8], period: usize) -> bool {
+        sequence.iter().zip(&sequence[period..]).all(|(a, b)| a == b)
+    }
+
+    #[test]
+    fn length_counters_run_out_on_half_frames() {
+        let mut apu = Apu::new();
+        apu.write(0x4015, 0x03);
+        // Length index 3 is 2 half frames; pulse 2 is halted.
+        apu.write(0x4003, 0x18);
+        apu.write(0x4004, 0x20);
+        apu.write(0x4007, 0x18);
+        // The triangle is disabled and ignores the load.
+        apu.write(0x400B, 0x18);
+        assert_eq!(apu.read(0x4015) & 0x0F, 0x03);
+
+        run(&mut apu, 14913);
+        assert_eq!(apu.read(0x4015) & 0x0F, 0x03);
+        run(&mut apu, 29829 - 14913);
+        assert_eq!(apu.read(0x4015) & 0x0F, 0x02);
+
+        apu.write(0x4015, 0x00);
+        assert_eq!(apu.read(0x4015) & 0x0F, 0x00);
+    }
+
+    #[test]
+    fn frame_irq_only_in_four_step_mode() {
+        let mut apu = Apu::new();
+        run(&mut apu, 29827);
+        assert!(!apu.irq());
+        run(&mut apu, 1);
+        assert!(apu.irq());
+
+        // Reading $4015 shows and acknowledges it.
+        assert_eq!(apu.read(0x4015), 0x40);
+        assert!(!apu.irq());
+
+        run(&mut apu, 29830);
+        assert!(apu.irq());
+        apu.write(0x4017, 0x40);
+        assert!(!apu.irq());
+        run(&mut apu, 2 * 29830);
+        assert!(!apu.irq());
+
+        apu.write(0x4017, 0x80);
+        run(&mut apu, 2 * 37282);
+        assert!(!apu.irq());
+    }
+
+    #[test]
+    fn frame_counter_timing_follows_the_region() {
+        for (region, irq_at) in [(Region::Ntsc, 29828), (Region::Pal, 33252), (Region::Dendy, 29828)] {
+            let mut apu = Apu::new();
+            apu.set_region(region);
+
+            run(&mut apu, irq_at - 1);
+            assert!(!apu.irq(), "{:?}", region);
+            run(&mut apu, 1);
+            assert!(apu.irq(), "{:?}", region);
+        }
+    }
+
+    #[test]
+    fn five_step_mode_clocks_half_frames_right_away() {
+        let mut apu = Apu::new();
+        apu.write(0x4015, 0x01);
+        apu.write(0x4003, 0x18);
+
+        apu.write(0x4017, 0x80);
+        run(&mut apu, 4);
+        apu.write(0x4017, 0x80);
+        run(&mut apu, 4);
+        assert_eq!(apu.read(0x4015) & 0x01, 0x00);
+    }
+
+    #[test]
+    fn pulse_duty_cycles() {
+        let mut high_cycles = vec![];
+
+        for duty in 0..4 {
+            let mut apu = Apu::new();
+            apu.write(0x4015, 0x01);
+            apu.write(0x4000, duty << 6 | 0x3F);
+            apu.write(0x4002, 0x08);
+            apu.write(0x4003, 0x08);
+
+            // One full wave is 16 * (period + 1) CPU cycles.
+            let mut high = 0;
+            for _ in 0..16 * 9 {
+                apu.clock();
+                high += (apu.output(Channel::Pulse1) == 15) as u32;
+            }
+            high_cycles.push(high);
+        }
+
+        assert_eq!(high_cycles, vec![18, 36, 72, 108]);
+    }
+
+    #[test]
+    fn sweeps_negate_differently_on_each_pulse() {
+        let mut apu = Apu::new();
+        apu.write(0x4015, 0x03);
+        for base in [0x4000, 0x4004] {
+            apu.write(base, 0xBF);
+            // Enabled, every half frame, down by period >> 1.
+            apu.write(base + 1, 0x89);
+            apu.write(base + 2, 0x00);
+            apu.write(base + 3, 0x09);
+        }
+
+        run(&mut apu, 14913);
+
+        // $100 - $80 - 1 and $100 - $80.
+        assert_eq!(wave_length(&mut apu, Channel::Pulse1), 16 * 0x80);
+        assert_eq!(wave_length(&mut apu, Channel::Pulse2), 16 * 0x81);
+    }
+
+    #[test]
+    fn sweep_target_out_of_range_mutes_even_when_disabled() {
+        let mut apu = Apu::new();
+        apu.write(0x4015, 0x03);
+        for (base, period_high) in [(0x4000, 0x06), (0x4004, 0x03)] {
+            apu.write(base, 0xBF);
+            apu.write(base + 1, 0x01);
+            apu.write(base + 2, 0x00);
+            apu.write(base + 3, 0x08 | period_high);
+        }
+
+        let (mut pulse_1, mut pulse_2) = (0, 0);
+        for _ in 0..20_000 {
+            apu.clock();
+            pulse_1 = pulse_1.max(apu.output(Channel::Pulse1));
+            pulse_2 = pulse_2.max(apu.output(Channel::Pulse2));
+        }
+
+        assert_eq!((pulse_1, pulse_2), (0, 15));
+    }
+
+    #[test]
+    fn envelope_decays_and_loops() {
+        let mut apu = Apu::new();
+        apu.write(0x4000, 0x20);
+        apu.write(0x4003, 0x00);
+
+        let mut levels = vec![];
+        for _ in 0..18 {
+            apu.clock_quarter_frame();
+            levels.push(apu.pulse_1.envelope.output());
+        }
+
+        assert_eq!(levels, vec![15, 14, 13, 12, 11, 10, 9, 8, 7, 6, 5, 4, 3, 2, 1, 0, 15, 14]);
+    }
+
+    #[test]
+    fn triangle_stops_with_the_linear_counter() {
+        let mut apu = Apu::new();
+        apu.write(0x4015, 0x04);
+        apu.write(0x4008, 0x02);
+        apu.write(0x400A, 0x10);
+        apu.write(0x400B, 0x08);
+
+        let mut levels = vec![];
+        for _ in 0..30_000 {
+            apu.clock();
+            levels.push(apu.output(Channel::Triangle));
+        }
+
+        // Loaded on the first quarter frame, then two more to run out.
+        let moving = |range: std::ops::Range<usize>| levels[range].windows(2).any(|pair| pair[0] != pair[1]);
+        assert!(!moving(0..7456));
+        assert!(moving(7457..22370));
+        assert!(!moving(22371..30_000));
+    }
+
+    #[test]
+    fn noise_loops_through_32767_or_93_steps() {
+        let mut apu = Apu::new();
+        apu.write(0x4015, 0x08);
+        apu.write(0x400C, 0x3F);
+        apu.write(0x400F, 0x08);
+
+        let sequence = |apu: &mut Apu, steps: usize| {
+            (0..steps)
+                .map(|_| {
+                    apu.noise.shift();
+                    apu.output(Channel::Noise)
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let long = sequence(&mut apu, 2 * 32767);
+        assert!(repeats_every(&long, 32767));
+        assert!(!repeats_every(&long, 93));
+
+        apu.write(0x400E, 0x80);
+        let short = sequence(&mut apu, 4 * 93);
+        assert!(repeats_every(&short, 93));
+        assert!(!repeats_every(&short, 31));
+    }
+
+    #[test]
+    fn dmc_plays_a_sample_and_raises_its_irq() {
+        let mut apu = Apu::new();
+        // IRQ on, fastest rate, one byte at $C040.
+        apu.write(0x4010, 0x8F);
+        apu.write(0x4011, 0x40);
+        apu.write(0x4012, 0x01);
+        apu.write(0x4013, 0x00);
+        assert_eq!(apu.dma_request(), None);
+
+        apu.write(0x4015, 0x10);
+        assert_eq!(apu.read(0x4015), 0x10);
+        assert_eq!(apu.dma_request(), Some(0xC040));
+        apu.dma_complete(0b1111_0000);
+        assert_eq!(apu.dma_request(), None);
+        assert_eq!(apu.read(0x4015), 0x80);
+        assert!(apu.irq());
+
+        // The byte only starts playing once the silent one before it ends:
+        // four bits down, then four up.
+        run(&mut apu, 8 * 54);
+        assert_eq!(apu.output(Channel::Dmc), 0x40);
+        run(&mut apu, 4 * 54);
+        assert_eq!(apu.output(Channel::Dmc), 0x40 - 8);
+        run(&mut apu, 4 * 54);
+        assert_eq!(apu.output(Channel::Dmc), 0x40);
+
+        apu.write(0x4015, 0x00);
+        assert!(!apu.irq());
+    }
+
+    #[test]
+    fn dmc_loops_without_an_irq() {
+        let mut apu = Apu::new();
+        apu.write(0x4010, 0xC0);
+        apu.write(0x4012, 0xFF);
+        apu.write(0x4013, 0x04);
+        apu.write(0x4015, 0x10);
+
+        let mut addresses = vec![];
+        for _ in 0..70 {
+            let addr = apu.dma_request().unwrap();
+            addresses.push(addr);
+            apu.dma_complete(0x00);
+            run(&mut apu, 8 * 428);
+        }
+
+        // 65 bytes from $FFC0, the last of which wraps around to $8000.
+        assert_eq!(addresses[..2], [0xFFC0, 0xFFC1]);
+        assert_eq!(addresses[63..67], [0xFFFF, 0x8000, 0xFFC0, 0xFFC1]);
+        assert!(!apu.dmc_irq());
+    }
+
+    #[test]
+    fn channels_mix_through_the_nonlinear_dacs() {
+        assert_eq!(mixer::mix(0, 0, 0, 0, 0), 0.0);
+
+        let pulses = mixer::mix(15, 15, 0, 0, 0);
+        assert!((pulses - 95.88 / (8128.0 / 30.0 + 100.0)).abs() < 0.002, "{}", pulses);
+        // Twice the input is well short of twice the output.
+        assert!(mixer::mix(15, 0, 0, 0, 0) > pulses * 0.55);
+
+        let tnd = mixer::mix(0, 0, 15, 15, 127);
+        let exact = 159.79 / (1.0 / (15.0 / 8227.0 + 15.0 / 12241.0 + 127.0 / 22638.0) + 100.0);
+        assert!((tnd - exact).abs() < 0.01, "{} {}", tnd, exact);
+    }
+
+    #[test]
+    fn resamples_to_the_output_rate() {
+        for sample_rate in [44_100, 48_000] {
+            let mut apu = Apu::new();
+            apu.set_sample_rate(sample_rate);
+            play_square(&mut apu, 0x100);
+
+            // A tenth of a second.
+            run(&mut apu, 178_977);
+
+            let samples = apu.take_samples();
+            assert!(samples.len().abs_diff(sample_rate as usize / 10) <= 1, "{}", samples.len());
+            assert!(apu.take_samples().is_empty());
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "unsupported sample rate")]
+    fn rejects_a_sample_rate_of_zero() {
+        Apu::new().set_sample_rate(0);
+    }
+
+    #[test]
+    #[should_panic(expected = "unsupported sample rate")]
+    fn rejects_unreasonably_high_sample_rates() {
+        Apu::new().set_sample_rate(*SAMPLE_RATES.end() + 1);
+    }
+
+    #[test]
+    fn square_waves_come_out_band_limited_and_centred() {
+        let mut apu = Apu::new();
+        // 1789773 / (16 * 112) is about 999Hz.
+        play_square(&mut apu, 111);
+        run(&mut apu, 178_977);
+
+        // Past the start-up transient, two zero crossings per cycle.
+        let samples = apu.take_samples();
+        let settled = &samples[samples.len() / 2..];
+        let crossings = settled.windows(2).filter(|pair| (pair[0] < 0.0) != (pair[1] < 0.0)).count();
+        assert!((98..=102).contains(&crossings), "{}", crossings);
+
+        // A stepped square wave has no business overshooting by more than
+        // the usual ringing, and the high-pass filters take out the DC.
+        let peak = settled.iter().fold(0.0f32, |peak, sample| peak.max(sample.abs()));
+        assert!(peak < 0.2, "{}", peak);
+        let mean = settled.iter().sum::<f32>() / settled.len() as f32;
+        assert!(mean.abs() < 0.01, "{}", mean);
+    }
+
+    #[test]
+    fn steps_decay_to_silence() {
+        let mut apu = Apu::new();
+        apu.write(0x4011, 0x7F);
+        run(&mut apu, 178_977);
+
+        let samples = apu.take_samples_i16();
+        let peak = samples.iter().map(|sample| sample.unsigned_abs()).max().unwrap();
+        assert!(peak > 5000, "{}", peak);
+        assert!(samples[samples.len() - 1].unsigned_abs() < 100, "{:?}", &samples[samples.len() - 10..]);
+    }
+}
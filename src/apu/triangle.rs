@@ -0,0 +1,79 @@
+use super::units::LengthCounter;
+
+/// 15 down to 0 and back up.
+const TRIANGLE_SEQUENCE: [u8; 32] = [
+    15, 14, 13, 12, 11, 10, 9, 8, 7, 6, 5, 4, 3, 2, 1, 0, //
+    0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15,
+];
+
+/// $4008-$400B. No volume control; instead of an envelope there is a
+/// linear counter, a second, finer grained note length clocked on quarter
+/// frames. The sequencer only steps while both counters are non-zero, and
+/// holds its last level when stopped.
+#[derive(Debug, Default)]
+pub(super) struct Triangle {
+    step: u8,
+    period: u16,
+    timer: u16,
+    pub(super) length: LengthCounter,
+
+    /// Also the length counter halt.
+    control: bool,
+    linear_reload_value: u8,
+    linear_counter: u8,
+    linear_reload: bool,
+}
+
+impl Triangle {
+    /// `register` is 0-3; register 1 isn't connected.
+    pub(super) fn write(&mut self, register: u16, data: u8) {
+        match register {
+            0 => {
+                self.control = data & 0x80 != 0;
+                self.linear_reload_value = data & 0x7F;
+                self.length.set_halted(self.control);
+            }
+            1 => {}
+            2 => self.period = (self.period & 0x0700) | data as u16,
+            _ => {
+                self.period = (self.period & 0x00FF) | ((data & 0x07) as u16) << 8;
+                self.length.load(data);
+                self.linear_reload = true;
+            }
+        }
+    }
+
+    /// Every CPU cycle, twice as often as the pulse timers, which is why
+    /// the same period plays an octave lower.
+    pub(super) fn clock_timer(&mut self) {
+        if self.timer == 0 {
+            self.timer = self.period;
+            if self.linear_counter > 0 && self.length.active() {
+                self.step = (self.step + 1) & 0x1F;
+            }
+        } else {
+            self.timer -= 1;
+        }
+    }
+
+    pub(super) fn clock_quarter_frame(&mut self) {
+        if self.linear_reload {
+            self.linear_counter = self.linear_reload_value;
+        } else if self.linear_counter > 0 {
+            self.linear_counter -= 1;
+        }
+
+        if !self.control {
+            self.linear_reload = false;
+        }
+    }
+
+    pub(super) fn clock_half_frame(&mut self) {
+        self.length.clock();
+    }
+
+    /// 0-15.
+    pub(super) fn output(&self) -> u8 {
+        TRIANGLE_SEQUENCE[self.step as usize]
+    }
+}
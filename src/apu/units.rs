@@ -0,0 +1,98 @@
+/// Note lengths in frame counter half frames, picked by the top five bits
+/// of a channel's last register.
+const LENGTH_TABLE: [u8; 32] = [
+    10, 254, 20, 2, 40, 4, 80, 6, 160, 8, 60, 10, 14, 12, 26, 14, //
+    12, 16, 24, 18, 48, 20, 96, 22, 192, 24, 72, 26, 16, 28, 32, 30,
+];
+
+/// Silences a channel once its note has run out. Clocked on half frames
+/// unless halted; disabling the channel in $4015 zeroes it.
+#[derive(Debug, Default)]
+pub(super) struct LengthCounter {
+    enabled: bool,
+    halted: bool,
+    counter: u8,
+}
+
+impl LengthCounter {
+    pub(super) fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        if !enabled {
+            self.counter = 0;
+        }
+    }
+
+    pub(super) fn set_halted(&mut self, halted: bool) {
+        self.halted = halted;
+    }
+
+    /// Loads from the index in bits 3-7 of `data`, if the channel is on.
+    pub(super) fn load(&mut self, data: u8) {
+        if self.enabled {
+            self.counter = LENGTH_TABLE[(data >> 3) as usize];
+        }
+    }
+
+    pub(super) fn clock(&mut self) {
+        if !self.halted && self.counter > 0 {
+            self.counter -= 1;
+        }
+    }
+
+    pub(super) fn active(&self) -> bool {
+        self.counter > 0
+    }
+}
+
+/// The volume of the pulse and noise channels: either a constant, or a
+/// level decaying from 15 to 0 one step every `period + 1` quarter frames,
+/// optionally starting over.
+#[derive(Debug, Default)]
+pub(super) struct Envelope {
+    start: bool,
+    looping: bool,
+    constant: bool,
+    /// The constant volume, or the decay period.
+    volume: u8,
+    divider: u8,
+    decay: u8,
+}
+
+impl Envelope {
+    /// Takes the --LC VVVV bits of a channel's first register. The loop
+    /// flag doubles as the length counter halt.
+    pub(super) fn write(&mut self, data: u8) {
+        self.looping = data & 0x20 != 0;
+        self.constant = data & 0x10 != 0;
+        self.volume = data & 0x0F;
+    }
+
+    /// Starts the decay over on the next quarter frame.
+    pub(super) fn restart(&mut self) {
+        self.start = true;
+    }
+
+    pub(super) fn clock(&mut self) {
+        if self.start {
+            self.start = false;
+            self.decay = 15;
+            self.divider = self.volume;
+        } else if self.divider > 0 {
+            self.divider -= 1;
+        } else {
+            self.divider = self.volume;
+            if self.decay > 0 {
+                self.decay -= 1;
+            } else if self.looping {
+                self.decay = 15;
+            }
+        }
+    }
+
+    pub(super) fn output(&self) -> u8 {
+        match self.constant {
+            true => self.volume,
+            false => self.decay,
+        }
+    }
+}
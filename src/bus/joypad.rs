@@ -0,0 +1,86 @@
+use std::ops::{BitOr, BitOrAssign};
+
+/// The buttons held on a standard controller, one bit each in the order
+/// the controller shifts them out: A first, Right last.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Buttons(pub u8);
+
+impl Buttons {
+    pub const NONE: Buttons = Buttons(0x00);
+    pub const A: Buttons = Buttons(0x01);
+    pub const B: Buttons = Buttons(0x02);
+    pub const SELECT: Buttons = Buttons(0x04);
+    pub const START: Buttons = Buttons(0x08);
+    pub const UP: Buttons = Buttons(0x10);
+    pub const DOWN: Buttons = Buttons(0x20);
+    pub const LEFT: Buttons = Buttons(0x40);
+    pub const RIGHT: Buttons = Buttons(0x80);
+
+    pub fn contains(self, buttons: Buttons) -> bool {
+        self.0 & buttons.0 == buttons.0
+    }
+}
+
+impl BitOr for Buttons {
+    type Output = Buttons;
+
+    fn bitor(self, rhs: Buttons) -> Buttons {
+        Buttons(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for Buttons {
+    fn bitor_assign(&mut self, rhs: Buttons) {
+        self.0 |= rhs.0;
+    }
+}
+
+/// The two controller ports on the front of the console, read through
+/// $4016 and $4017.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Port {
+    One,
+    Two,
+}
+
+/// A standard controller: a 4021 shift register that keeps loading the
+/// buttons while the strobe is high and shifts one out per read once it
+/// goes low.
+#[derive(Default)]
+pub(super) struct Joypad {
+    pub(super) buttons: Buttons,
+    strobe: bool,
+    shift: u8,
+}
+
+impl Joypad {
+    /// Only loads while the strobe is high, so writing 0 again partway
+    /// through a report doesn't start it over.
+    pub(super) fn write_strobe(&mut self, strobe: bool) {
+        if self.strobe || strobe {
+            self.shift = self.buttons.0;
+        }
+        self.strobe = strobe;
+    }
+
+    /// The next button in bit 0. After all eight the serial input, tied
+    /// high, has filled the register with 1s.
+    pub(super) fn read(&mut self) -> u8 {
+        if self.strobe {
+            self.shift = self.buttons.0;
+        }
+
+        let bit = self.peek();
+        if !self.strobe {
+            self.shift = (self.shift >> 1) | 0x80;
+        }
+        bit
+    }
+
+    pub(super) fn peek(&self) -> u8 {
+        match self.strobe {
+            true => self.buttons.0 & 0x01,
+            false => self.shift & 0x01,
+        }
+    }
+}
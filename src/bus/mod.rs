@@ -1,35 +1,402 @@
-use crate::cpu_6502::*;
+mod joypad;
+mod tests;
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use joypad::Joypad;
+pub use joypad::{Buttons, Port};
+
+/// Anything the CPU can be plugged into: a full NES memory map, a flat test
+/// RAM, a tracing harness...
+///
+/// `read` is a real CPU read and may trigger side effects in the addressed
+/// device (clearing vblank on $2002, acknowledging IRQs on $4015...). A
+/// `readonly` read, like `peek`, must leave every device untouched.
+pub trait BusInterface {
+    fn read(&mut self, addr: u16, readonly: bool) -> u8;
+    fn write(&mut self, addr: u16, data: u8);
+    fn peek(&self, addr: u16) -> u8;
+    /// Called by the CPU at the start of each of its cycles. A DMA unit
+    /// that wants the bus takes the cycle over and returns true, and the
+    /// CPU sits that cycle out. `next_is_read` says whether the CPU's next
+    /// access is a read: it can only be halted on one, and writes go ahead.
+    fn dma_cycle(&mut self, _next_is_read: bool) -> bool {
+        false
+    }
+}
+
+/// A device mapped into one of the `Bus` address ranges. Addresses are handed
+/// over already mirrored, e.g. the PPU only ever sees $2000-$2007.
+pub trait BusDevice {
+    fn read(&mut self, addr: u16) -> u8;
+    fn write(&mut self, addr: u16, data: u8);
+    /// What `read` would return, without any of its side effects.
+    fn peek(&self, addr: u16) -> u8;
+    /// `read`, or `None` if nothing in the device drives the data bus at
+    /// `addr`, which leaves the bus's open-bus value there. This is what
+    /// `Bus` uses.
+    fn try_read(&mut self, addr: u16) -> Option<u8> {
+        Some(self.read(addr))
+    }
+    /// `peek`, or `None` like `try_read`.
+    fn try_peek(&self, addr: u16) -> Option<u8> {
+        Some(self.peek(addr))
+    }
+    /// Called once per CPU cycle by `Bus::clock`.
+    fn clock(&mut self) {}
+    /// Whether the device is asserting the shared IRQ line.
+    fn irq(&self) -> bool {
+        false
+    }
+    /// Whether the device is asserting NMI. Only the PPU does.
+    fn nmi(&self) -> bool {
+        false
+    }
+    /// An address the device wants read over the CPU bus, like the DMC
+    /// fetching its next sample byte. The bus halts the CPU to do it.
+    fn dma_request(&self) -> Option<u16> {
+        None
+    }
+    /// Hands over the byte read for `dma_request`.
+    fn dma_complete(&mut self, _data: u8) {}
+}
+
+/// A device that something else holds on to as well, like the cartridge
+/// the PPU reads its pattern tables from, or a PPU whose frames the caller
+/// wants to look at.
+impl<T: BusDevice> BusDevice for Rc<RefCell<T>> {
+    fn read(&mut self, addr: u16) -> u8 {
+        self.borrow_mut().read(addr)
+    }
+
+    fn write(&mut self, addr: u16, data: u8) {
+        self.borrow_mut().write(addr, data);
+    }
+
+    fn peek(&self, addr: u16) -> u8 {
+        self.borrow().peek(addr)
+    }
+
+    fn try_read(&mut self, addr: u16) -> Option<u8> {
+        self.borrow_mut().try_read(addr)
+    }
+
+    fn try_peek(&self, addr: u16) -> Option<u8> {
+        self.borrow().try_peek(addr)
+    }
+
+    fn clock(&mut self) {
+        self.borrow_mut().clock();
+    }
+
+    fn irq(&self) -> bool {
+        self.borrow().irq()
+    }
+
+    fn nmi(&self) -> bool {
+        self.borrow().nmi()
+    }
+
+    fn dma_request(&self) -> Option<u16> {
+        self.borrow().dma_request()
+    }
+
+    fn dma_complete(&mut self, data: u8) {
+        self.borrow_mut().dma_complete(data);
+    }
+}
+
+pub const RAM_SIZE: usize = 2 * 1024;
+
+const RAM_START: u16 = 0x0000;
+const RAM_END: u16 = 0x1FFF;
+const RAM_MIRROR_MASK: u16 = 0x07FF;
+
+const PPU_START: u16 = 0x2000;
+const PPU_END: u16 = 0x3FFF;
+const PPU_MIRROR_MASK: u16 = 0x0007;
+
+const OAM_DMA: u16 = 0x4014;
+const OAMDATA: u16 = 0x2004;
+const OAM_DMA_TRANSFERS: u16 = 256;
+
+const JOYPAD_1: u16 = 0x4016;
+const JOYPAD_2: u16 = 0x4017;
+const JOYPAD_STROBE: u8 = 0x01;
+/// The controller ports only drive the low bits of a read.
+const JOYPAD_OPEN_BUS_MASK: u8 = 0xE0;
+
+const APU_IO_START: u16 = 0x4000;
+const APU_IO_END: u16 = 0x401F;
+
+const CARTRIDGE_START: u16 = 0x4020;
+const CARTRIDGE_END: u16 = 0xFFFF;
+
+/// A sprite DMA started by writing the source page to $4014. It halts the
+/// CPU for one cycle once the CPU gets to a read, waits one more if that
+/// leaves it on a write cycle, then alternates reading a byte of the page
+/// and writing it to $2004.
+struct OamDma {
+    page: u8,
+    halted: bool,
+    /// Reads and writes done so far, 512 in all.
+    transfers: u16,
+    data: u8,
+}
+
+/// A DMC sample fetch: a halt cycle, a dummy cycle, then the read on the
+/// next read cycle. During a sprite DMA the CPU is already halted, so it
+/// goes straight to waiting for a read cycle. The halt and dummy cycles
+/// run on regardless while the CPU is writing, but the CPU only stops at
+/// its next read, so a fetch costs it anywhere from 1 to 4 cycles.
+struct DmcDma {
+    addr: u16,
+    cycles: u8,
+}
 
 pub struct Bus {
-    pub ram: [u8; 64 * 1024],
-    pub cpu: *mut Cpu,
+    pub ram: [u8; RAM_SIZE],
+    ppu: Option<Box<dyn BusDevice>>,
+    apu_io: Option<Box<dyn BusDevice>>,
+    cartridge: Option<Box<dyn BusDevice>>,
+    joypads: [Joypad; 2],
+    open_bus: u8,
+    oam_dma: Option<OamDma>,
+    dmc_dma: Option<DmcDma>,
+    /// Set when a DMC fetch halted the CPU; see `BusInterface::read`.
+    dmc_halted_read: bool,
+    /// CPU cycles so far; even ones are read cycles for DMA.
+    cycle: u64,
 }
 
 impl Bus {
-    pub fn new(cpu: &mut Cpu) -> Self {
+    pub fn new() -> Self {
         Bus {
-            cpu: cpu,
-            ram: [0x00; 64 * 1024],
+            ram: [0x00; RAM_SIZE],
+            ppu: None,
+            apu_io: None,
+            cartridge: None,
+            joypads: Default::default(),
+            open_bus: 0x00,
+            oam_dma: None,
+            dmc_dma: None,
+            dmc_halted_read: false,
+            cycle: 0,
+        }
+    }
+
+    pub fn connect_ppu(&mut self, ppu: Box<dyn BusDevice>) {
+        self.ppu = Some(ppu);
+    }
+
+    pub fn connect_apu_io(&mut self, apu_io: Box<dyn BusDevice>) {
+        self.apu_io = Some(apu_io);
+    }
+
+    pub fn connect_cartridge(&mut self, cartridge: Box<dyn BusDevice>) {
+        self.cartridge = Some(cartridge);
+    }
+
+    pub fn disconnect_cartridge(&mut self) -> Option<Box<dyn BusDevice>> {
+        self.cartridge.take()
+    }
+
+    /// The buttons held on the controller in `port`, as of its next
+    /// strobe. Set them once per frame, before running it.
+    pub fn set_buttons(&mut self, port: Port, buttons: Buttons) {
+        self.joypads[port as usize].buttons = buttons;
+    }
+
+    pub fn buttons(&self, port: Port) -> Buttons {
+        self.joypads[port as usize].buttons
+    }
+
+    /// Drops any sprite DMA or DMC fetch in progress, as a reset does.
+    pub fn cancel_dma(&mut self) {
+        self.oam_dma = None;
+        self.dmc_dma = None;
+        self.dmc_halted_read = false;
+    }
+
+    /// Advances every connected device by one CPU cycle. Call it after each
+    /// `Cpu::clock`.
+    pub fn clock(&mut self) {
+        self.cycle += 1;
+
+        for device in [&mut self.ppu, &mut self.apu_io, &mut self.cartridge].into_iter().flatten() {
+            device.clock();
+        }
+    }
+
+    /// The IRQ line is wired-OR: any device can hold it asserted.
+    pub fn irq(&self) -> bool {
+        [&self.ppu, &self.apu_io, &self.cartridge].into_iter().flatten().any(|device| device.irq())
+    }
+
+    /// Feed this to `Cpu::set_nmi_line`; the CPU does the edge detection.
+    pub fn nmi(&self) -> bool {
+        [&self.ppu, &self.apu_io, &self.cartridge].into_iter().flatten().any(|device| device.nmi())
+    }
+}
+
+//private
+impl Bus {
+    fn read_device(&mut self, addr: u16) -> u8 {
+        // Nothing driving the data bus leaves the last value on it.
+        let data = match addr {
+            RAM_START..=RAM_END => Some(self.ram[(addr & RAM_MIRROR_MASK) as usize]),
+            PPU_START..=PPU_END => self.ppu.as_mut().and_then(|ppu| ppu.try_read(PPU_START | (addr & PPU_MIRROR_MASK))),
+            JOYPAD_1 | JOYPAD_2 => Some((self.open_bus & JOYPAD_OPEN_BUS_MASK) | self.joypads[(addr - JOYPAD_1) as usize].read()),
+            APU_IO_START..=APU_IO_END => self.apu_io.as_mut().and_then(|apu_io| apu_io.try_read(addr)),
+            CARTRIDGE_START..=CARTRIDGE_END => self.cartridge.as_mut().and_then(|cartridge| cartridge.try_read(addr)),
+        };
+
+        self.open_bus = data.unwrap_or(self.open_bus);
+        self.open_bus
+    }
+
+    fn oam_dma_halted(&self) -> bool {
+        self.oam_dma.as_ref().is_some_and(|dma| dma.halted)
+    }
+
+    /// Takes the cycle for a DMC fetch if one is due. It has priority over
+    /// a sprite DMA, which just waits.
+    fn dmc_dma_cycle(&mut self, next_is_read: bool) -> bool {
+        // During a sprite DMA the CPU is halted already.
+        let oam_dma_halted = self.oam_dma_halted();
+
+        // A fetch still waiting on a read cycle is dropped if the DMC stops
+        // asking for it, e.g. when $4015 disables it in the meantime.
+        let request = self.apu_io.as_ref().and_then(|apu_io| apu_io.dma_request());
+        match request {
+            None => self.dmc_dma = None,
+            Some(addr) if self.dmc_dma.is_none() => {
+                let cycles = if oam_dma_halted { 2 } else { 0 };
+                self.dmc_dma = Some(DmcDma { addr, cycles });
+            }
+            Some(_) => {}
+        }
+        let Some(dma) = self.dmc_dma.as_mut() else {
+            return false;
+        };
+
+        if !next_is_read && !oam_dma_halted {
+            dma.cycles = (dma.cycles + 1).min(2);
+            return false;
+        }
+
+        dma.cycles += 1;
+        if dma.cycles > 2 && self.cycle.is_multiple_of(2) {
+            let addr = dma.addr;
+            self.dmc_dma = None;
+            self.dmc_halted_read = !oam_dma_halted;
+
+            let data = self.read_device(addr);
+            if let Some(apu_io) = self.apu_io.as_mut() {
+                apu_io.dma_complete(data);
+            }
         }
+        true
     }
+}
 
-    // pub fn connect_to_cpu(&mut self, cpu_ptr: *mut Cpu) {
-    //     self.cpu = cpu_ptr;
-    // }
+impl Default for Bus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
-    #[allow(unused_comparisons)]
-    pub fn read(&self, addr: u16, readonly: bool) -> u8 {
-        if addr >= 0x0000 && addr <= 0xFFFF {
-            return self.ram[addr as usize];
+impl BusInterface for Bus {
+    /// A CPU read held up by a DMC fetch has really been happening all
+    /// along: the halted CPU keeps driving the address on every stalled
+    /// cycle. Back to back reads merge into one, but the DMC's own read
+    /// breaks them up, so registers with read side effects ($2007, the
+    /// controller ports) see one read too many.
+    fn read(&mut self, addr: u16, readonly: bool) -> u8 {
+        if readonly {
+            return self.peek(addr);
         }
 
-        0x00
+        if self.dmc_halted_read {
+            self.dmc_halted_read = false;
+            self.read_device(addr);
+        }
+        self.read_device(addr)
     }
 
-    #[allow(unused_comparisons)]
-    pub fn write(&mut self, addr: u16, data: u8) {
-        if addr >= 0x0000 && addr <= 0xFFFF {
-            self.ram[addr as usize] = data;
+    fn write(&mut self, addr: u16, data: u8) {
+        self.open_bus = data;
+
+        match addr {
+            RAM_START..=RAM_END => self.ram[(addr & RAM_MIRROR_MASK) as usize] = data,
+            PPU_START..=PPU_END => {
+                if let Some(ppu) = self.ppu.as_mut() {
+                    ppu.write(PPU_START | (addr & PPU_MIRROR_MASK), data);
+                }
+            }
+            OAM_DMA => {
+                self.oam_dma = Some(OamDma { page: data, halted: false, transfers: 0, data: 0x00 });
+            }
+            // One strobe line goes out to both ports.
+            JOYPAD_1 => {
+                for joypad in &mut self.joypads {
+                    joypad.write_strobe(data & JOYPAD_STROBE != 0);
+                }
+            }
+            APU_IO_START..=APU_IO_END => {
+                if let Some(apu_io) = self.apu_io.as_mut() {
+                    apu_io.write(addr, data);
+                }
+            }
+            CARTRIDGE_START..=CARTRIDGE_END => {
+                if let Some(cartridge) = self.cartridge.as_mut() {
+                    cartridge.write(addr, data);
+                }
+            }
+        }
+    }
+
+    fn peek(&self, addr: u16) -> u8 {
+        let data = match addr {
+            RAM_START..=RAM_END => Some(self.ram[(addr & RAM_MIRROR_MASK) as usize]),
+            PPU_START..=PPU_END => self.ppu.as_ref().and_then(|ppu| ppu.try_peek(PPU_START | (addr & PPU_MIRROR_MASK))),
+            JOYPAD_1 | JOYPAD_2 => Some((self.open_bus & JOYPAD_OPEN_BUS_MASK) | self.joypads[(addr - JOYPAD_1) as usize].peek()),
+            APU_IO_START..=APU_IO_END => self.apu_io.as_ref().and_then(|apu_io| apu_io.try_peek(addr)),
+            CARTRIDGE_START..=CARTRIDGE_END => self.cartridge.as_ref().and_then(|cartridge| cartridge.try_peek(addr)),
+        };
+
+        data.unwrap_or(self.open_bus)
+    }
+
+    fn dma_cycle(&mut self, next_is_read: bool) -> bool {
+        if self.dmc_dma_cycle(next_is_read) {
+            return true;
+        }
+
+        let Some(mut dma) = self.oam_dma.take() else {
+            return false;
+        };
+        let read_cycle = self.cycle.is_multiple_of(2);
+
+        if !dma.halted && !next_is_read {
+            self.oam_dma = Some(dma);
+            return false;
+        }
+
+        if !dma.halted {
+            dma.halted = true;
+        } else if dma.transfers % 2 == 1 {
+            self.write(OAMDATA, dma.data);
+            dma.transfers += 1;
+        } else if read_cycle {
+            dma.data = self.read_device(((dma.page as u16) << 8) | (dma.transfers / 2));
+            dma.transfers += 1;
+        }
+
+        if dma.transfers < 2 * OAM_DMA_TRANSFERS {
+            self.oam_dma = Some(dma);
         }
+        true
     }
 }
@@ -0,0 +1,568 @@
+#[cfg(test)]
+mod bus_tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use crate::apu::Apu;
+    use crate::bus::*;
+    use crate::cpu_6502::assembler::assemble;
+    use crate::cpu_6502::{Cpu, ExecutionMode};
+
+    type AccessLog = Rc<RefCell<Vec<(u16, Option<u8>)>>>;
+
+    /// Echoes the low address byte back and logs every access.
+    struct ProbeDevice {
+        log: AccessLog,
+    }
+
+    impl BusDevice for ProbeDevice {
+        fn read(&mut self, addr: u16) -> u8 {
+            self.log.borrow_mut().push((addr, None));
+            addr as u8
+        }
+
+        fn write(&mut self, addr: u16, data: u8) {
+            self.log.borrow_mut().push((addr, Some(data)));
+        }
+
+        fn peek(&self, addr: u16) -> u8 {
+            addr as u8
+        }
+    }
+
+    /// A status register that, like $2002, clears its flag when read.
+    struct ReadClearDevice {
+        flag: u8,
+    }
+
+    impl BusDevice for ReadClearDevice {
+        fn read(&mut self, _addr: u16) -> u8 {
+            let data = self.flag;
+            self.flag = 0x00;
+            data
+        }
+
+        fn write(&mut self, _addr: u16, data: u8) {
+            self.flag = data;
+        }
+
+        fn peek(&self, _addr: u16) -> u8 {
+            self.flag
+        }
+    }
+
+    /// Asks for a DMA of `request` and keeps what it gets, like the DMC.
+    #[derive(Default)]
+    struct DmaDevice {
+        request: Option<u16>,
+        received: Vec<u8>,
+    }
+
+    impl BusDevice for DmaDevice {
+        fn read(&mut self, _addr: u16) -> u8 {
+            0x00
+        }
+
+        fn write(&mut self, _addr: u16, _data: u8) {}
+
+        fn peek(&self, _addr: u16) -> u8 {
+            0x00
+        }
+
+        fn dma_request(&self) -> Option<u16> {
+            self.request
+        }
+
+        fn dma_complete(&mut self, data: u8) {
+            self.received.push(data);
+            self.request = None;
+        }
+    }
+
+    fn dma_device(bus: &mut Bus) -> Rc<RefCell<DmaDevice>> {
+        let device = Rc::new(RefCell::new(DmaDevice::default()));
+        bus.connect_apu_io(Box::new(device.clone()));
+        device
+    }
+
+    fn probe() -> (Box<dyn BusDevice>, AccessLog) {
+        let log = AccessLog::default();
+        (Box::new(ProbeDevice { log: log.clone() }), log)
+    }
+
+    #[test]
+    fn internal_ram_is_mirrored_every_2k() {
+        let mut bus = Bus::new();
+        bus.write(0x0012, 0xAB);
+
+        assert_eq!(bus.read(0x0812, false), 0xAB);
+        assert_eq!(bus.read(0x1012, false), 0xAB);
+        assert_eq!(bus.read(0x1812, false), 0xAB);
+
+        bus.write(0x1FFF, 0xCD);
+        assert_eq!(bus.ram[0x07FF], 0xCD);
+    }
+
+    #[test]
+    fn ppu_registers_are_mirrored_every_8_bytes() {
+        let mut bus = Bus::new();
+        let (ppu, log) = probe();
+        bus.connect_ppu(ppu);
+
+        assert_eq!(bus.read(0x2002, false), 0x02);
+        assert_eq!(bus.read(0x3FFA, false), 0x02);
+        bus.write(0x2F0E, 0x55);
+
+        assert_eq!(*log.borrow(), vec![(0x2002, None), (0x2002, None), (0x2006, Some(0x55))]);
+    }
+
+    #[test]
+    fn apu_io_and_cartridge_ranges_are_decoded() {
+        let mut bus = Bus::new();
+        let (apu_io, apu_log) = probe();
+        let (cartridge, cart_log) = probe();
+        bus.connect_apu_io(apu_io);
+        bus.connect_cartridge(cartridge);
+
+        bus.write(0x4015, 0x0F);
+        bus.read(0x401F, false);
+        bus.read(0x4020, false);
+        bus.write(0xFFFF, 0x01);
+
+        assert_eq!(*apu_log.borrow(), vec![(0x4015, Some(0x0F)), (0x401F, None)]);
+        assert_eq!(*cart_log.borrow(), vec![(0x4020, None), (0xFFFF, Some(0x01))]);
+    }
+
+    #[test]
+    fn unmapped_reads_return_open_bus() {
+        let mut bus = Bus::new();
+        bus.write(0x0000, 0x3C);
+
+        assert_eq!(bus.read(0x0000, false), 0x3C);
+        assert_eq!(bus.read(0x8000, false), 0x3C);
+        assert_eq!(bus.read(0x2002, false), 0x3C);
+    }
+
+    #[test]
+    fn peek_and_readonly_reads_have_no_side_effects() {
+        let mut bus = Bus::new();
+        bus.connect_ppu(Box::new(ReadClearDevice { flag: 0x80 }));
+        let (cartridge, cart_log) = probe();
+        bus.connect_cartridge(cartridge);
+
+        assert_eq!(bus.peek(0x2002), 0x80);
+        assert_eq!(bus.read(0x2002, true), 0x80);
+        assert_eq!(bus.peek(0x8000), 0x00);
+        assert!(cart_log.borrow().is_empty());
+
+        assert_eq!(bus.read(0x2002, false), 0x80);
+        assert_eq!(bus.peek(0x2002), 0x00);
+    }
+
+    #[test]
+    fn peek_does_not_disturb_open_bus() {
+        let mut bus = Bus::new();
+        bus.write(0x0000, 0x3C);
+        bus.ram[0x0001] = 0x99;
+
+        assert_eq!(bus.peek(0x0001), 0x99);
+        assert_eq!(bus.read(0x8000, false), 0x3C);
+    }
+
+    struct ClockCounter {
+        clocks: Rc<RefCell<usize>>,
+    }
+
+    impl BusDevice for ClockCounter {
+        fn read(&mut self, _addr: u16) -> u8 {
+            0x00
+        }
+
+        fn write(&mut self, _addr: u16, _data: u8) {}
+
+        fn peek(&self, _addr: u16) -> u8 {
+            0x00
+        }
+
+        fn clock(&mut self) {
+            *self.clocks.borrow_mut() += 1;
+        }
+    }
+
+    #[test]
+    fn clock_reaches_every_device() {
+        let clocks = Rc::new(RefCell::new(0));
+        let mut bus = Bus::new();
+        bus.connect_ppu(Box::new(ClockCounter { clocks: clocks.clone() }));
+        bus.connect_cartridge(Box::new(ClockCounter { clocks: clocks.clone() }));
+
+        bus.clock();
+        bus.clock();
+
+        assert_eq!(*clocks.borrow(), 4);
+    }
+
+    #[test]
+    fn shared_devices_stay_reachable_from_outside() {
+        let device = Rc::new(RefCell::new(ReadClearDevice { flag: 0x80 }));
+        let mut bus = Bus::new();
+        bus.connect_ppu(Box::new(device.clone()));
+
+        assert_eq!(bus.read(0x2002, false), 0x80);
+        assert_eq!(device.borrow().flag, 0x00);
+
+        device.borrow_mut().flag = 0x40;
+        assert_eq!(bus.peek(0x2002), 0x40);
+    }
+
+    /// Runs the DMA to the end, returning how many cycles it took.
+    fn run_dma(bus: &mut Bus) -> usize {
+        let mut cycles = 0;
+        while bus.dma_cycle(true) {
+            bus.clock();
+            cycles += 1;
+        }
+        cycles
+    }
+
+    #[test]
+    fn oam_dma_copies_a_page_to_2004() {
+        let mut bus = Bus::new();
+        let (ppu, log) = probe();
+        bus.connect_ppu(ppu);
+        for (i, byte) in bus.ram[0x0200..0x0300].iter_mut().enumerate() {
+            *byte = i as u8;
+        }
+
+        assert!(!bus.dma_cycle(true));
+        bus.write(0x4014, 0x02);
+        assert_eq!(run_dma(&mut bus), 514);
+
+        let expected: Vec<_> = (0..=255).map(|data| (0x2004, Some(data))).collect();
+        assert_eq!(*log.borrow(), expected);
+
+        // Halted on a write cycle, so no alignment cycle is needed
+        bus.clock();
+        bus.write(0x4014, 0x02);
+        assert_eq!(run_dma(&mut bus), 513);
+    }
+
+    #[test]
+    fn oam_dma_waits_for_a_read_cycle_to_halt() {
+        let mut bus = Bus::new();
+        let (ppu, log) = probe();
+        bus.connect_ppu(ppu);
+
+        bus.write(0x4014, 0x02);
+        for _ in 0..2 {
+            assert!(!bus.dma_cycle(false));
+            bus.clock();
+        }
+        assert!(log.borrow().is_empty());
+
+        // Same parity as starting right away.
+        assert_eq!(run_dma(&mut bus), 514);
+        assert_eq!(log.borrow().len(), 256);
+    }
+
+    #[test]
+    fn oam_dma_stalls_the_cpu() {
+        for mode in [ExecutionMode::Instruction, ExecutionMode::Cycle] {
+            let mut bus = Bus::new();
+            bus.connect_ppu(probe().0);
+            let program = assemble("lda #$02\nsta $4014\nnop", 0x0300).unwrap();
+            bus.ram[0x0300..0x0300 + program.len()].copy_from_slice(&program);
+
+            let mut cpu = Cpu::new();
+            cpu.set_execution_mode(mode);
+            cpu.reset(&mut bus);
+            cpu.set_pc(0x0300);
+
+            while cpu.state().pc != 0x0306 || !cpu.is_complete() {
+                cpu.clock(&mut bus);
+                bus.clock();
+            }
+
+            // Reset, LDA, STA, the stall, NOP
+            assert_eq!(cpu.state().cycles, 7 + 2 + 4 + 513 + 2, "{:?}", mode);
+        }
+    }
+
+    #[test]
+    fn dmc_dma_reads_through_the_bus_in_three_or_four_cycles() {
+        let mut bus = Bus::new();
+        let (cartridge, log) = probe();
+        bus.connect_cartridge(cartridge);
+        let device = dma_device(&mut bus);
+
+        device.borrow_mut().request = Some(0xC123);
+        assert_eq!(run_dma(&mut bus), 3);
+        // Now starting on an odd cycle, so one more to line up the read.
+        device.borrow_mut().request = Some(0xC124);
+        assert_eq!(run_dma(&mut bus), 4);
+
+        assert_eq!(device.borrow().received, vec![0x23, 0x24]);
+        assert_eq!(*log.borrow(), vec![(0xC123, None), (0xC124, None)]);
+        assert_eq!(bus.peek(0x4020), 0x20);
+    }
+
+    #[test]
+    fn dmc_dma_during_oam_dma_costs_two_cycles() {
+        let mut bus = Bus::new();
+        bus.connect_ppu(probe().0);
+        let device = dma_device(&mut bus);
+
+        bus.write(0x4014, 0x02);
+        for _ in 0..10 {
+            bus.dma_cycle(true);
+            bus.clock();
+        }
+        device.borrow_mut().request = Some(0x0000);
+
+        assert_eq!(10 + run_dma(&mut bus), 514 + 2);
+        assert_eq!(device.borrow().received.len(), 1);
+    }
+
+    #[test]
+    fn dmc_dma_repeats_the_read_it_halted() {
+        let mut bus = Bus::new();
+        let (ppu, log) = probe();
+        bus.connect_ppu(ppu);
+        let device = dma_device(&mut bus);
+        let program = assemble("lda $2007\nnop", 0x0300).unwrap();
+        bus.ram[0x0300..0x0300 + program.len()].copy_from_slice(&program);
+
+        let mut cpu = Cpu::new();
+        cpu.set_execution_mode(ExecutionMode::Cycle);
+        cpu.reset(&mut bus);
+        cpu.set_pc(0x0300);
+        while !cpu.is_complete() {
+            cpu.clock(&mut bus);
+            bus.clock();
+        }
+
+        // Opcode and operand fetched; the fetch lands on the $2007 read.
+        for _ in 0..3 {
+            cpu.clock(&mut bus);
+            bus.clock();
+        }
+        device.borrow_mut().request = Some(0x0000);
+        while !cpu.is_complete() {
+            cpu.clock(&mut bus);
+            bus.clock();
+        }
+
+        assert_eq!(*log.borrow(), vec![(0x2007, None), (0x2007, None)]);
+        assert_eq!(cpu.state().a, 0x07);
+    }
+
+    #[test]
+    fn disabling_the_dmc_cancels_a_fetch_still_waiting_for_a_read() {
+        let mut bus = Bus::new();
+        bus.connect_apu_io(Box::new(Apu::new()));
+
+        // A one byte sample, whose fetch comes up on a CPU write cycle.
+        bus.write(0x4013, 0x00);
+        bus.write(0x4015, 0x10);
+        assert!(!bus.dma_cycle(false));
+        bus.write(0x4015, 0x00);
+        bus.clock();
+
+        let mut cycles = 0;
+        while bus.dma_cycle(true) && cycles < 10 {
+            bus.clock();
+            cycles += 1;
+        }
+
+        assert_eq!(cycles, 0);
+        assert_eq!(bus.peek(0x4015) & 0x10, 0x00);
+    }
+
+    /// A cycle mode CPU about to run `source` from $0300.
+    fn cpu_running(bus: &mut Bus, source: &str) -> Cpu {
+        let program = assemble(source, 0x0300).unwrap();
+        bus.ram[0x0300..0x0300 + program.len()].copy_from_slice(&program);
+
+        let mut cpu = Cpu::new();
+        cpu.set_execution_mode(ExecutionMode::Cycle);
+        cpu.reset(bus);
+        cpu.set_pc(0x0300);
+        while !cpu.is_complete() {
+            cpu.clock(bus);
+            bus.clock();
+        }
+        cpu
+    }
+
+    #[test]
+    fn dmc_dma_on_write_cycles_lets_the_writes_through() {
+        // INC zp: opcode, operand, read, write, write. The halt waits for
+        // the next read, the NOP's opcode fetch, but the halt and dummy
+        // cycles go by during the writes.
+        let mut stalls = vec![];
+        for request_at in [2, 3, 4] {
+            let mut bus = Bus::new();
+            let device = dma_device(&mut bus);
+            let mut cpu = cpu_running(&mut bus, "inc $10\nnop");
+            let start = cpu.state().cycles;
+
+            for _ in 0..request_at {
+                cpu.clock(&mut bus);
+                bus.clock();
+            }
+            device.borrow_mut().request = Some(0x0000);
+            while cpu.state().pc != 0x0303 || !cpu.is_complete() {
+                cpu.clock(&mut bus);
+                bus.clock();
+            }
+
+            assert_eq!(bus.ram[0x10], 0x01);
+            assert_eq!(device.borrow().received.len(), 1);
+            stalls.push(cpu.state().cycles - start - 5 - 2);
+        }
+
+        // On the read: halt, dummy, alignment, read. On the first write:
+        // just the read. On the second: the dummy, alignment, read.
+        assert_eq!(stalls, vec![4, 1, 3]);
+    }
+
+    #[test]
+    fn dmc_dma_does_not_halt_a_write() {
+        let mut bus = Bus::new();
+        let (ppu, log) = probe();
+        bus.connect_ppu(ppu);
+        let device = dma_device(&mut bus);
+        let mut cpu = cpu_running(&mut bus, "sta $2000\nlda $2007");
+
+        // Opcode and both operand bytes; the fetch lands on the write.
+        for _ in 0..3 {
+            cpu.clock(&mut bus);
+            bus.clock();
+        }
+        device.borrow_mut().request = Some(0x0000);
+        cpu.clock(&mut bus);
+        bus.clock();
+
+        assert_eq!(*log.borrow(), vec![(0x2000, Some(0x00))]);
+        assert!(device.borrow().received.is_empty());
+        assert!(!bus.dmc_halted_read);
+
+        while cpu.state().pc != 0x0306 || !cpu.is_complete() {
+            cpu.clock(&mut bus);
+            bus.clock();
+        }
+        // The halted read was LDA's opcode fetch, so $2007 is read once.
+        assert_eq!(*log.borrow(), vec![(0x2000, Some(0x00)), (0x2007, None)]);
+        assert_eq!(device.borrow().received.len(), 1);
+    }
+
+    fn read_joypad(bus: &mut Bus, addr: u16) -> Vec<u8> {
+        (0..10).map(|_| bus.read(addr, false) & 0x01).collect()
+    }
+
+    #[test]
+    fn joypad_shifts_out_eight_buttons_then_ones() {
+        let mut bus = Bus::new();
+        bus.set_buttons(Port::One, Buttons::A | Buttons::START | Buttons::RIGHT);
+        bus.set_buttons(Port::Two, Buttons::B | Buttons::UP);
+
+        bus.write(0x4016, 0x01);
+        bus.write(0x4016, 0x00);
+
+        assert_eq!(read_joypad(&mut bus, 0x4016), vec![1, 0, 0, 1, 0, 0, 0, 1, 1, 1]);
+        assert_eq!(read_joypad(&mut bus, 0x4017), vec![0, 1, 0, 0, 1, 0, 0, 0, 1, 1]);
+    }
+
+    #[test]
+    fn joypad_keeps_reloading_while_strobed() {
+        let mut bus = Bus::new();
+        bus.set_buttons(Port::One, Buttons::A);
+        bus.write(0x4016, 0x01);
+
+        assert_eq!(read_joypad(&mut bus, 0x4016), vec![1; 10]);
+        bus.set_buttons(Port::One, Buttons::B);
+        assert_eq!(bus.read(0x4016, false) & 0x01, 0);
+
+        // Buttons changed after the strobe wait for the next one.
+        bus.write(0x4016, 0x00);
+        bus.set_buttons(Port::One, Buttons::A);
+        assert_eq!(read_joypad(&mut bus, 0x4016)[..2], [0, 1]);
+    }
+
+    #[test]
+    fn writing_0_with_the_strobe_low_does_not_restart_the_report() {
+        let mut bus = Bus::new();
+        bus.set_buttons(Port::One, Buttons::A | Buttons::B);
+        bus.write(0x4016, 0x01);
+        bus.write(0x4016, 0x00);
+
+        assert_eq!(bus.read(0x4016, false) & 0x01, 1);
+        bus.write(0x4016, 0x00);
+        assert_eq!(read_joypad(&mut bus, 0x4016)[..3], [1, 0, 0]);
+    }
+
+    #[test]
+    fn joypad_reads_leave_the_upper_bits_to_open_bus() {
+        let mut bus = Bus::new();
+        let (apu_io, apu_log) = probe();
+        bus.connect_apu_io(apu_io);
+        bus.set_buttons(Port::One, Buttons::A);
+        bus.write(0x4016, 0x01);
+
+        bus.write(0x0000, 0x5A);
+        assert_eq!(bus.peek(0x4016), 0x41);
+        assert_eq!(bus.read(0x4016, false), 0x41);
+        bus.write(0x0000, 0xBF);
+        assert_eq!(bus.read(0x4017, false), 0xA0);
+
+        // Only the $4017 writes are the frame counter's.
+        bus.write(0x4017, 0x40);
+        assert_eq!(*apu_log.borrow(), vec![(0x4017, Some(0x40))]);
+    }
+
+    #[test]
+    fn peek_does_not_shift_the_joypad() {
+        let mut bus = Bus::new();
+        bus.set_buttons(Port::One, Buttons::A);
+        bus.write(0x4016, 0x01);
+        bus.write(0x4016, 0x00);
+
+        assert_eq!(bus.peek(0x4016) & 0x01, 1);
+        assert_eq!(bus.read(0x4016, true) & 0x01, 1);
+        assert_eq!(read_joypad(&mut bus, 0x4016)[..2], [1, 0]);
+    }
+
+    #[test]
+    fn dmc_dma_makes_joypad_reads_skip_a_button() {
+        let mut bus = Bus::new();
+        let device = dma_device(&mut bus);
+        bus.set_buttons(Port::One, Buttons::B);
+        bus.write(0x4016, 0x01);
+        bus.write(0x4016, 0x00);
+        let program = assemble("lda $4016\nnop", 0x0300).unwrap();
+        bus.ram[0x0300..0x0300 + program.len()].copy_from_slice(&program);
+
+        let mut cpu = Cpu::new();
+        cpu.set_execution_mode(ExecutionMode::Cycle);
+        cpu.reset(&mut bus);
+        cpu.set_pc(0x0300);
+        while !cpu.is_complete() {
+            cpu.clock(&mut bus);
+            bus.clock();
+        }
+
+        for _ in 0..3 {
+            cpu.clock(&mut bus);
+            bus.clock();
+        }
+        device.borrow_mut().request = Some(0x0000);
+        while !cpu.is_complete() {
+            cpu.clock(&mut bus);
+            bus.clock();
+        }
+
+        // A went by unseen; B was read in its place.
+        assert_eq!(cpu.state().a & 0x01, 1);
+        assert_eq!(read_joypad(&mut bus, 0x4016)[..2], [0, 0]);
+    }
+}
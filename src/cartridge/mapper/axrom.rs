@@ -0,0 +1,59 @@
+use super::*;
+use crate::cartridge::PRG_ROM_START;
+
+const PRG_BANK_SIZE: usize = 32 * 1024;
+const CHR_BANK_SIZE: usize = 8 * 1024;
+
+const PRG_BANK_MASK: u8 = 0x07;
+const NAMETABLE_SELECT: u8 = 0x10;
+
+/// Mapper 7: writes to $8000-$FFFF select a 32KB PRG bank (bits 0-2) and
+/// which nametable fills the whole screen (bit 4). CHR is 8KB of RAM.
+pub struct Axrom {
+    prg_bank: u8,
+    mirroring: Mirroring,
+}
+
+impl Axrom {
+    pub fn new() -> Self {
+        Axrom { prg_bank: 0, mirroring: Mirroring::SingleScreenLower }
+    }
+}
+
+impl Default for Axrom {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Mapper for Axrom {
+    fn cpu_write(&mut self, _memory: &mut CartridgeMemory, addr: u16, data: u8) {
+        if addr >= PRG_ROM_START {
+            self.prg_bank = data & PRG_BANK_MASK;
+            self.mirroring = if data & NAMETABLE_SELECT != 0 {
+                Mirroring::SingleScreenUpper
+            } else {
+                Mirroring::SingleScreenLower
+            };
+        }
+    }
+
+    fn cpu_peek(&self, memory: &CartridgeMemory, addr: u16) -> Option<u8> {
+        match addr {
+            PRG_ROM_START..=0xFFFF => Some(memory.read_prg_rom(self.prg_bank as usize, PRG_BANK_SIZE, addr)),
+            _ => None,
+        }
+    }
+
+    fn ppu_write(&mut self, memory: &mut CartridgeMemory, addr: u16, data: u8) {
+        memory.write_chr(0, CHR_BANK_SIZE, addr, data);
+    }
+
+    fn ppu_peek(&self, memory: &CartridgeMemory, addr: u16) -> u8 {
+        memory.read_chr(0, CHR_BANK_SIZE, addr)
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+}
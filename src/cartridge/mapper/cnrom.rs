@@ -0,0 +1,48 @@
+use super::*;
+use crate::cartridge::{PRG_RAM_END, PRG_RAM_START, PRG_ROM_START};
+
+const PRG_BANK_SIZE: usize = 32 * 1024;
+const CHR_BANK_SIZE: usize = 8 * 1024;
+
+/// Mapper 3: PRG ROM laid out like NROM, and any write to $8000-$FFFF
+/// selects the 8KB CHR bank.
+pub struct Cnrom {
+    mirroring: Mirroring,
+    chr_bank: u8,
+}
+
+impl Cnrom {
+    pub fn new(mirroring: Mirroring) -> Self {
+        Cnrom { mirroring, chr_bank: 0 }
+    }
+}
+
+impl Mapper for Cnrom {
+    fn cpu_write(&mut self, memory: &mut CartridgeMemory, addr: u16, data: u8) {
+        match addr {
+            PRG_RAM_START..=PRG_RAM_END => memory.write_prg_ram(0, addr, data),
+            PRG_ROM_START..=0xFFFF => self.chr_bank = data,
+            _ => {}
+        }
+    }
+
+    fn cpu_peek(&self, memory: &CartridgeMemory, addr: u16) -> Option<u8> {
+        match addr {
+            PRG_RAM_START..=PRG_RAM_END => Some(memory.read_prg_ram(0, addr)),
+            PRG_ROM_START..=0xFFFF => Some(memory.read_prg_rom(0, PRG_BANK_SIZE, addr)),
+            _ => None,
+        }
+    }
+
+    fn ppu_write(&mut self, memory: &mut CartridgeMemory, addr: u16, data: u8) {
+        memory.write_chr(self.chr_bank as usize, CHR_BANK_SIZE, addr, data);
+    }
+
+    fn ppu_peek(&self, memory: &CartridgeMemory, addr: u16) -> u8 {
+        memory.read_chr(self.chr_bank as usize, CHR_BANK_SIZE, addr)
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+}
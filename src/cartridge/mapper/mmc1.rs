@@ -0,0 +1,199 @@
+use super::*;
+use crate::cartridge::{PRG_RAM_END, PRG_RAM_START, PRG_ROM_START};
+
+const PRG_BANK_SIZE: usize = 16 * 1024;
+const CHR_BANK_SIZE: usize = 4 * 1024;
+
+const SHIFT_RESET: u8 = 0x80;
+const SHIFT_WRITES: u8 = 5;
+
+const CONTROL_POWER_ON: u8 = 0x0C;
+const CONTROL_MIRRORING: u8 = 0x03;
+const CONTROL_PRG_MODE: u8 = 0x0C;
+const CONTROL_CHR_4K: u8 = 0x10;
+
+const PRG_BANK_MASK: u8 = 0x0F;
+const PRG_RAM_DISABLE: u8 = 0x10;
+
+/// 512KB boards (SUROM, SXROM) use bit 4 of the CHR bank register to pick
+/// the 256KB half of PRG ROM.
+const PRG_OUTER_BANK: u8 = 0x10;
+const PRG_OUTER_BANK_SIZE: usize = 256 * 1024;
+/// SOROM (16KB) picks the 8KB PRG-RAM bank with bit 3 of the CHR bank
+/// register, SXROM (32KB) with bits 2-3.
+const SOROM_PRG_RAM_SIZE: usize = 16 * 1024;
+const SOROM_PRG_RAM_BANK_SHIFT: u8 = 3;
+const SXROM_PRG_RAM_SIZE: usize = 32 * 1024;
+const SXROM_PRG_RAM_BANK_SHIFT: u8 = 2;
+
+/// Mapper 1. Registers are loaded one bit at a time through a serial port
+/// at $8000-$FFFF; the fifth write lands in the register picked by A13-A14:
+///
+/// $8000 control, $A000 CHR bank 0, $C000 CHR bank 1, $E000 PRG bank.
+pub struct Mmc1 {
+    shift: u8,
+    writes: u8,
+    control: u8,
+    chr_bank_0: u8,
+    chr_bank_1: u8,
+    prg_bank: u8,
+    /// Which CHR register the PPU last used, for the boards that wire its
+    /// high bits to PRG.
+    ppu_a12: bool,
+    cycle: u64,
+    last_write_cycle: Option<u64>,
+}
+
+impl Mmc1 {
+    pub fn new() -> Self {
+        Mmc1 {
+            shift: 0,
+            writes: 0,
+            control: CONTROL_POWER_ON,
+            chr_bank_0: 0,
+            chr_bank_1: 0,
+            prg_bank: 0,
+            ppu_a12: false,
+            cycle: 0,
+            last_write_cycle: None,
+        }
+    }
+
+    fn load(&mut self, addr: u16, data: u8) {
+        // The second write of a read-modify-write instruction comes on the
+        // very next cycle, and the MMC1 ignores it.
+        let consecutive = self.last_write_cycle == Some(self.cycle.wrapping_sub(1));
+        self.last_write_cycle = Some(self.cycle);
+        if consecutive {
+            return;
+        }
+
+        if data & SHIFT_RESET != 0 {
+            self.shift = 0;
+            self.writes = 0;
+            self.control |= CONTROL_POWER_ON;
+            return;
+        }
+
+        self.shift |= (data & 0x01) << self.writes;
+        self.writes += 1;
+
+        if self.writes == SHIFT_WRITES {
+            match addr {
+                0x8000..=0x9FFF => self.control = self.shift,
+                0xA000..=0xBFFF => self.chr_bank_0 = self.shift,
+                0xC000..=0xDFFF => self.chr_bank_1 = self.shift,
+                _ => self.prg_bank = self.shift,
+            }
+
+            self.shift = 0;
+            self.writes = 0;
+        }
+    }
+
+    /// The CHR register whose high bits drive PRG banking on big boards.
+    fn outer_register(&self) -> u8 {
+        if self.control & CONTROL_CHR_4K != 0 && self.ppu_a12 {
+            self.chr_bank_1
+        } else {
+            self.chr_bank_0
+        }
+    }
+
+    fn prg_ram_enabled(&self) -> bool {
+        self.prg_bank & PRG_RAM_DISABLE == 0
+    }
+
+    fn prg_ram_bank(&self, memory: &CartridgeMemory) -> usize {
+        match memory.prg_ram.len() {
+            SOROM_PRG_RAM_SIZE => ((self.outer_register() >> SOROM_PRG_RAM_BANK_SHIFT) & 0x01) as usize,
+            SXROM_PRG_RAM_SIZE.. => ((self.outer_register() >> SXROM_PRG_RAM_BANK_SHIFT) & 0x03) as usize,
+            _ => 0,
+        }
+    }
+
+    /// The 16KB bank at `addr`.
+    fn prg_rom_bank(&self, memory: &CartridgeMemory, addr: u16) -> usize {
+        let outer = match memory.prg_rom.len() > PRG_OUTER_BANK_SIZE && self.outer_register() & PRG_OUTER_BANK != 0 {
+            true => PRG_OUTER_BANK_SIZE / PRG_BANK_SIZE,
+            false => 0,
+        };
+        let bank = (self.prg_bank & PRG_BANK_MASK) as usize;
+        let upper_half = addr >= 0xC000;
+
+        let inner = match (self.control & CONTROL_PRG_MODE) >> 2 {
+            // 32KB at a time, ignoring the low bit
+            0 | 1 => (bank & !1) | upper_half as usize,
+            // First bank fixed at $8000
+            2 => if upper_half { bank } else { 0 },
+            // Last bank fixed at $C000
+            _ => if upper_half { PRG_BANK_MASK as usize } else { bank },
+        };
+
+        outer + inner
+    }
+
+    /// The 4KB bank at `addr`.
+    fn chr_bank(&self, addr: u16) -> usize {
+        let upper_half = addr >= 0x1000;
+
+        match self.control & CONTROL_CHR_4K != 0 {
+            true if upper_half => self.chr_bank_1 as usize,
+            true => self.chr_bank_0 as usize,
+            false => (self.chr_bank_0 & !1) as usize | upper_half as usize,
+        }
+    }
+}
+
+impl Default for Mmc1 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Mapper for Mmc1 {
+    fn cpu_write(&mut self, memory: &mut CartridgeMemory, addr: u16, data: u8) {
+        match addr {
+            PRG_RAM_START..=PRG_RAM_END if self.prg_ram_enabled() => {
+                memory.write_prg_ram(self.prg_ram_bank(memory), addr, data);
+            }
+            PRG_ROM_START..=0xFFFF => self.load(addr, data),
+            _ => {}
+        }
+    }
+
+    fn cpu_peek(&self, memory: &CartridgeMemory, addr: u16) -> Option<u8> {
+        match addr {
+            PRG_RAM_START..=PRG_RAM_END if self.prg_ram_enabled() => Some(memory.read_prg_ram(self.prg_ram_bank(memory), addr)),
+            PRG_ROM_START..=0xFFFF => Some(memory.read_prg_rom(self.prg_rom_bank(memory, addr), PRG_BANK_SIZE, addr)),
+            _ => None,
+        }
+    }
+
+    fn ppu_read(&mut self, memory: &CartridgeMemory, addr: u16) -> u8 {
+        self.ppu_a12 = addr & 0x1000 != 0;
+        self.ppu_peek(memory, addr)
+    }
+
+    fn ppu_write(&mut self, memory: &mut CartridgeMemory, addr: u16, data: u8) {
+        self.ppu_a12 = addr & 0x1000 != 0;
+        memory.write_chr(self.chr_bank(addr), CHR_BANK_SIZE, addr, data);
+    }
+
+    fn ppu_peek(&self, memory: &CartridgeMemory, addr: u16) -> u8 {
+        memory.read_chr(self.chr_bank(addr), CHR_BANK_SIZE, addr)
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        match self.control & CONTROL_MIRRORING {
+            0 => Mirroring::SingleScreenLower,
+            1 => Mirroring::SingleScreenUpper,
+            2 => Mirroring::Vertical,
+            _ => Mirroring::Horizontal,
+        }
+    }
+
+    fn cpu_clock(&mut self) {
+        self.cycle = self.cycle.wrapping_add(1);
+    }
+}
@@ -0,0 +1,208 @@
+use super::*;
+use crate::cartridge::{PRG_RAM_END, PRG_RAM_START, PRG_ROM_START};
+
+const PRG_BANK_SIZE: usize = 8 * 1024;
+const CHR_BANK_SIZE: usize = 1024;
+
+const BANK_SELECT_TARGET: u8 = 0x07;
+const BANK_SELECT_PRG_MODE: u8 = 0x40;
+const BANK_SELECT_CHR_INVERSION: u8 = 0x80;
+
+const PRG_RAM_CHIP_ENABLE: u8 = 0x80;
+const PRG_RAM_WRITE_PROTECT: u8 = 0x40;
+
+/// How many CPU cycles A12 has to stay low before a rise clocks the IRQ
+/// counter. This keeps the quick toggling during sprite fetches from
+/// counting more than once per scanline.
+const A12_FILTER_CYCLES: u64 = 3;
+
+/// The MMC3 revisions disagree on when a counter of 0 raises an IRQ.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Mmc3Revision {
+    /// IRQ only when the counter is decremented to 0, or reloaded to 0
+    /// after a write to $C001.
+    A,
+    /// IRQ every time the counter is 0 after being clocked, so a latch of
+    /// 0 fires on every scanline.
+    #[default]
+    BC,
+}
+
+/// Mapper 4. Eight bank registers are picked through $8000 and written
+/// through $8001; see the methods below for how they map.
+///
+/// The scanline IRQ counter is clocked by rising edges on PPU A12, which
+/// the PPU produces once per scanline when backgrounds and sprites use
+/// different pattern tables.
+pub struct Mmc3 {
+    revision: Mmc3Revision,
+    bank_select: u8,
+    banks: [u8; 8],
+    mirroring: Mirroring,
+    four_screen: bool,
+    prg_ram_protect: u8,
+    irq_latch: u8,
+    irq_counter: u8,
+    irq_reload: bool,
+    irq_enabled: bool,
+    irq_pending: bool,
+    a12_high: bool,
+    a12_fell_at: u64,
+    cycle: u64,
+}
+
+impl Mmc3 {
+    pub fn new(mirroring: Mirroring, revision: Mmc3Revision) -> Self {
+        Mmc3 {
+            revision,
+            bank_select: 0,
+            banks: [0, 2, 4, 5, 6, 7, 0, 1],
+            mirroring,
+            four_screen: mirroring == Mirroring::FourScreen,
+            prg_ram_protect: PRG_RAM_CHIP_ENABLE,
+            irq_latch: 0,
+            irq_counter: 0,
+            irq_reload: false,
+            irq_enabled: false,
+            irq_pending: false,
+            a12_high: false,
+            a12_fell_at: 0,
+            cycle: 0,
+        }
+    }
+
+    /// $8000 R6 | R7 | second to last | last, or with the PRG mode bit set
+    /// second to last | R7 | R6 | last.
+    fn prg_bank(&self, memory: &CartridgeMemory, addr: u16) -> usize {
+        let last = memory.prg_rom_banks(PRG_BANK_SIZE) - 1;
+        let swapped = self.bank_select & BANK_SELECT_PRG_MODE != 0;
+
+        match (addr - PRG_ROM_START) / PRG_BANK_SIZE as u16 {
+            0 if swapped => last.saturating_sub(1),
+            0 => (self.banks[6] & 0x3F) as usize,
+            1 => (self.banks[7] & 0x3F) as usize,
+            2 if swapped => (self.banks[6] & 0x3F) as usize,
+            2 => last.saturating_sub(1),
+            _ => last,
+        }
+    }
+
+    /// 2KB banks R0 and R1 followed by 1KB banks R2-R5, with the halves
+    /// swapped when CHR inversion is on.
+    fn chr_bank(&self, addr: u16) -> usize {
+        let addr = match self.bank_select & BANK_SELECT_CHR_INVERSION != 0 {
+            true => addr ^ 0x1000,
+            false => addr,
+        };
+
+        let slot = (addr / CHR_BANK_SIZE as u16) as usize;
+        match slot {
+            0..=3 => (self.banks[slot / 2] & 0xFE) as usize | (slot & 1),
+            _ => self.banks[slot - 2] as usize,
+        }
+    }
+
+    fn prg_ram_readable(&self) -> bool {
+        self.prg_ram_protect & PRG_RAM_CHIP_ENABLE != 0
+    }
+
+    fn prg_ram_writable(&self) -> bool {
+        self.prg_ram_readable() && self.prg_ram_protect & PRG_RAM_WRITE_PROTECT == 0
+    }
+
+    fn watch_a12(&mut self, addr: u16) {
+        let high = addr & 0x1000 != 0;
+
+        if high && !self.a12_high && self.cycle.wrapping_sub(self.a12_fell_at) >= A12_FILTER_CYCLES {
+            self.clock_irq_counter();
+        }
+        if !high && self.a12_high {
+            self.a12_fell_at = self.cycle;
+        }
+
+        self.a12_high = high;
+    }
+
+    fn clock_irq_counter(&mut self) {
+        let before = self.irq_counter;
+        let reload = self.irq_reload;
+
+        if self.irq_counter == 0 || self.irq_reload {
+            self.irq_counter = self.irq_latch;
+        } else {
+            self.irq_counter -= 1;
+        }
+        self.irq_reload = false;
+
+        let fire = match self.revision {
+            Mmc3Revision::A => self.irq_counter == 0 && (before > 0 || reload),
+            Mmc3Revision::BC => self.irq_counter == 0,
+        };
+
+        if fire && self.irq_enabled {
+            self.irq_pending = true;
+        }
+    }
+}
+
+impl Mapper for Mmc3 {
+    fn cpu_write(&mut self, memory: &mut CartridgeMemory, addr: u16, data: u8) {
+        let even = addr & 0x0001 == 0;
+
+        match addr {
+            PRG_RAM_START..=PRG_RAM_END if self.prg_ram_writable() => memory.write_prg_ram(0, addr, data),
+            0x8000..=0x9FFF if even => self.bank_select = data,
+            0x8000..=0x9FFF => self.banks[(self.bank_select & BANK_SELECT_TARGET) as usize] = data,
+            0xA000..=0xBFFF if even && self.four_screen => {}
+            0xA000..=0xBFFF if even => {
+                self.mirroring = if data & 0x01 != 0 { Mirroring::Horizontal } else { Mirroring::Vertical };
+            }
+            0xA000..=0xBFFF => self.prg_ram_protect = data,
+            0xC000..=0xDFFF if even => self.irq_latch = data,
+            0xC000..=0xDFFF => {
+                self.irq_counter = 0;
+                self.irq_reload = true;
+            }
+            0xE000..=0xFFFF if even => {
+                self.irq_enabled = false;
+                self.irq_pending = false;
+            }
+            0xE000..=0xFFFF => self.irq_enabled = true,
+            _ => {}
+        }
+    }
+
+    fn cpu_peek(&self, memory: &CartridgeMemory, addr: u16) -> Option<u8> {
+        match addr {
+            PRG_RAM_START..=PRG_RAM_END if self.prg_ram_readable() => Some(memory.read_prg_ram(0, addr)),
+            PRG_ROM_START..=0xFFFF => Some(memory.read_prg_rom(self.prg_bank(memory, addr), PRG_BANK_SIZE, addr)),
+            _ => None,
+        }
+    }
+
+    fn ppu_read(&mut self, memory: &CartridgeMemory, addr: u16) -> u8 {
+        self.watch_a12(addr);
+        self.ppu_peek(memory, addr)
+    }
+
+    fn ppu_write(&mut self, memory: &mut CartridgeMemory, addr: u16, data: u8) {
+        self.watch_a12(addr);
+        memory.write_chr(self.chr_bank(addr), CHR_BANK_SIZE, addr, data);
+    }
+
+    fn ppu_peek(&self, memory: &CartridgeMemory, addr: u16) -> u8 {
+        memory.read_chr(self.chr_bank(addr), CHR_BANK_SIZE, addr)
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    fn cpu_clock(&mut self) {
+        self.cycle = self.cycle.wrapping_add(1);
+    }
+
+    fn irq(&self) -> bool {
+        self.irq_pending
+    }
+}
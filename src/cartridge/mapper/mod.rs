@@ -0,0 +1,77 @@
+mod axrom;
+mod cnrom;
+mod mmc1;
+mod mmc3;
+mod nrom;
+mod tests;
+mod uxrom;
+
+pub use axrom::Axrom;
+pub use cnrom::Cnrom;
+pub use mmc1::Mmc1;
+pub use mmc3::{Mmc3, Mmc3Revision};
+pub use nrom::Nrom;
+pub use uxrom::Uxrom;
+
+use super::{CartridgeError, CartridgeMemory, Header, Mirroring};
+
+/// The banking hardware on a cartridge board. It sees every CPU access to
+/// $4020-$FFFF and every PPU access to the pattern tables at $0000-$1FFF,
+/// and decides which part of `CartridgeMemory` answers.
+///
+/// Mappers only hold their registers; the memory is passed in so that the
+/// same chips can be inspected (and saved) whatever the board.
+pub trait Mapper {
+    /// `None` where nothing on the board drives the data bus, which leaves
+    /// the CPU reading open bus.
+    fn cpu_read(&mut self, memory: &CartridgeMemory, addr: u16) -> Option<u8> {
+        self.cpu_peek(memory, addr)
+    }
+
+    fn cpu_write(&mut self, memory: &mut CartridgeMemory, addr: u16, data: u8);
+
+    /// What `cpu_read` would return, without any of its side effects.
+    fn cpu_peek(&self, memory: &CartridgeMemory, addr: u16) -> Option<u8>;
+
+    fn ppu_read(&mut self, memory: &CartridgeMemory, addr: u16) -> u8 {
+        self.ppu_peek(memory, addr)
+    }
+
+    fn ppu_write(&mut self, memory: &mut CartridgeMemory, addr: u16, data: u8);
+
+    fn ppu_peek(&self, memory: &CartridgeMemory, addr: u16) -> u8;
+
+    fn mirroring(&self) -> Mirroring;
+
+    /// Called once per CPU cycle, for boards that care about timing.
+    fn cpu_clock(&mut self) {}
+
+    /// Whether the board is pulling the CPU's IRQ line low. The line is
+    /// level triggered, so it stays asserted until the game acknowledges
+    /// it through whatever register the board has for that.
+    fn irq(&self) -> bool {
+        false
+    }
+}
+
+/// Picks the mapper the header asks for.
+pub fn new(header: &Header) -> Result<Box<dyn Mapper>, CartridgeError> {
+    let mapper: Box<dyn Mapper> = match header.mapper {
+        0 => Box::new(Nrom::new(header.mirroring)),
+        1 => Box::new(Mmc1::new()),
+        2 => Box::new(Uxrom::new(header.mirroring)),
+        3 => Box::new(Cnrom::new(header.mirroring)),
+        4 => {
+            let revision = match header.submapper {
+                4 => Mmc3Revision::A,
+                _ => Mmc3Revision::BC,
+            };
+
+            Box::new(Mmc3::new(header.mirroring, revision))
+        }
+        7 => Box::new(Axrom::new()),
+        mapper => return Err(CartridgeError::UnsupportedMapper { mapper, submapper: header.submapper }),
+    };
+
+    Ok(mapper)
+}
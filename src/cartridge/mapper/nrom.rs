@@ -0,0 +1,45 @@
+use super::*;
+use crate::cartridge::{PRG_RAM_END, PRG_RAM_START, PRG_ROM_START};
+
+const PRG_BANK_SIZE: usize = 32 * 1024;
+const CHR_BANK_SIZE: usize = 8 * 1024;
+
+/// Mapper 0: no banking at all. 16KB of PRG ROM is mirrored into both
+/// halves of $8000-$FFFF.
+pub struct Nrom {
+    mirroring: Mirroring,
+}
+
+impl Nrom {
+    pub fn new(mirroring: Mirroring) -> Self {
+        Nrom { mirroring }
+    }
+}
+
+impl Mapper for Nrom {
+    fn cpu_write(&mut self, memory: &mut CartridgeMemory, addr: u16, data: u8) {
+        if let PRG_RAM_START..=PRG_RAM_END = addr {
+            memory.write_prg_ram(0, addr, data);
+        }
+    }
+
+    fn cpu_peek(&self, memory: &CartridgeMemory, addr: u16) -> Option<u8> {
+        match addr {
+            PRG_RAM_START..=PRG_RAM_END => Some(memory.read_prg_ram(0, addr)),
+            PRG_ROM_START..=0xFFFF => Some(memory.read_prg_rom(0, PRG_BANK_SIZE, addr)),
+            _ => None,
+        }
+    }
+
+    fn ppu_write(&mut self, memory: &mut CartridgeMemory, addr: u16, data: u8) {
+        memory.write_chr(0, CHR_BANK_SIZE, addr, data);
+    }
+
+    fn ppu_peek(&self, memory: &CartridgeMemory, addr: u16) -> u8 {
+        memory.read_chr(0, CHR_BANK_SIZE, addr)
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+}
@@ -0,0 +1,384 @@
+#[cfg(test)]
+mod mapper_tests {
+    use crate::cartridge::mapper::*;
+
+    /// `prg_banks` 16KB PRG banks and `chr_banks` 8KB CHR ROM banks (CHR RAM
+    /// if 0), each filled with its bank number.
+    fn chips(prg_banks: usize, chr_banks: usize) -> CartridgeMemory {
+        let bank = |size, count: usize| (0..count).flat_map(|bank| std::iter::repeat_n(bank as u8, size)).collect::<Vec<u8>>();
+
+        CartridgeMemory {
+            prg_rom: bank(16 * 1024, prg_banks),
+            chr: if chr_banks > 0 { bank(8 * 1024, chr_banks) } else { vec![0x00; 8 * 1024] },
+            chr_is_ram: chr_banks == 0,
+            prg_ram: vec![0x00; 8 * 1024],
+            nametable_ram: vec![],
+        }
+    }
+
+    #[test]
+    fn nrom_mirrors_16k_of_prg() {
+        let mut memory = chips(1, 1);
+        let mut mapper = Nrom::new(Mirroring::Vertical);
+
+        memory.prg_rom[0x0123] = 0xAB;
+        assert_eq!(mapper.cpu_read(&memory, 0x8123), Some(0xAB));
+        assert_eq!(mapper.cpu_read(&memory, 0xC123), Some(0xAB));
+
+        mapper.cpu_write(&mut memory, 0x6010, 0x42);
+        assert_eq!(mapper.cpu_read(&memory, 0x6010), Some(0x42));
+
+        // CHR ROM stays read only
+        mapper.ppu_write(&mut memory, 0x0010, 0xFF);
+        assert_eq!(mapper.ppu_read(&memory, 0x0010), 0x00);
+        assert_eq!(mapper.mirroring(), Mirroring::Vertical);
+
+        let memory = chips(2, 1);
+        assert_eq!(mapper.cpu_read(&memory, 0x8000), Some(0));
+        assert_eq!(mapper.cpu_read(&memory, 0xC000), Some(1));
+    }
+
+    #[test]
+    fn uxrom_switches_the_low_bank() {
+        let mut memory = chips(8, 0);
+        let mut mapper = Uxrom::new(Mirroring::Horizontal);
+
+        assert_eq!(mapper.cpu_read(&memory, 0x8000), Some(0));
+        assert_eq!(mapper.cpu_read(&memory, 0xC000), Some(7));
+
+        mapper.cpu_write(&mut memory, 0xFFFF, 5);
+        assert_eq!(mapper.cpu_read(&memory, 0xBFFF), Some(5));
+        assert_eq!(mapper.cpu_read(&memory, 0xFFFF), Some(7));
+
+        // Banks past the end of the ROM wrap around
+        mapper.cpu_write(&mut memory, 0x8000, 10);
+        assert_eq!(mapper.cpu_read(&memory, 0x8000), Some(2));
+
+        mapper.ppu_write(&mut memory, 0x1FFF, 0x99);
+        assert_eq!(mapper.ppu_read(&memory, 0x1FFF), 0x99);
+        assert_eq!(mapper.mirroring(), Mirroring::Horizontal);
+    }
+
+    #[test]
+    fn cnrom_switches_chr() {
+        let mut memory = chips(2, 4);
+        let mut mapper = Cnrom::new(Mirroring::Vertical);
+
+        assert_eq!(mapper.ppu_read(&memory, 0x0000), 0);
+
+        mapper.cpu_write(&mut memory, 0x8000, 3);
+        assert_eq!(mapper.ppu_read(&memory, 0x0000), 3);
+        assert_eq!(mapper.ppu_read(&memory, 0x1FFF), 3);
+
+        // PRG is not banked, and nothing answers below $6000
+        assert_eq!(mapper.cpu_read(&memory, 0x5000), None);
+        assert_eq!(mapper.cpu_read(&memory, 0x8000), Some(0));
+        assert_eq!(mapper.cpu_read(&memory, 0xC000), Some(1));
+    }
+
+    #[test]
+    fn axrom_switches_32k_and_single_screen_mirroring() {
+        let mut memory = chips(8, 0);
+        let mut mapper = Axrom::new();
+
+        assert_eq!(mapper.cpu_read(&memory, 0x8000), Some(0));
+        assert_eq!(mapper.cpu_read(&memory, 0xC000), Some(1));
+        assert_eq!(mapper.mirroring(), Mirroring::SingleScreenLower);
+
+        mapper.cpu_write(&mut memory, 0x8000, 0x12);
+        assert_eq!(mapper.cpu_read(&memory, 0x8000), Some(4));
+        assert_eq!(mapper.cpu_read(&memory, 0xC000), Some(5));
+        assert_eq!(mapper.mirroring(), Mirroring::SingleScreenUpper);
+
+        mapper.cpu_write(&mut memory, 0xFFFF, 0x03);
+        assert_eq!(mapper.cpu_read(&memory, 0x8000), Some(6));
+        assert_eq!(mapper.mirroring(), Mirroring::SingleScreenLower);
+    }
+
+    /// Loads an MMC1 register through the serial port, one write every
+    /// other cycle like a string of STAs would.
+    fn mmc1_load(mapper: &mut Mmc1, memory: &mut CartridgeMemory, addr: u16, value: u8) {
+        for bit in 0..5 {
+            mapper.cpu_write(memory, addr, value >> bit);
+            mapper.cpu_clock();
+            mapper.cpu_clock();
+        }
+    }
+
+    #[test]
+    fn mmc1_prg_modes() {
+        let mut memory = chips(8, 0);
+        let mut mapper = Mmc1::new();
+
+        // Powers on with the last bank fixed at $C000
+        assert_eq!(mapper.cpu_read(&memory, 0x8000), Some(0));
+        assert_eq!(mapper.cpu_read(&memory, 0xC000), Some(7));
+
+        mmc1_load(&mut mapper, &mut memory, 0xE000, 3);
+        assert_eq!(mapper.cpu_read(&memory, 0x8000), Some(3));
+        assert_eq!(mapper.cpu_read(&memory, 0xC000), Some(7));
+
+        // First bank fixed at $8000
+        mmc1_load(&mut mapper, &mut memory, 0x8000, 0x08);
+        assert_eq!(mapper.cpu_read(&memory, 0x8000), Some(0));
+        assert_eq!(mapper.cpu_read(&memory, 0xC000), Some(3));
+
+        // 32KB mode ignores the low bit
+        mmc1_load(&mut mapper, &mut memory, 0x8000, 0x00);
+        assert_eq!(mapper.cpu_read(&memory, 0x8000), Some(2));
+        assert_eq!(mapper.cpu_read(&memory, 0xC000), Some(3));
+    }
+
+    #[test]
+    fn mmc1_reset_and_consecutive_writes() {
+        let mut memory = chips(8, 0);
+        let mut mapper = Mmc1::new();
+
+        mmc1_load(&mut mapper, &mut memory, 0x8000, 0x02);
+        assert_eq!(mapper.mirroring(), Mirroring::Vertical);
+
+        // A write with bit 7 set throws away the half loaded value and goes
+        // back to fixing the last bank
+        mapper.cpu_write(&mut memory, 0x8000, 0x01);
+        mapper.cpu_clock();
+        mapper.cpu_clock();
+        mapper.cpu_write(&mut memory, 0x8000, 0x80);
+        mapper.cpu_clock();
+        mapper.cpu_clock();
+        mmc1_load(&mut mapper, &mut memory, 0xE000, 0x05);
+        assert_eq!(mapper.cpu_read(&memory, 0x8000), Some(5));
+        assert_eq!(mapper.cpu_read(&memory, 0xC000), Some(7));
+
+        // The second write of a read-modify-write is ignored, so this is
+        // still only the first bit
+        mapper.cpu_write(&mut memory, 0xE000, 0x00);
+        mapper.cpu_clock();
+        mapper.cpu_write(&mut memory, 0xE000, 0x01);
+        mapper.cpu_clock();
+        mapper.cpu_clock();
+        for _ in 0..4 {
+            mapper.cpu_write(&mut memory, 0xE000, 0x00);
+            mapper.cpu_clock();
+            mapper.cpu_clock();
+        }
+        assert_eq!(mapper.cpu_read(&memory, 0x8000), Some(0));
+    }
+
+    #[test]
+    fn mmc1_chr_modes_and_mirroring() {
+        let mut memory = chips(2, 4);
+        memory.chr = (0..8).flat_map(|bank| std::iter::repeat_n(bank as u8, 4 * 1024)).collect();
+        let mut mapper = Mmc1::new();
+
+        mmc1_load(&mut mapper, &mut memory, 0xA000, 5);
+        mmc1_load(&mut mapper, &mut memory, 0xC000, 6);
+        assert_eq!(mapper.ppu_read(&memory, 0x0000), 4);
+        assert_eq!(mapper.ppu_read(&memory, 0x1000), 5);
+
+        mmc1_load(&mut mapper, &mut memory, 0x8000, 0x10 | 0x03);
+        assert_eq!(mapper.ppu_read(&memory, 0x0000), 5);
+        assert_eq!(mapper.ppu_read(&memory, 0x1000), 6);
+        assert_eq!(mapper.mirroring(), Mirroring::Horizontal);
+
+        for (control, mirroring) in [(0, Mirroring::SingleScreenLower), (1, Mirroring::SingleScreenUpper), (2, Mirroring::Vertical)] {
+            mmc1_load(&mut mapper, &mut memory, 0x8000, control);
+            assert_eq!(mapper.mirroring(), mirroring);
+        }
+    }
+
+    #[test]
+    fn mmc1_big_boards_bank_prg_and_prg_ram_through_chr_registers() {
+        // SXROM: 512KB PRG ROM, 32KB PRG-RAM
+        let mut memory = chips(32, 0);
+        memory.prg_ram = vec![0x00; 32 * 1024];
+        let mut mapper = Mmc1::new();
+
+        mmc1_load(&mut mapper, &mut memory, 0xE000, 2);
+        assert_eq!(mapper.cpu_read(&memory, 0x8000), Some(2));
+        assert_eq!(mapper.cpu_read(&memory, 0xC000), Some(15));
+
+        mmc1_load(&mut mapper, &mut memory, 0xA000, 0x10 | 0x0C);
+        assert_eq!(mapper.cpu_read(&memory, 0x8000), Some(18));
+        assert_eq!(mapper.cpu_read(&memory, 0xC000), Some(31));
+
+        mapper.cpu_write(&mut memory, 0x6000, 0xAA);
+        assert_eq!(memory.prg_ram[3 * 8 * 1024], 0xAA);
+
+        mmc1_load(&mut mapper, &mut memory, 0xA000, 0x00);
+        assert_eq!(mapper.cpu_read(&memory, 0x6000), Some(0x00));
+        mapper.cpu_write(&mut memory, 0x6000, 0x55);
+        assert_eq!(memory.prg_ram[0], 0x55);
+
+        // Bit 4 of the PRG bank register disables PRG-RAM
+        mmc1_load(&mut mapper, &mut memory, 0xE000, 0x10);
+        mapper.cpu_write(&mut memory, 0x6000, 0x11);
+        assert_eq!(memory.prg_ram[0], 0x55);
+    }
+
+    #[test]
+    fn mmc1_sorom_banks_prg_ram_with_bit_3_only() {
+        // SOROM: 16KB PRG-RAM
+        let mut memory = chips(16, 0);
+        memory.prg_ram = vec![0x00; 16 * 1024];
+        let mut mapper = Mmc1::new();
+
+        // Bit 2 is not connected
+        mmc1_load(&mut mapper, &mut memory, 0xA000, 0x04);
+        mapper.cpu_write(&mut memory, 0x6000, 0xAA);
+        assert_eq!(memory.prg_ram[0], 0xAA);
+
+        mmc1_load(&mut mapper, &mut memory, 0xA000, 0x08);
+        mapper.cpu_write(&mut memory, 0x6000, 0x55);
+        assert_eq!(memory.prg_ram[8 * 1024], 0x55);
+
+        mmc1_load(&mut mapper, &mut memory, 0xA000, 0x0C);
+        assert_eq!(mapper.cpu_read(&memory, 0x6000), Some(0x55));
+        mmc1_load(&mut mapper, &mut memory, 0xA000, 0x00);
+        assert_eq!(mapper.cpu_read(&memory, 0x6000), Some(0xAA));
+    }
+
+    fn mmc3_write(mapper: &mut Mmc3, memory: &mut CartridgeMemory, writes: &[(u16, u8)]) {
+        for &(addr, data) in writes {
+            mapper.cpu_write(memory, addr, data);
+        }
+    }
+
+    /// One rendered scanline as the MMC3 sees it: background fetches from
+    /// $0000, then sprite fetches from $1000.
+    fn mmc3_scanline(mapper: &mut Mmc3, memory: &CartridgeMemory) {
+        mapper.ppu_read(memory, 0x0000);
+        for _ in 0..85 {
+            mapper.cpu_clock();
+        }
+        mapper.ppu_read(memory, 0x1000);
+        for _ in 0..29 {
+            mapper.cpu_clock();
+        }
+    }
+
+    #[test]
+    fn mmc3_prg_banking() {
+        let mut memory = chips(16, 0);
+        memory.prg_rom = (0..32).flat_map(|bank| std::iter::repeat_n(bank as u8, 8 * 1024)).collect();
+        let mut mapper = Mmc3::new(Mirroring::Vertical, Mmc3Revision::BC);
+
+        mmc3_write(&mut mapper, &mut memory, &[(0x8000, 6), (0x8001, 3), (0x8000, 7), (0x8001, 9)]);
+        let banks = |mapper: &Mmc3, memory: &CartridgeMemory| [0x8000, 0xA000, 0xC000, 0xE000].map(|addr| mapper.cpu_peek(memory, addr).unwrap());
+        assert_eq!(banks(&mapper, &memory), [3, 9, 30, 31]);
+
+        mmc3_write(&mut mapper, &mut memory, &[(0x8000, 0x40)]);
+        assert_eq!(banks(&mapper, &memory), [30, 9, 3, 31]);
+    }
+
+    #[test]
+    fn mmc3_chr_banking_and_mirroring() {
+        let mut memory = chips(2, 0);
+        memory.chr = (0..32).flat_map(|bank| std::iter::repeat_n(bank as u8, 1024)).collect();
+        memory.chr_is_ram = false;
+        let mut mapper = Mmc3::new(Mirroring::Vertical, Mmc3Revision::BC);
+
+        for (register, bank) in [5, 8, 20, 21, 22, 23].into_iter().enumerate() {
+            mmc3_write(&mut mapper, &mut memory, &[(0x8000, register as u8), (0x8001, bank)]);
+        }
+
+        let slots = |mapper: &Mmc3, memory: &CartridgeMemory| (0..8).map(|slot| mapper.ppu_peek(memory, slot * 0x400)).collect::<Vec<u8>>();
+        assert_eq!(slots(&mapper, &memory), [4, 5, 8, 9, 20, 21, 22, 23]);
+
+        mmc3_write(&mut mapper, &mut memory, &[(0x8000, 0x80)]);
+        assert_eq!(slots(&mapper, &memory), [20, 21, 22, 23, 4, 5, 8, 9]);
+
+        mmc3_write(&mut mapper, &mut memory, &[(0xA000, 1)]);
+        assert_eq!(mapper.mirroring(), Mirroring::Horizontal);
+
+        let mut mapper = Mmc3::new(Mirroring::FourScreen, Mmc3Revision::BC);
+        mmc3_write(&mut mapper, &mut memory, &[(0xA000, 1)]);
+        assert_eq!(mapper.mirroring(), Mirroring::FourScreen);
+    }
+
+    #[test]
+    fn mmc3_prg_ram_protect() {
+        let mut memory = chips(2, 0);
+        let mut mapper = Mmc3::new(Mirroring::Vertical, Mmc3Revision::BC);
+
+        mapper.cpu_write(&mut memory, 0x6000, 0x12);
+        assert_eq!(mapper.cpu_read(&memory, 0x6000), Some(0x12));
+
+        mmc3_write(&mut mapper, &mut memory, &[(0xA001, 0xC0), (0x6000, 0x34)]);
+        assert_eq!(mapper.cpu_read(&memory, 0x6000), Some(0x12));
+
+        mmc3_write(&mut mapper, &mut memory, &[(0xA001, 0x00)]);
+        assert_eq!(mapper.cpu_read(&memory, 0x6000), None);
+    }
+
+    #[test]
+    fn mmc3_scanline_irq() {
+        let mut memory = chips(2, 0);
+        let mut mapper = Mmc3::new(Mirroring::Vertical, Mmc3Revision::BC);
+
+        mmc3_write(&mut mapper, &mut memory, &[(0xC000, 2), (0xC001, 0), (0xE001, 0)]);
+
+        // Reload to 2, then 1, then 0
+        for _ in 0..2 {
+            mmc3_scanline(&mut mapper, &memory);
+            assert!(!mapper.irq());
+        }
+        mmc3_scanline(&mut mapper, &memory);
+        assert!(mapper.irq());
+
+        // The line stays asserted until $E000 acknowledges it
+        mmc3_scanline(&mut mapper, &memory);
+        assert!(mapper.irq());
+        mmc3_write(&mut mapper, &mut memory, &[(0xE000, 0)]);
+        assert!(!mapper.irq());
+
+        // Disabled: the counter still runs but nothing is raised
+        for _ in 0..3 {
+            mmc3_scanline(&mut mapper, &memory);
+        }
+        assert!(!mapper.irq());
+    }
+
+    #[test]
+    fn mmc3_a12_filter() {
+        let mut memory = chips(2, 0);
+        let mut mapper = Mmc3::new(Mirroring::Vertical, Mmc3Revision::BC);
+        mmc3_write(&mut mapper, &mut memory, &[(0xC000, 1), (0xC001, 0), (0xE001, 0)]);
+
+        mmc3_scanline(&mut mapper, &memory);
+
+        // Eight sprite fetches with A12 dipping low for a moment in between
+        // only count as the one rise above
+        for _ in 0..8 {
+            mapper.ppu_read(&memory, 0x0000);
+            mapper.cpu_clock();
+            mapper.ppu_read(&memory, 0x1000);
+            mapper.cpu_clock();
+        }
+        assert!(!mapper.irq());
+
+        mmc3_scanline(&mut mapper, &memory);
+        assert!(mapper.irq());
+    }
+
+    #[test]
+    fn mmc3_revisions_differ_on_a_zero_latch() {
+        let mut memory = chips(2, 0);
+
+        let mut irqs = |revision| {
+            let mut mapper = Mmc3::new(Mirroring::Vertical, revision);
+            mmc3_write(&mut mapper, &mut memory, &[(0xC000, 0), (0xC001, 0), (0xE001, 0)]);
+
+            (0..4)
+                .map(|_| {
+                    mmc3_scanline(&mut mapper, &memory);
+                    let irq = mapper.irq();
+                    mmc3_write(&mut mapper, &mut memory, &[(0xE000, 0), (0xE001, 0)]);
+                    irq
+                })
+                .collect::<Vec<bool>>()
+        };
+
+        assert_eq!(irqs(Mmc3Revision::BC), [true, true, true, true]);
+        assert_eq!(irqs(Mmc3Revision::A), [true, false, false, false]);
+    }
+}
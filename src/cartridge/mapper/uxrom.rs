@@ -0,0 +1,50 @@
+use super::*;
+use crate::cartridge::{PRG_RAM_END, PRG_RAM_START, PRG_ROM_START};
+
+const PRG_BANK_SIZE: usize = 16 * 1024;
+const CHR_BANK_SIZE: usize = 8 * 1024;
+const FIXED_BANK_START: u16 = 0xC000;
+
+/// Mapper 2: any write to $8000-$FFFF selects the 16KB bank at $8000-$BFFF.
+/// The last bank is fixed at $C000-$FFFF.
+pub struct Uxrom {
+    mirroring: Mirroring,
+    prg_bank: u8,
+}
+
+impl Uxrom {
+    pub fn new(mirroring: Mirroring) -> Self {
+        Uxrom { mirroring, prg_bank: 0 }
+    }
+}
+
+impl Mapper for Uxrom {
+    fn cpu_write(&mut self, memory: &mut CartridgeMemory, addr: u16, data: u8) {
+        match addr {
+            PRG_RAM_START..=PRG_RAM_END => memory.write_prg_ram(0, addr, data),
+            PRG_ROM_START..=0xFFFF => self.prg_bank = data,
+            _ => {}
+        }
+    }
+
+    fn cpu_peek(&self, memory: &CartridgeMemory, addr: u16) -> Option<u8> {
+        match addr {
+            PRG_RAM_START..=PRG_RAM_END => Some(memory.read_prg_ram(0, addr)),
+            PRG_ROM_START..FIXED_BANK_START => Some(memory.read_prg_rom(self.prg_bank as usize, PRG_BANK_SIZE, addr)),
+            FIXED_BANK_START..=0xFFFF => Some(memory.read_prg_rom(memory.prg_rom_banks(PRG_BANK_SIZE) - 1, PRG_BANK_SIZE, addr)),
+            _ => None,
+        }
+    }
+
+    fn ppu_write(&mut self, memory: &mut CartridgeMemory, addr: u16, data: u8) {
+        memory.write_chr(0, CHR_BANK_SIZE, addr, data);
+    }
+
+    fn ppu_peek(&self, memory: &CartridgeMemory, addr: u16) -> u8 {
+        memory.read_chr(0, CHR_BANK_SIZE, addr)
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+}
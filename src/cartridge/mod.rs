@@ -0,0 +1,463 @@
+pub mod mapper;
+mod tests;
+
+use std::fmt::Display;
+use std::path::Path;
+
+use crate::bus::BusDevice;
+use mapper::Mapper;
+
+const HEADER_SIZE: usize = 16;
+const TRAINER_SIZE: usize = 512;
+const MAGIC: &[u8; 4] = b"NES\x1A";
+
+const PRG_ROM_UNIT: usize = 16 * 1024;
+const CHR_ROM_UNIT: usize = 8 * 1024;
+const INES_PRG_RAM_UNIT: usize = 8 * 1024;
+const INES_CHR_RAM_SIZE: usize = 8 * 1024;
+
+const PRG_RAM_BANK_SIZE: usize = 8 * 1024;
+const PRG_RAM_START: u16 = 0x6000;
+const PRG_RAM_END: u16 = 0x7FFF;
+const TRAINER_START: u16 = 0x7000;
+const PRG_ROM_START: u16 = 0x8000;
+
+pub const NAMETABLE_SIZE: usize = 1024;
+const NAMETABLE_START: u16 = 0x2000;
+/// Four-screen boards carry the two nametables the console is missing.
+const FOUR_SCREEN_RAM_SIZE: usize = 2 * NAMETABLE_SIZE;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RomFormat {
+    INes,
+    Nes2,
+}
+
+/// How the two physical nametables are laid out across $2000-$2FFF.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mirroring {
+    Horizontal,
+    Vertical,
+    FourScreen,
+    /// Every nametable shows the first physical one; only mappers pick this.
+    SingleScreenLower,
+    SingleScreenUpper,
+}
+
+impl Mirroring {
+    /// The physical 1KB page a nametable address at $2000-$3EFF lands in.
+    /// Pages 0 and 1 are the console's own CIRAM; 2 and 3 only exist on
+    /// four-screen boards, in `CartridgeMemory::nametable_ram`.
+    pub fn nametable_page(self, addr: u16) -> usize {
+        let table = (addr.wrapping_sub(NAMETABLE_START) as usize / NAMETABLE_SIZE) % 4;
+
+        match self {
+            Mirroring::Horizontal => table >> 1,
+            Mirroring::Vertical => table & 1,
+            Mirroring::FourScreen => table,
+            Mirroring::SingleScreenLower => 0,
+            Mirroring::SingleScreenUpper => 1,
+        }
+    }
+}
+
+/// Which console the game was made for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Timing {
+    Ntsc,
+    Pal,
+    MultiRegion,
+    Dendy,
+}
+
+/// Everything the 16 byte header says about the cartridge. Sizes are in
+/// bytes and already decoded from whichever notation the header used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Header {
+    pub format: RomFormat,
+    pub prg_rom_size: usize,
+    pub chr_rom_size: usize,
+    pub mapper: u16,
+    pub submapper: u8,
+    pub mirroring: Mirroring,
+    pub battery: bool,
+    pub trainer: bool,
+    /// Volatile PRG-RAM at $6000-$7FFF.
+    pub prg_ram_size: usize,
+    /// Battery backed PRG-RAM at $6000-$7FFF.
+    pub prg_nvram_size: usize,
+    pub chr_ram_size: usize,
+    pub chr_nvram_size: usize,
+    pub timing: Timing,
+}
+
+#[derive(Debug)]
+pub enum CartridgeError {
+    Io(std::io::Error),
+    /// The file does not start with "NES<EOF>".
+    NotINes,
+    /// The file ends before the end of `section`.
+    Truncated { section: &'static str, expected: usize, available: usize },
+    /// The header describes a section bigger than anything addressable.
+    SizeTooLarge { section: &'static str },
+    NoPrgRom,
+    UnsupportedMapper { mapper: u16, submapper: u8 },
+}
+
+impl Display for CartridgeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use CartridgeError::*;
+
+        match self {
+            Io(error) => write!(f, "cannot read the ROM: {}", error),
+            NotINes => write!(f, "not an iNES ROM: the file does not start with \"NES\\x1A\""),
+            Truncated { section, expected, available } => {
+                write!(f, "the ROM is truncated: the {} needs {} bytes but only {} are left", section, expected, available)
+            }
+            SizeTooLarge { section } => write!(f, "the header gives an impossible {} size", section),
+            NoPrgRom => write!(f, "the header says there is no PRG ROM"),
+            UnsupportedMapper { mapper, submapper } => write!(f, "mapper {}.{} is not supported", mapper, submapper),
+        }
+    }
+}
+
+impl std::error::Error for CartridgeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CartridgeError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for CartridgeError {
+    fn from(error: std::io::Error) -> Self {
+        CartridgeError::Io(error)
+    }
+}
+
+impl Header {
+    pub fn parse(bytes: &[u8]) -> Result<Self, CartridgeError> {
+        if bytes.len() < MAGIC.len() || &bytes[..MAGIC.len()] != MAGIC {
+            return Err(CartridgeError::NotINes);
+        }
+
+        if bytes.len() < HEADER_SIZE {
+            return Err(CartridgeError::Truncated { section: "header", expected: HEADER_SIZE, available: bytes.len() });
+        }
+
+        let flags6 = bytes[6];
+        let flags7 = bytes[7];
+
+        let format = if flags7 & 0x0C == 0x08 { RomFormat::Nes2 } else { RomFormat::INes };
+
+        let mirroring = if flags6 & 0x08 != 0 {
+            Mirroring::FourScreen
+        } else if flags6 & 0x01 != 0 {
+            Mirroring::Vertical
+        } else {
+            Mirroring::Horizontal
+        };
+
+        let mut header = Header {
+            format,
+            prg_rom_size: bytes[4] as usize * PRG_ROM_UNIT,
+            chr_rom_size: bytes[5] as usize * CHR_ROM_UNIT,
+            mapper: ((flags7 & 0xF0) | (flags6 >> 4)) as u16,
+            submapper: 0,
+            mirroring,
+            battery: flags6 & 0x02 != 0,
+            trainer: flags6 & 0x04 != 0,
+            prg_ram_size: 0,
+            prg_nvram_size: 0,
+            chr_ram_size: 0,
+            chr_nvram_size: 0,
+            timing: Timing::Ntsc,
+        };
+
+        match format {
+            RomFormat::INes => {
+                // Headers dirtied by old rippers ("DiskDude!") have garbage
+                // from byte 7 on, so only the low mapper nibble is usable.
+                if bytes[12..HEADER_SIZE].iter().any(|&b| b != 0) {
+                    header.mapper &= 0x000F;
+                }
+
+                let prg_ram_size = bytes[8].max(1) as usize * INES_PRG_RAM_UNIT;
+                if header.battery {
+                    header.prg_nvram_size = prg_ram_size;
+                } else {
+                    header.prg_ram_size = prg_ram_size;
+                }
+
+                if header.chr_rom_size == 0 {
+                    header.chr_ram_size = INES_CHR_RAM_SIZE;
+                }
+
+                if bytes[9] & 0x01 != 0 {
+                    header.timing = Timing::Pal;
+                }
+            }
+            RomFormat::Nes2 => {
+                header.mapper |= ((bytes[8] & 0x0F) as u16) << 8;
+                header.submapper = bytes[8] >> 4;
+
+                header.prg_rom_size = nes2_rom_size(bytes[4], bytes[9] & 0x0F, PRG_ROM_UNIT)
+                    .ok_or(CartridgeError::SizeTooLarge { section: "PRG ROM" })?;
+                header.chr_rom_size = nes2_rom_size(bytes[5], bytes[9] >> 4, CHR_ROM_UNIT)
+                    .ok_or(CartridgeError::SizeTooLarge { section: "CHR ROM" })?;
+
+                header.prg_ram_size = nes2_ram_size(bytes[10] & 0x0F);
+                header.prg_nvram_size = nes2_ram_size(bytes[10] >> 4);
+                header.chr_ram_size = nes2_ram_size(bytes[11] & 0x0F);
+                header.chr_nvram_size = nes2_ram_size(bytes[11] >> 4);
+
+                header.timing = match bytes[12] & 0x03 {
+                    0 => Timing::Ntsc,
+                    1 => Timing::Pal,
+                    2 => Timing::MultiRegion,
+                    _ => Timing::Dendy,
+                };
+            }
+        }
+
+        if header.prg_rom_size == 0 {
+            return Err(CartridgeError::NoPrgRom);
+        }
+
+        Ok(header)
+    }
+}
+
+/// NES 2.0 ROM sizes are either a plain 12 bit count of `unit`s or, when the
+/// high nibble is $F, 2^E * (MM * 2 + 1) bytes packed as EEEEEEMM.
+fn nes2_rom_size(lsb: u8, msb: u8, unit: usize) -> Option<usize> {
+    if msb == 0x0F {
+        let exponent = (lsb >> 2) as u32;
+        let multiplier = (lsb & 0x03) as usize * 2 + 1;
+
+        1usize.checked_shl(exponent)?.checked_mul(multiplier)
+    } else {
+        Some((((msb as usize) << 8) | lsb as usize) * unit)
+    }
+}
+
+/// NES 2.0 RAM sizes are shift counts: 64 << n bytes, or none at all for 0.
+fn nes2_ram_size(shift: u8) -> usize {
+    match shift {
+        0 => 0,
+        shift => 64 << shift,
+    }
+}
+
+/// The ROM and RAM chips on the board. Mappers decide which part of them
+/// the CPU and PPU see.
+pub struct CartridgeMemory {
+    pub prg_rom: Vec<u8>,
+    /// CHR ROM, or CHR RAM when the cartridge has none.
+    pub chr: Vec<u8>,
+    pub chr_is_ram: bool,
+    pub prg_ram: Vec<u8>,
+    /// Nametable pages 2 and 3 on four-screen boards, empty otherwise.
+    pub nametable_ram: Vec<u8>,
+}
+
+impl CartridgeMemory {
+    pub fn prg_rom_banks(&self, bank_size: usize) -> usize {
+        (self.prg_rom.len() / bank_size).max(1)
+    }
+
+    pub fn chr_banks(&self, bank_size: usize) -> usize {
+        (self.chr.len() / bank_size).max(1)
+    }
+
+    /// Reads `addr` within `bank` of `bank_size` bytes. Banks past the end
+    /// wrap around, as the unconnected high bank lines would.
+    pub fn read_prg_rom(&self, bank: usize, bank_size: usize, addr: u16) -> u8 {
+        self.prg_rom[banked(bank, bank_size, addr) % self.prg_rom.len()]
+    }
+
+    pub fn read_chr(&self, bank: usize, bank_size: usize, addr: u16) -> u8 {
+        match self.chr.len() {
+            0 => 0x00,
+            len => self.chr[banked(bank, bank_size, addr) % len],
+        }
+    }
+
+    /// Writes CHR RAM; CHR ROM ignores the write.
+    pub fn write_chr(&mut self, bank: usize, bank_size: usize, addr: u16, data: u8) {
+        if self.chr_is_ram && !self.chr.is_empty() {
+            let len = self.chr.len();
+            self.chr[banked(bank, bank_size, addr) % len] = data;
+        }
+    }
+
+    /// `bank` of PRG-RAM at $6000-$7FFF, in 8KB banks and mirrored if
+    /// smaller. Reads as 0 if there is none.
+    pub fn read_prg_ram(&self, bank: usize, addr: u16) -> u8 {
+        match self.prg_ram.len() {
+            0 => 0x00,
+            len => self.prg_ram[banked(bank, PRG_RAM_BANK_SIZE, addr) % len],
+        }
+    }
+
+    pub fn write_prg_ram(&mut self, bank: usize, addr: u16, data: u8) {
+        if !self.prg_ram.is_empty() {
+            let len = self.prg_ram.len();
+            self.prg_ram[banked(bank, PRG_RAM_BANK_SIZE, addr) % len] = data;
+        }
+    }
+}
+
+fn banked(bank: usize, bank_size: usize, addr: u16) -> usize {
+    bank * bank_size + (addr as usize & (bank_size - 1))
+}
+
+pub struct Cartridge {
+    header: Header,
+    memory: CartridgeMemory,
+    mapper: Box<dyn Mapper>,
+}
+
+impl Cartridge {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, CartridgeError> {
+        let header = Header::parse(bytes)?;
+        let mapper = mapper::new(&header)?;
+
+        let mut rest = &bytes[HEADER_SIZE..];
+        let mut take = |section, len: usize| {
+            if rest.len() < len {
+                return Err(CartridgeError::Truncated { section, expected: len, available: rest.len() });
+            }
+
+            let (taken, remaining) = rest.split_at(len);
+            rest = remaining;
+            Ok(taken)
+        };
+
+        let trainer = if header.trainer { Some(take("trainer", TRAINER_SIZE)?) } else { None };
+        let prg_rom = take("PRG ROM", header.prg_rom_size)?.to_vec();
+
+        let chr_is_ram = header.chr_rom_size == 0;
+        let chr = if chr_is_ram {
+            vec![0x00; header.chr_ram_size + header.chr_nvram_size]
+        } else {
+            take("CHR ROM", header.chr_rom_size)?.to_vec()
+        };
+
+        let mut prg_ram = vec![0x00; header.prg_ram_size + header.prg_nvram_size];
+        if let Some(trainer) = trainer {
+            let start = (TRAINER_START - PRG_RAM_START) as usize;
+
+            if prg_ram.len() < start + TRAINER_SIZE {
+                prg_ram.resize((PRG_RAM_END - PRG_RAM_START) as usize + 1, 0x00);
+            }
+
+            prg_ram[start..start + TRAINER_SIZE].copy_from_slice(trainer);
+        }
+
+        let nametable_ram = match header.mirroring {
+            Mirroring::FourScreen => vec![0x00; FOUR_SCREEN_RAM_SIZE],
+            _ => vec![],
+        };
+
+        let memory = CartridgeMemory { prg_rom, chr, chr_is_ram, prg_ram, nametable_ram };
+
+        Ok(Cartridge { header, memory, mapper })
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, CartridgeError> {
+        Self::from_bytes(&std::fs::read(path)?)
+    }
+
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+
+    /// The current layout, which some mappers switch at run time.
+    pub fn mirroring(&self) -> Mirroring {
+        self.mapper.mirroring()
+    }
+
+    pub fn prg_rom(&self) -> &[u8] {
+        &self.memory.prg_rom
+    }
+
+    pub fn chr(&self) -> &[u8] {
+        &self.memory.chr
+    }
+
+    pub fn prg_ram(&self) -> &[u8] {
+        &self.memory.prg_ram
+    }
+
+    /// The PRG-RAM to save to disk, if the cartridge has a battery.
+    pub fn battery_ram(&self) -> Option<&[u8]> {
+        match self.header.battery {
+            true => Some(&self.memory.prg_ram),
+            false => None,
+        }
+    }
+
+    /// Restores a save made from `battery_ram`. A save of the wrong size
+    /// fills as much as fits.
+    pub fn load_battery_ram(&mut self, data: &[u8]) {
+        let len = data.len().min(self.memory.prg_ram.len());
+        self.memory.prg_ram[..len].copy_from_slice(&data[..len]);
+    }
+
+    /// A PPU read of the pattern tables at $0000-$1FFF.
+    pub fn ppu_read(&mut self, addr: u16) -> u8 {
+        self.mapper.ppu_read(&self.memory, addr)
+    }
+
+    pub fn ppu_write(&mut self, addr: u16, data: u8) {
+        self.mapper.ppu_write(&mut self.memory, addr, data);
+    }
+
+    pub fn ppu_peek(&self, addr: u16) -> u8 {
+        self.mapper.ppu_peek(&self.memory, addr)
+    }
+
+    /// Reads nametable page 2 or 3 of a four-screen board; `index` counts
+    /// from the start of page 2.
+    pub fn read_nametable_ram(&self, index: usize) -> u8 {
+        self.memory.nametable_ram.get(index).copied().unwrap_or(0x00)
+    }
+
+    pub fn write_nametable_ram(&mut self, index: usize, data: u8) {
+        if let Some(byte) = self.memory.nametable_ram.get_mut(index) {
+            *byte = data;
+        }
+    }
+}
+
+impl BusDevice for Cartridge {
+    fn read(&mut self, addr: u16) -> u8 {
+        self.try_read(addr).unwrap_or(0x00)
+    }
+
+    fn try_read(&mut self, addr: u16) -> Option<u8> {
+        self.mapper.cpu_read(&self.memory, addr)
+    }
+
+    fn write(&mut self, addr: u16, data: u8) {
+        self.mapper.cpu_write(&mut self.memory, addr, data);
+    }
+
+    fn peek(&self, addr: u16) -> u8 {
+        self.try_peek(addr).unwrap_or(0x00)
+    }
+
+    fn try_peek(&self, addr: u16) -> Option<u8> {
+        self.mapper.cpu_peek(&self.memory, addr)
+    }
+
+    fn clock(&mut self) {
+        self.mapper.cpu_clock();
+    }
+
+    fn irq(&self) -> bool {
+        self.mapper.irq()
+    }
+}
@@ -0,0 +1,180 @@
+#[cfg(test)]
+mod cartridge_tests {
+    use crate::bus::*;
+    use crate::cartridge::*;
+
+    /// A ROM image with the given header bytes 4-15 and PRG/CHR banks filled
+    /// with their bank number.
+    fn rom(header: [u8; 12], prg_banks: usize, chr_banks: usize) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&header);
+
+        for bank in 0..prg_banks {
+            bytes.resize(bytes.len() + PRG_ROM_UNIT, bank as u8);
+        }
+        for bank in 0..chr_banks {
+            bytes.resize(bytes.len() + CHR_ROM_UNIT, bank as u8);
+        }
+
+        bytes
+    }
+
+    #[test]
+    fn parses_ines_headers() {
+        let header = Header::parse(&rom([2, 1, 0x43, 0x10, 0, 1, 0, 0, 0, 0, 0, 0], 2, 1)).unwrap();
+
+        assert_eq!(header, Header {
+            format: RomFormat::INes,
+            prg_rom_size: 32 * 1024,
+            chr_rom_size: 8 * 1024,
+            mapper: 0x14,
+            submapper: 0,
+            mirroring: Mirroring::Vertical,
+            battery: true,
+            trainer: false,
+            prg_ram_size: 0,
+            prg_nvram_size: 8 * 1024,
+            chr_ram_size: 0,
+            chr_nvram_size: 0,
+            timing: Timing::Pal,
+        });
+
+        let header = Header::parse(&rom([1, 0, 0x08, 0, 0, 0, 0, 0, 0, 0, 0, 0], 1, 0)).unwrap();
+        assert_eq!(header.mirroring, Mirroring::FourScreen);
+        assert_eq!(header.prg_ram_size, 8 * 1024);
+        assert_eq!(header.chr_ram_size, 8 * 1024);
+    }
+
+    #[test]
+    fn ignores_garbage_in_dirty_ines_headers() {
+        let mut bytes = rom([1, 1, 0x20, 0x40, 0, 0, 0, 0, 0, 0, 0, 0], 1, 1);
+        bytes[7..16].copy_from_slice(b"DiskDude!");
+
+        assert_eq!(Header::parse(&bytes).unwrap().mapper, 2);
+    }
+
+    #[test]
+    fn parses_nes2_headers() {
+        let header = Header::parse(&rom([2, 0, 0x12, 0x58, 0x31, 0x00, 0x70, 0x07, 0x03, 0, 0, 0], 2, 0)).unwrap();
+
+        assert_eq!(header, Header {
+            format: RomFormat::Nes2,
+            prg_rom_size: 32 * 1024,
+            chr_rom_size: 0,
+            mapper: 0x151,
+            submapper: 3,
+            mirroring: Mirroring::Horizontal,
+            battery: true,
+            trainer: false,
+            prg_ram_size: 0,
+            prg_nvram_size: 8 * 1024,
+            chr_ram_size: 8 * 1024,
+            chr_nvram_size: 0,
+            timing: Timing::Dendy,
+        });
+
+        // Exponent-multiplier notation: 2^4 * 3 bytes of PRG ROM
+        let header = Header::parse(&rom([0x11, 0, 0, 0x08, 0, 0x0F, 0, 0, 0, 0, 0, 0], 0, 0)).unwrap();
+        assert_eq!(header.prg_rom_size, 48);
+    }
+
+    #[test]
+    fn reports_broken_roms() {
+        let error = |bytes: &[u8]| Cartridge::from_bytes(bytes).err().unwrap();
+
+        assert!(matches!(error(b"PK\x03\x04"), CartridgeError::NotINes));
+        assert!(matches!(error(&MAGIC[..]), CartridgeError::Truncated { section: "header", expected: 16, available: 4 }));
+        assert!(matches!(error(&rom([0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0], 0, 1)), CartridgeError::NoPrgRom));
+        assert!(matches!(error(&rom([1, 1, 0x50, 0, 0, 0, 0, 0, 0, 0, 0, 0], 1, 1)), CartridgeError::UnsupportedMapper { mapper: 5, submapper: 0 }));
+
+        let mut truncated = rom([2, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0], 2, 1);
+        truncated.truncate(HEADER_SIZE + PRG_ROM_UNIT * 2 + 100);
+        let error = error(&truncated);
+        assert!(matches!(error, CartridgeError::Truncated { section: "CHR ROM", expected: 8192, available: 100 }));
+        assert_eq!(error.to_string(), "the ROM is truncated: the CHR ROM needs 8192 bytes but only 100 are left");
+    }
+
+    #[test]
+    fn loads_the_trainer_at_7000() {
+        let mut bytes = rom([1, 1, 0x04, 0, 0, 0, 0, 0, 0, 0, 0, 0], 0, 0);
+        bytes.extend((0..TRAINER_SIZE).map(|i| i as u8));
+        bytes.resize(bytes.len() + PRG_ROM_UNIT + CHR_ROM_UNIT, 0xEA);
+
+        let cartridge = Cartridge::from_bytes(&bytes).unwrap();
+        assert_eq!(cartridge.peek(0x7000), 0x00);
+        assert_eq!(cartridge.peek(0x7001), 0x01);
+        assert_eq!(cartridge.peek(0x71FF), 0xFF);
+        assert_eq!(cartridge.peek(0x8000), 0xEA);
+    }
+
+    #[test]
+    fn nrom_attaches_to_the_cartridge_range() {
+        let mut bytes = rom([1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0], 1, 1);
+        bytes[HEADER_SIZE + 0x3FFC] = 0x00;
+        bytes[HEADER_SIZE + 0x3FFD] = 0xC0;
+
+        let mut bus = Bus::new();
+        bus.connect_cartridge(Box::new(Cartridge::from_bytes(&bytes).unwrap()));
+
+        // 16KB of PRG ROM shows up twice
+        assert_eq!(bus.read(0xBFFC, false), 0x00);
+        assert_eq!(bus.read(0xBFFD, false), 0xC0);
+        assert_eq!(bus.read(0xFFFD, false), 0xC0);
+
+        bus.write(0x8000, 0x55);
+        assert_eq!(bus.read(0x8000, false), 0x00);
+
+        bus.write(0x6000, 0x55);
+        assert_eq!(bus.read(0x6000, false), 0x55);
+    }
+
+    #[test]
+    fn unmapped_cartridge_space_reads_open_bus() {
+        let bytes = rom([1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0], 1, 1);
+        let mut bus = Bus::new();
+        bus.connect_cartridge(Box::new(Cartridge::from_bytes(&bytes).unwrap()));
+
+        bus.write(0x0000, 0x5A);
+        assert_eq!(bus.peek(0x4020), 0x5A);
+        assert_eq!(bus.read(0x5FFF, false), 0x5A);
+    }
+
+    #[test]
+    fn battery_ram_round_trips() {
+        let mut cartridge = Cartridge::from_bytes(&rom([2, 0, 0x12, 0, 0, 0, 0, 0, 0, 0, 0, 0], 2, 0)).unwrap();
+        assert_eq!(cartridge.battery_ram().map(|ram| ram.len()), Some(8 * 1024));
+
+        cartridge.load_battery_ram(&[0x12, 0x34]);
+        assert_eq!(cartridge.peek(0x6001), 0x34);
+
+        cartridge.write(0x6002, 0x56);
+        assert_eq!(&cartridge.battery_ram().unwrap()[..3], [0x12, 0x34, 0x56]);
+
+        let cartridge = Cartridge::from_bytes(&rom([2, 0, 0x10, 0, 0, 0, 0, 0, 0, 0, 0, 0], 2, 0)).unwrap();
+        assert_eq!(cartridge.battery_ram(), None);
+    }
+
+    #[test]
+    fn nametable_pages_for_each_mirroring() {
+        let pages = |mirroring: Mirroring| [0x2000, 0x2400, 0x2BFF, 0x2C00, 0x3400].map(|addr| mirroring.nametable_page(addr));
+
+        assert_eq!(pages(Mirroring::Horizontal), [0, 0, 1, 1, 0]);
+        assert_eq!(pages(Mirroring::Vertical), [0, 1, 0, 1, 1]);
+        assert_eq!(pages(Mirroring::SingleScreenLower), [0, 0, 0, 0, 0]);
+        assert_eq!(pages(Mirroring::SingleScreenUpper), [1, 1, 1, 1, 1]);
+        assert_eq!(pages(Mirroring::FourScreen), [0, 1, 2, 3, 1]);
+    }
+
+    #[test]
+    fn four_screen_boards_bring_nametable_ram() {
+        let mut cartridge = Cartridge::from_bytes(&rom([1, 0, 0x08, 0, 0, 0, 0, 0, 0, 0, 0, 0], 1, 0)).unwrap();
+        assert_eq!(cartridge.mirroring(), Mirroring::FourScreen);
+
+        cartridge.write_nametable_ram(0x07FF, 0x42);
+        assert_eq!(cartridge.read_nametable_ram(0x07FF), 0x42);
+
+        let mut cartridge = Cartridge::from_bytes(&rom([1, 0, 0x00, 0, 0, 0, 0, 0, 0, 0, 0, 0], 1, 0)).unwrap();
+        cartridge.write_nametable_ram(0x0000, 0x42);
+        assert_eq!(cartridge.read_nametable_ram(0x0000), 0x00);
+    }
+}
@@ -0,0 +1,346 @@
+use std::fmt::Display;
+
+use fxhash::FxHashMap;
+use lazy_static::lazy_static;
+
+use super::instruction::*;
+
+lazy_static! {
+    /// `CPU_INSTRUCTIONS` turned around. Documented encodings win, then the
+    /// first one in the table.
+    static ref ENCODINGS: FxHashMap<(Opcode, AddressingMode), u8> = {
+        let mut encodings = FxHashMap::default();
+
+        for official in [true, false] {
+            for (opcode, instruction) in CPU_INSTRUCTIONS.iter().enumerate() {
+                if is_official_opcode(opcode as u8) == official {
+                    encodings.entry((instruction.opcode, instruction.addr_mode)).or_insert(opcode as u8);
+                }
+            }
+        }
+
+        encodings
+    };
+
+    static ref MNEMONICS: FxHashMap<String, Opcode> = {
+        let mut mnemonics: FxHashMap<String, Opcode> = CPU_INSTRUCTIONS
+            .iter()
+            .map(|instruction| (instruction.opcode.to_string(), instruction.opcode))
+            .collect();
+
+        // Names other assemblers and logs use
+        mnemonics.insert("JAM".to_string(), Opcode::Kil);
+        mnemonics.insert("ISB".to_string(), Opcode::Isc);
+
+        mnemonics
+    };
+}
+
+/// Why a line of source could not be assembled.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AssembleErrorKind {
+    UnknownMnemonic(String),
+    InvalidOperand(String),
+    /// The mnemonic has no addressing mode that fits the operand.
+    UnsupportedOperand { opcode: Opcode, operand: String },
+    UndefinedSymbol(String),
+    DuplicateSymbol(String),
+    /// A value that has to fit in a byte does not.
+    ValueOutOfRange(u16),
+    BranchOutOfRange { from: u16, to: u16 },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssembleError {
+    /// 1-based line number in the source.
+    pub line: usize,
+    pub kind: AssembleErrorKind,
+}
+
+impl Display for AssembleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use AssembleErrorKind::*;
+
+        write!(f, "line {}: ", self.line)?;
+
+        match &self.kind {
+            UnknownMnemonic(mnemonic) => write!(f, "unknown mnemonic `{}`", mnemonic),
+            InvalidOperand(operand) => write!(f, "cannot parse `{}`", operand),
+            UnsupportedOperand { opcode, operand } => write!(f, "{} cannot take the operand `{}`", opcode, operand),
+            UndefinedSymbol(name) => write!(f, "`{}` is not defined", name),
+            DuplicateSymbol(name) => write!(f, "`{}` is already defined", name),
+            ValueOutOfRange(value) => write!(f, "${:04X} does not fit in a byte", value),
+            BranchOutOfRange { from, to } => write!(f, "branch at ${:04X} cannot reach ${:04X}", from, to),
+        }
+    }
+}
+
+impl std::error::Error for AssembleError {}
+
+/// The shape of an operand as written, before the value is known.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Shape {
+    None,
+    Accumulator,
+    Immediate,
+    Plain,
+    IndexedX,
+    IndexedY,
+    Indirect,
+    IndirectX,
+    IndirectY,
+}
+
+enum Statement {
+    Instruction { opcode: u8, addr_mode: AddressingMode, expr: String },
+    Data { width: u16, exprs: Vec<String> },
+}
+
+/// Assembles `source` for loading at `origin`.
+///
+/// The syntax is the common one: `label:` definitions, `name = value`
+/// constants, `;` comments, `$` hex, `%` binary and decimal numbers, `<`/`>`
+/// for the low/high byte, `+`/`-` on values, `a:` to force an absolute
+/// operand, and `.byte`/`.db` and `.word`/`.dw` for data. Zero page is used
+/// whenever the value is already known to fit, so forward references always
+/// assemble to absolute addressing.
+pub fn assemble(source: &str, origin: u16) -> Result<Vec<u8>, AssembleError> {
+    let mut symbols: FxHashMap<String, u16> = FxHashMap::default();
+    let mut statements = Vec::new();
+    let mut pc = origin;
+
+    for (index, text) in source.lines().enumerate() {
+        let line = index + 1;
+        let error = |kind| AssembleError { line, kind };
+
+        let mut text = text.split(';').next().unwrap_or("").trim();
+
+        if let Some((name, rest)) = text.split_once(':') {
+            if is_identifier(name.trim()) {
+                define(&mut symbols, name.trim(), pc).map_err(error)?;
+                text = rest.trim();
+            }
+        }
+
+        if let Some((name, value)) = text.split_once('=') {
+            let value = evaluate(value.trim(), &symbols).map_err(error)?;
+            define(&mut symbols, name.trim(), value).map_err(error)?;
+            continue;
+        }
+
+        if text.is_empty() {
+            continue;
+        }
+
+        let (mnemonic, operand) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+        let operand: String = operand.chars().filter(|c| !c.is_whitespace()).collect();
+
+        let statement = match mnemonic.to_ascii_lowercase().as_str() {
+            ".byte" | ".db" => Statement::Data { width: 1, exprs: operand.split(',').map(String::from).collect() },
+            ".word" | ".dw" => Statement::Data { width: 2, exprs: operand.split(',').map(String::from).collect() },
+            _ => {
+                let opcode = *MNEMONICS
+                    .get(&mnemonic.to_ascii_uppercase())
+                    .ok_or_else(|| error(AssembleErrorKind::UnknownMnemonic(mnemonic.to_string())))?;
+                let (shape, expr) = parse_operand(&operand).map_err(error)?;
+
+                let (expr, forced_absolute) = match expr.get(..2) {
+                    Some(prefix) if prefix.eq_ignore_ascii_case("a:") => (&expr[2..], true),
+                    _ => (expr, false),
+                };
+                let value = evaluate(expr, &symbols).ok();
+
+                let addr_mode = choose_addr_mode(opcode, shape, value, forced_absolute)
+                    .ok_or_else(|| error(AssembleErrorKind::UnsupportedOperand { opcode, operand: operand.clone() }))?;
+
+                Statement::Instruction { opcode: ENCODINGS[&(opcode, addr_mode)], addr_mode, expr: expr.to_string() }
+            }
+        };
+
+        let len = match &statement {
+            Statement::Instruction { addr_mode, .. } => 1 + addr_mode.operand_len(),
+            Statement::Data { width, exprs } => width * exprs.len() as u16,
+        };
+
+        statements.push((line, pc, statement));
+        pc = pc.wrapping_add(len);
+    }
+
+    let mut bytes = Vec::new();
+
+    for (line, addr, statement) in statements {
+        let error = |kind| AssembleError { line, kind };
+
+        match statement {
+            Statement::Instruction { opcode, addr_mode, expr } => {
+                bytes.push(opcode);
+
+                let value = match addr_mode.operand_len() {
+                    0 => continue,
+                    _ => evaluate(&expr, &symbols).map_err(error)?,
+                };
+
+                match addr_mode {
+                    AddressingMode::Relative => {
+                        let offset = value.wrapping_sub(addr.wrapping_add(2)) as i16;
+                        if !(-128..=127).contains(&offset) {
+                            return Err(error(AssembleErrorKind::BranchOutOfRange { from: addr, to: value }));
+                        }
+
+                        bytes.push(offset as u8);
+                    }
+                    _ if addr_mode.operand_len() == 1 => bytes.push(byte(value).map_err(error)?),
+                    _ => bytes.extend_from_slice(&value.to_le_bytes()),
+                }
+            }
+            Statement::Data { width, exprs } => {
+                for expr in exprs {
+                    let value = evaluate(&expr, &symbols).map_err(error)?;
+
+                    match width {
+                        1 => bytes.push(byte(value).map_err(error)?),
+                        _ => bytes.extend_from_slice(&value.to_le_bytes()),
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(bytes)
+}
+
+fn define(symbols: &mut FxHashMap<String, u16>, name: &str, value: u16) -> Result<(), AssembleErrorKind> {
+    if !is_identifier(name) {
+        return Err(AssembleErrorKind::InvalidOperand(name.to_string()));
+    }
+
+    if symbols.insert(name.to_string(), value).is_some() {
+        return Err(AssembleErrorKind::DuplicateSymbol(name.to_string()));
+    }
+
+    Ok(())
+}
+
+fn is_identifier(text: &str) -> bool {
+    let mut chars = text.chars();
+
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn byte(value: u16) -> Result<u8, AssembleErrorKind> {
+    u8::try_from(value).map_err(|_| AssembleErrorKind::ValueOutOfRange(value))
+}
+
+/// Splits an operand (with whitespace already removed) into its shape and
+/// the expression inside it.
+fn parse_operand(operand: &str) -> Result<(Shape, &str), AssembleErrorKind> {
+    let upper = operand.to_ascii_uppercase();
+    let len = operand.len();
+
+    let parsed = if operand.is_empty() {
+        (Shape::None, "")
+    } else if upper == "A" {
+        (Shape::Accumulator, "")
+    } else if let Some(expr) = operand.strip_prefix('#') {
+        (Shape::Immediate, expr)
+    } else if operand.starts_with('(') {
+        if upper.ends_with(",X)") {
+            (Shape::IndirectX, &operand[1..len - 3])
+        } else if upper.ends_with("),Y") {
+            (Shape::IndirectY, &operand[1..len - 3])
+        } else if operand.ends_with(')') {
+            (Shape::Indirect, &operand[1..len - 1])
+        } else {
+            return Err(AssembleErrorKind::InvalidOperand(operand.to_string()));
+        }
+    } else if upper.ends_with(",X") {
+        (Shape::IndexedX, &operand[..len - 2])
+    } else if upper.ends_with(",Y") {
+        (Shape::IndexedY, &operand[..len - 2])
+    } else {
+        (Shape::Plain, operand)
+    };
+
+    Ok(parsed)
+}
+
+fn choose_addr_mode(opcode: Opcode, shape: Shape, value: Option<u16>, forced_absolute: bool) -> Option<AddressingMode> {
+    use AddressingMode::*;
+
+    let supports = |addr_mode| ENCODINGS.contains_key(&(opcode, addr_mode));
+    let sized = |zero_page, absolute| {
+        let fits = !forced_absolute && value.is_some_and(|value| value < 0x100);
+
+        if fits && supports(zero_page) {
+            Some(zero_page)
+        } else if supports(absolute) {
+            Some(absolute)
+        } else if !forced_absolute && supports(zero_page) {
+            Some(zero_page)
+        } else {
+            None
+        }
+    };
+
+    let addr_mode = match shape {
+        Shape::None | Shape::Accumulator => Some(Implied),
+        Shape::Immediate => Some(Immediate),
+        Shape::Plain if supports(Relative) => Some(Relative),
+        Shape::Plain => sized(ZeroPage, Absolute),
+        Shape::IndexedX => sized(ZeroPage_X, Absolute_X),
+        Shape::IndexedY => sized(ZeroPage_Y, Absolute_Y),
+        Shape::Indirect => Some(Indirect),
+        Shape::IndirectX => Some(Indirect_X),
+        Shape::IndirectY => Some(Indirect_Y),
+    };
+
+    addr_mode.filter(|&addr_mode| supports(addr_mode))
+}
+
+/// Evaluates terms joined by `+` and `-`, with an optional leading `<` or `>`.
+fn evaluate(expr: &str, symbols: &FxHashMap<String, u16>) -> Result<u16, AssembleErrorKind> {
+    let (expr, part) = match expr.chars().next() {
+        Some('<') => (&expr[1..], Some(0)),
+        Some('>') => (&expr[1..], Some(8)),
+        _ => (expr, None),
+    };
+
+    let mut total: u16 = 0;
+    let mut rest = expr;
+    let mut negative = false;
+
+    loop {
+        let end = rest.find(['+', '-']).unwrap_or(rest.len());
+        let term = evaluate_term(&rest[..end], symbols)?;
+        total = if negative { total.wrapping_sub(term) } else { total.wrapping_add(term) };
+
+        if end == rest.len() {
+            break;
+        }
+
+        negative = rest[end..].starts_with('-');
+        rest = &rest[end + 1..];
+    }
+
+    Ok(match part {
+        Some(shift) => (total >> shift) & 0x00FF,
+        None => total,
+    })
+}
+
+fn evaluate_term(term: &str, symbols: &FxHashMap<String, u16>) -> Result<u16, AssembleErrorKind> {
+    let invalid = || AssembleErrorKind::InvalidOperand(term.to_string());
+
+    if let Some(digits) = term.strip_prefix('$') {
+        u16::from_str_radix(digits, 16).map_err(|_| invalid())
+    } else if let Some(digits) = term.strip_prefix('%') {
+        u16::from_str_radix(digits, 2).map_err(|_| invalid())
+    } else if term.starts_with(|c: char| c.is_ascii_digit()) {
+        term.parse().map_err(|_| invalid())
+    } else if is_identifier(term) {
+        symbols.get(term).copied().ok_or_else(|| AssembleErrorKind::UndefinedSymbol(term.to_string()))
+    } else {
+        Err(invalid())
+    }
+}
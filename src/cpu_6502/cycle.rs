@@ -0,0 +1,514 @@
+use super::*;
+use instruction::OperandAccess::*;
+
+/// Stands in for the bus while an opcode's ALU work runs in cycle mode: the
+/// operand was already read on its own cycle, and any write is replayed on
+/// the cycle the 6502 actually performs it.
+struct OperandLatch {
+    data: u8,
+    written: Option<u8>,
+}
+
+impl BusInterface for OperandLatch {
+    fn read(&mut self, _addr: u16, _readonly: bool) -> u8 {
+        self.data
+    }
+
+    fn write(&mut self, _addr: u16, data: u8) {
+        self.written = Some(data);
+    }
+
+    fn peek(&self, _addr: u16) -> u8 {
+        self.data
+    }
+}
+
+impl Cpu {
+    /// One cycle of `ExecutionMode::Cycle`: exactly one bus access, dummy
+    /// reads and the double write of read-modify-write instructions included.
+    pub(super) fn clock_cycle(&mut self, bus: &mut impl BusInterface) {
+        self.step += 1;
+
+        // Interrupt sequences (BRK included) never poll, so the handler's
+        // first instruction always runs. A taken branch skips the poll on
+        // its third cycle.
+        let polls = match CPU_INSTRUCTIONS[self.opcode as usize] {
+            _ if self.interrupt_vector.is_some() => false,
+            _ if self.step == 1 => true,
+            Instruction { opcode: Opcode::Brk, .. } => false,
+            Instruction { addr_mode: AddressingMode::Relative, .. } => self.step != 3,
+            _ => true,
+        };
+        if polls {
+            self.poll_interrupts();
+        }
+
+        let done = if let Some(vector) = self.interrupt_vector {
+            self.interrupt_cycle(bus, vector)
+        }
+        else if self.step == 1 {
+            self.opcode = self.read(bus, self.pc);
+            self.pc = self.pc.wrapping_add(1);
+            self.set_flag(Flags6502::Unused, true);
+
+            false
+        }
+        else {
+            self.instruction_cycle(bus)
+        };
+
+        if done {
+            self.step = 0;
+            self.interrupt_vector = None;
+        }
+    }
+
+    /// Whether the access `clock_cycle` is about to make is a read, which
+    /// is the only kind of cycle DMA can halt the CPU on.
+    pub(super) fn next_cycle_reads(&self) -> bool {
+        use AddressingMode::*;
+        use Opcode::*;
+
+        let step = self.step + 1;
+        if step == 1 {
+            return true;
+        }
+        if self.interrupt_vector.is_some() {
+            return !(3..=5).contains(&step);
+        }
+
+        let instruction = &CPU_INSTRUCTIONS[self.opcode as usize];
+        let operand_step = match (&instruction.opcode, &instruction.addr_mode) {
+            (Brk, _) => return !(3..=5).contains(&step),
+            (Jsr, _) => return !(4..=5).contains(&step),
+            (Pha, _) | (Php, _) => return step != 3,
+            (Rti, _) | (Rts, _) | (Pla, _) | (Plp, _) | (Jmp, _) => return true,
+            (_, Implied) | (_, Immediate) | (_, Relative) | (_, Indirect) => return true,
+            (_, ZeroPage) => 3,
+            (_, ZeroPage_X) | (_, ZeroPage_Y) | (_, Absolute) => 4,
+            (_, Absolute_X) | (_, Absolute_Y) => 5,
+            (_, Indirect_X) | (_, Indirect_Y) => 6,
+        };
+
+        match instruction.opcode.operand_access() {
+            Read => true,
+            Write => step != operand_step,
+            ReadModifyWrite => !(operand_step + 1..=operand_step + 2).contains(&step),
+        }
+    }
+
+    fn interrupt_cycle(&mut self, bus: &mut impl BusInterface, vector: u16) -> bool {
+        match self.step {
+            1 | 2 => {
+                self.read(bus, self.pc);
+            }
+            3 => self.push(bus, (self.pc >> 8) as u8),
+            4 => self.push(bus, self.pc as u8),
+            5 => {
+                let vector = self.hijack_vector(vector);
+                self.interrupt_vector = Some(vector);
+
+                self.set_flag(Flags6502::BreakCommand, false);
+                self.set_flag(Flags6502::Unused, true);
+                self.push(bus, self.status);
+                self.set_flag(Flags6502::InterruptDisable, true);
+            }
+            6 => self.pc = self.read(bus, vector) as u16,
+            _ => {
+                self.pc |= (self.read(bus, vector.wrapping_add(1)) as u16) << 8;
+                return true;
+            }
+        }
+
+        false
+    }
+
+    fn instruction_cycle(&mut self, bus: &mut impl BusInterface) -> bool {
+        use AddressingMode::*;
+        use Opcode::*;
+
+        let instruction = &CPU_INSTRUCTIONS[self.opcode as usize];
+
+        match (&instruction.opcode, &instruction.addr_mode) {
+            (Brk, _) => self.brk_cycle(bus),
+            (Jsr, _) => self.jsr_cycle(bus),
+            (Rti, _) => self.rti_cycle(bus),
+            (Rts, _) => self.rts_cycle(bus),
+            (Pha, _) | (Php, _) => self.push_cycle(bus),
+            (Pla, _) | (Plp, _) => self.pull_cycle(bus),
+            (Jmp, Absolute) => self.jmp_absolute_cycle(bus),
+            (Jmp, Indirect) => self.jmp_indirect_cycle(bus),
+            (_, Relative) => self.branch_cycle(bus),
+            (opcode, addr_mode) => {
+                let access = opcode.operand_access();
+                self.addressed_cycle(bus, addr_mode, access)
+            }
+        }
+    }
+
+    /// Runs the opcode's ALU work on an operand that is already on hand,
+    /// returning what it wanted to write, if anything.
+    fn execute(&mut self, data: u8) -> Option<u8> {
+        let mut latch = OperandLatch { data, written: None };
+
+        if CPU_INSTRUCTIONS[self.opcode as usize].addr_mode == AddressingMode::Implied {
+            self.fetched = self.a_reg;
+        }
+        CPU_INSTRUCTIONS[self.opcode as usize].opcode.opcode_operation(self, &mut latch);
+
+        latch.written
+    }
+
+    fn fetch_operand(&mut self, bus: &mut impl BusInterface) -> u8 {
+        let data = self.read(bus, self.pc);
+        self.pc = self.pc.wrapping_add(1);
+
+        data
+    }
+
+    fn addressed_cycle(&mut self, bus: &mut impl BusInterface, addr_mode: &AddressingMode, access: OperandAccess) -> bool {
+        use AddressingMode::*;
+
+        // Cycles left once the effective address is known: 1 to read or
+        // write it, 3 to read, write back unchanged and write the result.
+        let operand_step = match (addr_mode, self.step) {
+            (Implied, _) => {
+                self.read(bus, self.pc);
+                self.execute(self.a_reg);
+                return true;
+            }
+            (Immediate, _) => {
+                self.addr_abs = self.pc;
+                let data = self.fetch_operand(bus);
+                self.execute(data);
+                return true;
+            }
+            (ZeroPage, 2) => {
+                self.addr_abs = self.fetch_operand(bus) as u16;
+                return false;
+            }
+            (ZeroPage, _) => 3,
+            (ZeroPage_X, 2) | (ZeroPage_Y, 2) | (Indirect_X, 2) | (Indirect_Y, 2) => {
+                self.ptr = self.fetch_operand(bus) as u16;
+                return false;
+            }
+            (ZeroPage_X, 3) | (ZeroPage_Y, 3) => {
+                self.read(bus, self.ptr);
+                let index = if *addr_mode == ZeroPage_X { self.x_reg } else { self.y_reg };
+                self.addr_abs = (self.ptr as u8).wrapping_add(index) as u16;
+                return false;
+            }
+            (ZeroPage_X, _) | (ZeroPage_Y, _) => 4,
+            (Absolute, 2) | (Absolute_X, 2) | (Absolute_Y, 2) => {
+                self.ptr = self.fetch_operand(bus) as u16;
+                return false;
+            }
+            (Absolute, 3) => {
+                self.addr_abs = ((self.fetch_operand(bus) as u16) << 8) | self.ptr;
+                return false;
+            }
+            (Absolute, _) => 4,
+            (Absolute_X, 3) | (Absolute_Y, 3) => {
+                self.ptr |= (self.fetch_operand(bus) as u16) << 8;
+                let index = if *addr_mode == Absolute_X { self.x_reg } else { self.y_reg };
+                self.addr_abs = self.ptr.wrapping_add(index as u16);
+                return false;
+            }
+            (Indirect_X, 3) => {
+                self.read(bus, self.ptr);
+                self.ptr = (self.ptr as u8).wrapping_add(self.x_reg) as u16;
+                return false;
+            }
+            (Indirect_X, 4) => {
+                self.addr_abs = self.read(bus, self.ptr) as u16;
+                return false;
+            }
+            (Indirect_X, 5) => {
+                self.addr_abs |= (self.read(bus, (self.ptr as u8).wrapping_add(1) as u16) as u16) << 8;
+                return false;
+            }
+            (Indirect_X, _) => 6,
+            (Indirect_Y, 3) => {
+                self.addr_abs = self.read(bus, self.ptr) as u16;
+                return false;
+            }
+            (Indirect_Y, 4) => {
+                let hi = self.read(bus, (self.ptr as u8).wrapping_add(1) as u16) as u16;
+                self.ptr = (hi << 8) | self.addr_abs;
+                self.addr_abs = self.ptr.wrapping_add(self.y_reg as u16);
+                return false;
+            }
+            (Absolute_X, 4) | (Absolute_Y, 4) | (Indirect_Y, 5) => {
+                // The low byte has been indexed but the carry into the high
+                // byte is a cycle late, so this read may hit the wrong page.
+                let page_crossed = (self.ptr & 0xFF00) != (self.addr_abs & 0xFF00);
+                let partial = (self.ptr & 0xFF00) | (self.addr_abs & 0x00FF);
+
+                if access == Read && !page_crossed {
+                    let data = self.read(bus, self.addr_abs);
+                    self.execute(data);
+                    return true;
+                }
+
+                self.read(bus, partial);
+                return false;
+            }
+            (Absolute_X, _) | (Absolute_Y, _) => 5,
+            (Indirect_Y, _) => 6,
+            (Relative, _) | (Indirect, _) => unreachable!("handled by their own cycle sequences"),
+        };
+
+        match (access, self.step - operand_step) {
+            (Read, _) => {
+                let data = self.read(bus, self.addr_abs);
+                self.execute(data);
+                true
+            }
+            (Write, _) => {
+                if let Some(data) = self.execute(0x00) {
+                    self.write(bus, self.addr_abs, data);
+                }
+                else {
+                    // Unstable stores disabled with `UnstableOpcodes::Nop`.
+                    self.read(bus, self.addr_abs);
+                }
+                true
+            }
+            (ReadModifyWrite, 0) => {
+                self.fetched = self.read(bus, self.addr_abs);
+                false
+            }
+            (ReadModifyWrite, 1) => {
+                self.write(bus, self.addr_abs, self.fetched);
+                self.fetched = self.execute(self.fetched).unwrap_or(self.fetched);
+                false
+            }
+            (ReadModifyWrite, _) => {
+                self.write(bus, self.addr_abs, self.fetched);
+                true
+            }
+        }
+    }
+
+    fn branch_condition(&self) -> bool {
+        use Opcode::*;
+
+        match CPU_INSTRUCTIONS[self.opcode as usize].opcode {
+            Bcc => self.get_flag(Flags6502::Carry) == 0,
+            Bcs => self.get_flag(Flags6502::Carry) == 1,
+            Bne => self.get_flag(Flags6502::Zero) == 0,
+            Beq => self.get_flag(Flags6502::Zero) == 1,
+            Bpl => self.get_flag(Flags6502::Negative) == 0,
+            Bmi => self.get_flag(Flags6502::Negative) == 1,
+            Bvc => self.get_flag(Flags6502::Overflow) == 0,
+            Bvs => self.get_flag(Flags6502::Overflow) == 1,
+            _ => unreachable!("only branches use relative addressing"),
+        }
+    }
+
+    fn branch_cycle(&mut self, bus: &mut impl BusInterface) -> bool {
+        match self.step {
+            2 => {
+                self.addr_rel = self.fetch_operand(bus) as i8 as u16;
+                !self.branch_condition()
+            }
+            3 => {
+                self.read(bus, self.pc);
+                self.addr_abs = self.pc.wrapping_add(self.addr_rel);
+                let page_crossed = (self.addr_abs & 0xFF00) != (self.pc & 0xFF00);
+                self.pc = (self.pc & 0xFF00) | (self.addr_abs & 0x00FF);
+                !page_crossed
+            }
+            _ => {
+                self.read(bus, self.pc);
+                self.pc = self.addr_abs;
+                true
+            }
+        }
+    }
+
+    fn jmp_absolute_cycle(&mut self, bus: &mut impl BusInterface) -> bool {
+        match self.step {
+            2 => {
+                self.ptr = self.fetch_operand(bus) as u16;
+                false
+            }
+            _ => {
+                self.pc = ((self.read(bus, self.pc) as u16) << 8) | self.ptr;
+                true
+            }
+        }
+    }
+
+    fn jmp_indirect_cycle(&mut self, bus: &mut impl BusInterface) -> bool {
+        match self.step {
+            2 => {
+                self.ptr = self.fetch_operand(bus) as u16;
+                false
+            }
+            3 => {
+                self.ptr |= (self.fetch_operand(bus) as u16) << 8;
+                false
+            }
+            4 => {
+                self.addr_abs = self.read(bus, self.ptr) as u16;
+                false
+            }
+            _ => {
+                let ptr_next = (self.ptr & 0xFF00) | (self.ptr.wrapping_add(1) & 0x00FF);
+                self.pc = ((self.read(bus, ptr_next) as u16) << 8) | self.addr_abs;
+                true
+            }
+        }
+    }
+
+    fn jsr_cycle(&mut self, bus: &mut impl BusInterface) -> bool {
+        match self.step {
+            2 => {
+                self.ptr = self.fetch_operand(bus) as u16;
+                false
+            }
+            3 => {
+                self.read(bus, STACK_BASE + self.stk_ptr as u16);
+                false
+            }
+            4 => {
+                self.push(bus, (self.pc >> 8) as u8);
+                false
+            }
+            5 => {
+                self.push(bus, self.pc as u8);
+                false
+            }
+            _ => {
+                self.pc = ((self.read(bus, self.pc) as u16) << 8) | self.ptr;
+                true
+            }
+        }
+    }
+
+    fn rts_cycle(&mut self, bus: &mut impl BusInterface) -> bool {
+        match self.step {
+            2 => {
+                self.read(bus, self.pc);
+                false
+            }
+            3 => {
+                self.read(bus, STACK_BASE + self.stk_ptr as u16);
+                false
+            }
+            4 => {
+                self.pc = self.pull(bus) as u16;
+                false
+            }
+            5 => {
+                self.pc |= (self.pull(bus) as u16) << 8;
+                false
+            }
+            _ => {
+                self.read(bus, self.pc);
+                self.pc = self.pc.wrapping_add(1);
+                true
+            }
+        }
+    }
+
+    fn rti_cycle(&mut self, bus: &mut impl BusInterface) -> bool {
+        match self.step {
+            2 => {
+                self.read(bus, self.pc);
+                false
+            }
+            3 => {
+                self.read(bus, STACK_BASE + self.stk_ptr as u16);
+                false
+            }
+            4 => {
+                self.status = self.pull(bus);
+                self.set_flag(Flags6502::BreakCommand, false);
+                self.set_flag(Flags6502::Unused, true);
+                false
+            }
+            5 => {
+                self.pc = self.pull(bus) as u16;
+                false
+            }
+            _ => {
+                self.pc |= (self.pull(bus) as u16) << 8;
+                true
+            }
+        }
+    }
+
+    fn brk_cycle(&mut self, bus: &mut impl BusInterface) -> bool {
+        match self.step {
+            2 => {
+                self.fetch_operand(bus);
+                false
+            }
+            3 => {
+                self.push(bus, (self.pc >> 8) as u8);
+                false
+            }
+            4 => {
+                self.push(bus, self.pc as u8);
+                false
+            }
+            5 => {
+                self.ptr = self.hijack_vector(IRQ_BASE);
+                self.push(bus, self.status | Flags6502::BreakCommand as u8 | Flags6502::Unused as u8);
+                self.set_flag(Flags6502::InterruptDisable, true);
+                false
+            }
+            6 => {
+                self.pc = self.read(bus, self.ptr) as u16;
+                false
+            }
+            _ => {
+                self.pc |= (self.read(bus, self.ptr.wrapping_add(1)) as u16) << 8;
+                true
+            }
+        }
+    }
+
+    fn push_cycle(&mut self, bus: &mut impl BusInterface) -> bool {
+        match self.step {
+            2 => {
+                self.read(bus, self.pc);
+                false
+            }
+            _ => {
+                let data = if CPU_INSTRUCTIONS[self.opcode as usize].opcode == Opcode::Php {
+                    self.status | Flags6502::BreakCommand as u8 | Flags6502::Unused as u8
+                }
+                else {
+                    self.a_reg
+                };
+
+                self.push(bus, data);
+                true
+            }
+        }
+    }
+
+    fn pull_cycle(&mut self, bus: &mut impl BusInterface) -> bool {
+        match self.step {
+            2 => {
+                self.read(bus, self.pc);
+                false
+            }
+            3 => {
+                self.read(bus, STACK_BASE + self.stk_ptr as u16);
+                false
+            }
+            _ => {
+                let stk_ptr = self.stk_ptr;
+                let data = self.read(bus, STACK_BASE + stk_ptr.wrapping_add(1) as u16);
+
+                let opcode = &CPU_INSTRUCTIONS[self.opcode as usize].opcode;
+                opcode.opcode_operation(self, &mut OperandLatch { data, written: None });
+                true
+            }
+        }
+    }
+}
@@ -0,0 +1,227 @@
+use std::fmt::Display;
+
+use crate::bus::BusInterface;
+use super::instruction::*;
+
+/// One decoded instruction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DisassembledInstruction {
+    pub addr: u16,
+    /// The opcode followed by its operand bytes.
+    pub bytes: Vec<u8>,
+    pub opcode: Opcode,
+    pub addr_mode: AddressingMode,
+    /// The bytes after the opcode, little endian; `None` for implied and
+    /// accumulator instructions.
+    pub operand: Option<u16>,
+    /// Where a branch goes when taken.
+    pub target: Option<u16>,
+}
+
+impl DisassembledInstruction {
+    /// Decodes the instruction at `addr`, fetching its bytes with `read`.
+    pub fn decode(addr: u16, mut read: impl FnMut(u16) -> u8) -> Self {
+        let opcode = read(addr);
+        let instruction = &CPU_INSTRUCTIONS[opcode as usize];
+
+        let mut bytes = vec![opcode];
+        for i in 1..=instruction.addr_mode.operand_len() {
+            bytes.push(read(addr.wrapping_add(i)));
+        }
+
+        let operand = match bytes.len() {
+            2 => Some(bytes[1] as u16),
+            3 => Some(((bytes[2] as u16) << 8) | bytes[1] as u16),
+            _ => None,
+        };
+
+        let target = match (instruction.addr_mode, operand) {
+            (AddressingMode::Relative, Some(offset)) => Some(addr.wrapping_add(2).wrapping_add(offset as u8 as i8 as u16)),
+            _ => None,
+        };
+
+        DisassembledInstruction {
+            addr,
+            bytes,
+            opcode: instruction.opcode,
+            addr_mode: instruction.addr_mode,
+            operand,
+            target,
+        }
+    }
+
+    pub fn is_official(&self) -> bool {
+        is_official_opcode(self.bytes[0])
+    }
+
+    /// Shifts and rotates that work on A rather than memory.
+    pub fn is_accumulator(&self) -> bool {
+        use Opcode::*;
+
+        self.addr_mode == AddressingMode::Implied && matches!(self.opcode, Asl | Lsr | Rol | Ror)
+    }
+
+    pub fn format(&self, syntax: &impl Syntax) -> String {
+        syntax.format(self)
+    }
+
+    /// The operand in the usual `$nn`/`$nnnn` notation. `absolute_prefix`
+    /// goes in front of absolute operands that would fit in zero page.
+    fn operand_text(&self, absolute_prefix: &str) -> String {
+        use AddressingMode::*;
+
+        let operand = self.operand.unwrap_or(0);
+
+        match self.addr_mode {
+            Implied if self.is_accumulator() => "A".to_string(),
+            Implied => String::new(),
+            Immediate => format!("#${:02X}", operand),
+            ZeroPage => format!("${:02X}", operand),
+            ZeroPage_X => format!("${:02X},X", operand),
+            ZeroPage_Y => format!("${:02X},Y", operand),
+            Relative => format!("${:04X}", self.target.unwrap_or(0)),
+            Absolute | Absolute_X | Absolute_Y => {
+                let prefix = if operand < 0x100 { absolute_prefix } else { "" };
+                let index = match self.addr_mode {
+                    Absolute_X => ",X",
+                    Absolute_Y => ",Y",
+                    _ => "",
+                };
+
+                format!("{}${:04X}{}", prefix, operand, index)
+            }
+            Indirect => format!("(${:04X})", operand),
+            Indirect_X => format!("(${:02X},X)", operand),
+            Indirect_Y => format!("(${:02X}),Y", operand),
+        }
+    }
+
+    /// Whether another encoding of the same mnemonic and addressing mode
+    /// comes first in the opcode table, so an assembler would not pick this one.
+    fn has_preferred_encoding(&self) -> bool {
+        CPU_INSTRUCTIONS
+            .iter()
+            .position(|i| i.opcode == self.opcode && i.addr_mode == self.addr_mode)
+            != Some(self.bytes[0] as usize)
+    }
+
+    fn byte_list(&self, directive: &str) -> String {
+        let bytes = self.bytes.iter().map(|b| format!("${:02X}", b)).collect::<Vec<String>>();
+
+        format!("{} {}", directive, bytes.join(","))
+    }
+}
+
+impl Display for DisassembledInstruction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", Nesdev.format(self))
+    }
+}
+
+/// Turns a `DisassembledInstruction` into a line of source.
+pub trait Syntax {
+    fn format(&self, instruction: &DisassembledInstruction) -> String;
+}
+
+/// ca65 with `.setcpu "6502X"`: lowercase, `a:` on absolute operands that
+/// fit in zero page, and `.byte` for encodings it cannot produce.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Ca65;
+
+/// asm6: uppercase, official opcodes only, `.db` for anything it would
+/// assemble differently.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Asm6;
+
+/// The nestest log layout: address, raw bytes, then the instruction with
+/// unofficial opcodes marked by `*`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Nesdev;
+
+impl Syntax for Ca65 {
+    fn format(&self, instruction: &DisassembledInstruction) -> String {
+        use Opcode::*;
+
+        let unstable = matches!(instruction.opcode, Nop | Xaa | Lxa | Ahx | Tas | Shx | Shy);
+        if !instruction.is_official() && (unstable || instruction.has_preferred_encoding()) {
+            return instruction.byte_list(".byte").to_lowercase();
+        }
+
+        let mnemonic = match instruction.opcode {
+            Kil => "JAM".to_string(),
+            opcode => opcode.to_string(),
+        };
+
+        let line = match instruction.operand_text("a:") {
+            operand if operand.is_empty() => mnemonic,
+            operand => format!("{} {}", mnemonic, operand),
+        };
+
+        line.to_lowercase()
+    }
+}
+
+impl Syntax for Asm6 {
+    fn format(&self, instruction: &DisassembledInstruction) -> String {
+        use AddressingMode::*;
+
+        let shrinks_to_zero_page = matches!(instruction.addr_mode, Absolute | Absolute_X | Absolute_Y)
+            && instruction.operand.unwrap_or(0) < 0x100
+            && !matches!(instruction.opcode, Opcode::Jmp | Opcode::Jsr);
+
+        if !instruction.is_official() || shrinks_to_zero_page {
+            return instruction.byte_list(".db");
+        }
+
+        match instruction.operand_text("") {
+            operand if operand.is_empty() => instruction.opcode.to_string(),
+            operand => format!("{} {}", instruction.opcode, operand),
+        }
+    }
+}
+
+impl Syntax for Nesdev {
+    fn format(&self, instruction: &DisassembledInstruction) -> String {
+        let bytes = instruction.bytes.iter().map(|b| format!("{:02X}", b)).collect::<Vec<String>>();
+        let marker = if instruction.is_official() { ' ' } else { '*' };
+
+        let line = format!("{:04X}  {:<8} {}{} {}", instruction.addr, bytes.join(" "), marker, instruction.opcode, instruction.operand_text(""));
+
+        line.trim_end().to_string()
+    }
+}
+
+/// Decodes `start..=stop` through `BusInterface::peek`, so nothing on the bus
+/// notices. The last instruction may run past `stop`.
+pub fn disassemble(bus: &impl BusInterface, start: u16, stop: u16) -> Vec<DisassembledInstruction> {
+    let mut instructions = Vec::new();
+    let mut addr = start as u32;
+
+    while addr <= stop as u32 {
+        let instruction = DisassembledInstruction::decode(addr as u16, |a| bus.peek(a));
+        addr += instruction.bytes.len() as u32;
+        instructions.push(instruction);
+    }
+
+    instructions
+}
+
+/// Decodes `bytes` as if loaded at `origin`. A trailing instruction whose
+/// operand is cut off is left out.
+pub fn disassemble_bytes(bytes: &[u8], origin: u16) -> Vec<DisassembledInstruction> {
+    let mut instructions = Vec::new();
+    let mut offset = 0;
+
+    while offset < bytes.len() {
+        let len = 1 + CPU_INSTRUCTIONS[bytes[offset] as usize].addr_mode.operand_len() as usize;
+        if offset + len > bytes.len() {
+            break;
+        }
+
+        let instruction = DisassembledInstruction::decode(origin.wrapping_add(offset as u16), |a| bytes[a.wrapping_sub(origin) as usize]);
+        offset += len;
+        instructions.push(instruction);
+    }
+
+    instructions
+}
@@ -22,7 +22,7 @@ impl Instruction {
 
 //Addressing modes
 #[allow(non_camel_case_types)]
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum AddressingMode {
     Implied,
     Immediate,
@@ -39,7 +39,18 @@ pub enum AddressingMode {
 }
 
 impl AddressingMode {
-    pub fn addr_mode_operation(&self, cpu: &mut Cpu) -> u8 {
+    /// How many bytes follow the opcode.
+    pub fn operand_len(&self) -> u16 {
+        use self::AddressingMode::*;
+
+        match self {
+            Implied => 0,
+            Immediate | ZeroPage | ZeroPage_X | ZeroPage_Y | Relative | Indirect_X | Indirect_Y => 1,
+            Absolute | Absolute_X | Absolute_Y | Indirect => 2,
+        }
+    }
+
+    pub fn addr_mode_operation(&self, cpu: &mut Cpu, bus: &mut impl BusInterface) -> u8 {
         use self::AddressingMode::*;
 
         match self {
@@ -49,35 +60,32 @@ impl AddressingMode {
                 0
              }
              Immediate => {
-                cpu.pc += 1;
                 cpu.addr_abs = cpu.pc;
+                cpu.pc = cpu.pc.wrapping_add(1);
 
                 0                
              }
              ZeroPage => {
-                cpu.addr_abs = cpu.read(cpu.pc) as u16;
-                cpu.pc += 1;
-                cpu.addr_abs &= 0x00FF;
+                cpu.addr_abs = cpu.read(bus, cpu.pc) as u16;
+                cpu.pc = cpu.pc.wrapping_add(1);
 
                 0
              }
              ZeroPage_X => {
-                cpu.addr_abs = cpu.read(cpu.pc + (cpu.x_reg as u16)) as u16;
-                cpu.pc += 1;
-                cpu.addr_abs &= 0x00FF;
+                cpu.addr_abs = cpu.read(bus, cpu.pc).wrapping_add(cpu.x_reg) as u16;
+                cpu.pc = cpu.pc.wrapping_add(1);
 
                 0
              }
              ZeroPage_Y => {
-                cpu.addr_abs = cpu.read(cpu.pc + (cpu.y_reg as u16)) as u16;
-                cpu.pc += 1;
-                cpu.addr_abs &= 0x00FF;
+                cpu.addr_abs = cpu.read(bus, cpu.pc).wrapping_add(cpu.y_reg) as u16;
+                cpu.pc = cpu.pc.wrapping_add(1);
 
                 0
              }
              Relative => {
-                cpu.addr_rel = cpu.read(cpu.pc) as u16;
-                cpu.pc += 1;
+                cpu.addr_rel = cpu.read(bus, cpu.pc) as u16;
+                cpu.pc = cpu.pc.wrapping_add(1);
 
                 if cpu.addr_rel & 0x80 != 0 {
                     cpu.addr_rel |= 0xFF00;
@@ -86,94 +94,77 @@ impl AddressingMode {
                 0
              }
              Absolute => {
-                let lo: u16 = cpu.read(cpu.pc) as u16;
-                cpu.pc += 1;
-                let hi: u16 = cpu.read(cpu.pc) as u16;
-                cpu.pc += 1;
-
-                cpu.addr_abs = hi << 8 | lo;
+                cpu.addr_abs = cpu.read_word(bus, cpu.pc);
+                cpu.pc = cpu.pc.wrapping_add(2);
 
                 0
              }
              Absolute_X => {
-                let lo: u16 = cpu.read(cpu.pc) as u16;
-                cpu.pc += 1;
-                let hi: u16 = cpu.read(cpu.pc) as u16;
-                cpu.pc += 1;
+                let base = cpu.read_word(bus, cpu.pc);
+                cpu.pc = cpu.pc.wrapping_add(2);
 
-                cpu.addr_abs = (hi << 8 | lo) + cpu.x_reg as u16;
+                cpu.addr_abs = base.wrapping_add(cpu.x_reg as u16);
 
-                if (cpu.addr_abs & 0xFF00) != (hi << 8) {
-                    return 1;
+                if (cpu.addr_abs & 0xFF00) != (base & 0xFF00) {
+                    1
                 } 
                 else {
-                    return 0;
+                    0
                 }
              }
              Absolute_Y => {
-                let lo: u16 = cpu.read(cpu.pc) as u16;
-                cpu.pc += 1;
-                let hi: u16 = cpu.read(cpu.pc) as u16;
-                cpu.pc += 1;
+                let base = cpu.read_word(bus, cpu.pc);
+                cpu.pc = cpu.pc.wrapping_add(2);
 
-                cpu.addr_abs = (hi << 8 | lo) + cpu.y_reg as u16;
+                cpu.addr_abs = base.wrapping_add(cpu.y_reg as u16);
 
-                if (cpu.addr_abs & 0xFF00) != (hi << 8) {
-                    return 1;
+                if (cpu.addr_abs & 0xFF00) != (base & 0xFF00) {
+                    1
                 } 
                 else {
-                    return 0;
+                    0
                 }
              }
              Indirect => {
-                let ptr_lo: u16 = cpu.read(cpu.pc) as u16;
-                cpu.pc += 1;
-                let ptr_hi: u16 = cpu.read(cpu.pc) as u16;
-                cpu.pc += 1;
+                let ptr = cpu.read_word(bus, cpu.pc);
+                cpu.pc = cpu.pc.wrapping_add(2);
 
-                let ptr: u16 = (ptr_hi << 8) | ptr_lo;
+                // The pointer's high byte is never carried into: JMP ($xxFF)
+                // fetches its high byte from $xx00.
+                let ptr_next = (ptr & 0xFF00) | (ptr.wrapping_add(1) & 0x00FF);
 
-                if ptr_lo == 0x00FF {
-                    cpu.addr_abs = ((cpu.read(ptr & 0xFF00) as u16) << 8) | (cpu.read(ptr + 0) as u16);
-                }
-                else {
-                    cpu.addr_abs = ((cpu.read(ptr + 1) as u16) << 8) | (cpu.read(ptr + 0) as u16);
-                }
+                cpu.addr_abs = ((cpu.read(bus, ptr_next) as u16) << 8) | (cpu.read(bus, ptr) as u16);
 
                 0
              }
              Indirect_X => {
-                let temp = cpu.read(cpu.pc) as u16;
-                cpu.pc += 1;
-
-                let lo = cpu.read((temp + (cpu.x_reg as u16)) & 0x00FF) as u16;
-                let hi = cpu.read((temp + (cpu.x_reg as u16) + 1) & 0x00FF) as u16;
+                let ptr = cpu.read(bus, cpu.pc).wrapping_add(cpu.x_reg);
+                cpu.pc = cpu.pc.wrapping_add(1);
 
-                cpu.addr_abs = (hi << 8) | lo;
+                cpu.addr_abs = cpu.read_zero_page_word(bus, ptr);
 
-                1
+                0
              }
              Indirect_Y => {
-                let temp = cpu.read(cpu.pc) as u16;
-                cpu.pc += 1;
+                let ptr = cpu.read(bus, cpu.pc);
+                cpu.pc = cpu.pc.wrapping_add(1);
 
-                let lo = cpu.read(temp & 0x00FF) as u16;
-                let hi = cpu.read((temp + 1) & 0x00FF) as u16;
+                let base = cpu.read_zero_page_word(bus, ptr);
 
-                cpu.addr_abs = ((hi << 8) | lo) + cpu.x_reg as u16;
+                cpu.addr_abs = base.wrapping_add(cpu.y_reg as u16);
 
-                if cpu.addr_abs & 0xFF00 != (hi << 8) {
-                    return 1;
+                if (cpu.addr_abs & 0xFF00) != (base & 0xFF00) {
+                    1
                 }
                 else {
-                    return 0;
+                    0
                 }
              }
         }
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Opcode {
     Adc,    //Add with carry
     And,    //Logical AND
@@ -231,32 +222,75 @@ pub enum Opcode {
     Txa,
     Txs,
     Tya,
-    Kil
+    Kil,    //Jam the CPU
+
+    // Stable unofficial opcodes
+    Lax,    //Load A and X
+    Sax,    //Store A AND X
+    Dcp,    //Decrement memory then compare
+    Isc,    //Increment memory then subtract with carry
+    Slo,    //Shift left then OR
+    Rla,    //Rotate left then AND
+    Sre,    //Shift right then exclusive OR
+    Rra,    //Rotate right then add with carry
+    Anc,    //AND then copy N into C
+    Alr,    //AND then shift right
+    Arr,    //AND then rotate right
+    Axs,    //X = (A AND X) - immediate
+    Las,    //A = X = SP = memory AND SP
+
+    // Unstable unofficial opcodes, see `UnstableOpcodes`
+    Xaa,    //A = (A OR magic) AND X AND immediate
+    Lxa,    //A = X = (A OR magic) AND immediate
+    Ahx,    //Store A AND X AND (high byte + 1)
+    Tas,    //SP = A AND X, store SP AND (high byte + 1)
+    Shx,    //Store X AND (high byte + 1)
+    Shy,    //Store Y AND (high byte + 1)
+}
+
+/// What an instruction does with its memory operand, which decides the
+/// shape of its bus cycles in `ExecutionMode::Cycle`.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum OperandAccess {
+    Read,
+    Write,
+    ReadModifyWrite,
 }
 
 impl Opcode {
-    pub fn opcode_operation(&self, cpu: &mut Cpu) -> u8 {
+    /// Whether this mnemonic is documented. Some official mnemonics also
+    /// have unofficial encodings, see `is_official_opcode`.
+    pub fn is_official(&self) -> bool {
+        use self::Opcode::*;
+
+        !matches!(self, Kil | Lax | Sax | Dcp | Isc | Slo | Rla | Sre | Rra | Anc | Alr | Arr | Axs | Las | Xaa | Lxa | Ahx | Tas | Shx | Shy)
+    }
+
+    pub fn operand_access(&self) -> OperandAccess {
+        use self::Opcode::*;
+
+        match self {
+            Sta | Stx | Sty | Sax | Ahx | Tas | Shx | Shy => OperandAccess::Write,
+            Asl | Lsr | Rol | Ror | Inc | Dec | Slo | Rla | Sre | Rra | Dcp | Isc => OperandAccess::ReadModifyWrite,
+            _ => OperandAccess::Read,
+        }
+    }
+
+    pub fn opcode_operation(&self, cpu: &mut Cpu, bus: &mut impl BusInterface) -> u8 {
         use self::Opcode::*;
         use self::AddressingMode::*;
         use Flags6502::*;
 
         match self {
             Adc => {
-                cpu.fetch();
-
-                let temp = cpu.a_reg as u16 + cpu.fetched as u16 + cpu.get_flag(Carry) as u16;
+                cpu.fetch(bus);
 
-                cpu.set_flag(Carry, temp > 255);
-                cpu.set_flag(Zero, (temp & 0x00FF) == 0);
-                cpu.set_flag(Overflow, (((cpu.a_reg as u16 ^ temp) & !(cpu.a_reg as u16 ^ cpu.fetched as u16)) & 0x0080) != 0);
-                cpu.set_flag(Negative, (temp & 0x80) != 0);
-
-                cpu.a_reg = (temp & 0x00FF) as u8;
+                cpu.add_with_carry(cpu.fetched);
 
                 1
             }
             And => {
-                cpu.fetch();
+                cpu.fetch(bus);
 
                 cpu.a_reg &= cpu.fetched;
 
@@ -266,7 +300,7 @@ impl Opcode {
                 1
             }
             Asl => {
-                cpu.fetch();
+                cpu.fetch(bus);
 
                 let temp = (cpu.fetched as u16) << 1;
 
@@ -278,55 +312,28 @@ impl Opcode {
                     cpu.a_reg = (temp & 0x00FF) as u8;
                 }
                 else {
-                    cpu.write(cpu.addr_abs, (temp & 0x00FF) as u8);
+                    cpu.write(bus, cpu.addr_abs, (temp & 0x00FF) as u8);
                 }
 
                 0
             }
             Bcc => {
-                if cpu.get_flag(Carry) == 0 {
-                    cpu.cycles_remaining += 1;
-                    cpu.addr_abs = cpu.pc + cpu.addr_rel;
-
-                    if (cpu.addr_abs & 0xFF00) != (cpu.pc &0xFF00) {
-                        cpu.cycles_remaining += 1;
-                    }
-                    
-                    cpu.pc = cpu.addr_abs;
-                }
+                cpu.branch(cpu.get_flag(Carry) == 0);
 
                 0
             }
             Bcs => {
-                if cpu.get_flag(Carry) == 1 {
-                    cpu.cycles_remaining += 1;
-                    cpu.addr_abs = cpu.pc + cpu.addr_rel;
-
-                    if (cpu.addr_abs & 0xFF00) != (cpu.pc & 0xFF00) {
-                        cpu.cycles_remaining += 1;
-                    }
-
-                    cpu.pc = cpu.addr_abs;
-                }
+                cpu.branch(cpu.get_flag(Carry) == 1);
 
                 0
             }
             Beq => {
-                if cpu.get_flag(Zero) == 1 {
-                    cpu.cycles_remaining += 1;
-                    cpu.addr_abs = cpu.pc + cpu.addr_rel;
-
-                    if (cpu.addr_abs & 0xFF00) != (cpu.pc & 0xFF00) {
-                        cpu.cycles_remaining += 1;
-                    }
-
-                    cpu.pc = cpu.addr_abs;                    
-                }
+                cpu.branch(cpu.get_flag(Zero) == 1);
 
                 0
             }
             Bit => {
-                cpu.fetch();
+                cpu.fetch(bus);
 
                 let temp = cpu.a_reg & cpu.fetched;
 
@@ -337,89 +344,41 @@ impl Opcode {
                 0
             }
             Bmi => {
-                if cpu.get_flag(Negative) == 1 {
-                    cpu.cycles_remaining += 1;
-                    cpu.addr_abs = cpu.pc + cpu.addr_rel;
-
-                    if (cpu.addr_abs & 0xFF00) != (cpu.pc & 0xFF00) {
-                        cpu.cycles_remaining += 1;
-                    }
+                cpu.branch(cpu.get_flag(Negative) == 1);
 
-                    cpu.pc = cpu.addr_abs;
-                }
                 0
             }
             Bne => {
-                if cpu.get_flag(Zero) == 0 {
-                    cpu.cycles_remaining += 1;
-                    cpu.addr_abs = cpu.pc + cpu.addr_rel;
-
-                    if (cpu.addr_abs & 0xFF00) != (cpu.pc & 0x00FF) {
-                        cpu.cycles_remaining += 1;
-                    } 
-
-                    cpu.pc = cpu.addr_abs;
-                }
+                cpu.branch(cpu.get_flag(Zero) == 0);
 
                 0
             }
             Bpl => {
-                if cpu.get_flag(Negative) == 0 {
-                    cpu.cycles_remaining += 1;
-                    cpu.addr_abs = cpu.pc + cpu.addr_rel;
-
-                    if (cpu.addr_abs & 0xFF00) != (cpu.pc & 0xFF00) {
-                        cpu.cycles_remaining += 1;
-                    }
-
-                    cpu.pc = cpu.addr_abs;
-                }
+                cpu.branch(cpu.get_flag(Negative) == 0);
 
                 0
             }
             Brk => {
-                cpu.pc += 1;
+                // BRK has a padding byte after the opcode, which RTI skips.
+                cpu.pc = cpu.pc.wrapping_add(1);
+
+                cpu.push(bus, (cpu.pc >> 8) as u8);
+                cpu.push(bus, cpu.pc as u8);
+                cpu.push(bus, cpu.status | BreakCommand as u8 | Unused as u8);
 
                 cpu.set_flag(InterruptDisable, true);
-                cpu.write(0x0100 + cpu.stk_ptr as u16, ((cpu.pc >> 8) & 0x00FF) as u8);
-                cpu.stk_ptr -= 1;
-                cpu.write(0x0100 + cpu.stk_ptr as u16, (cpu.pc & 0x00FF) as u8);
-                cpu.stk_ptr -= 1;
-
-                cpu.set_flag(BreakCommand, true);
-                cpu.write(0x0100 + cpu.stk_ptr as u16, cpu.status);
-                cpu.stk_ptr -= 1;
-                cpu.set_flag(BreakCommand, false);
 
-                cpu.pc = cpu.read(0xFFFE) as u16 | (cpu.read(0xFFFF) as u16) << 8;
+                cpu.pc = cpu.read_word(bus, IRQ_BASE);
 
                 0
             }
             Bvc => {
-                if cpu.get_flag(Overflow) == 0 {
-                    cpu.cycles_remaining += 1;
-                    cpu.addr_abs = cpu.pc + cpu.addr_rel;
-
-                    if (cpu.addr_abs & 0xFF00) != (cpu.pc & 0xFF00) {
-                        cpu.cycles_remaining += 1;
-                    }
-
-                    cpu.pc = cpu.addr_abs;
-                }
+                cpu.branch(cpu.get_flag(Overflow) == 0);
 
                 0
             }
             Bvs => {
-                if cpu.get_flag(Overflow) == 1 {
-                    cpu.cycles_remaining += 1;
-                    cpu.addr_abs = cpu.pc + cpu.addr_abs;
-
-                    if (cpu.addr_abs & 0xFF00) != (cpu.pc & 0xFF00) {
-                        cpu.cycles_remaining += 1;
-                    }
-
-                    cpu.pc = cpu.addr_abs;
-                }
+                cpu.branch(cpu.get_flag(Overflow) == 1);
 
                 0
             }
@@ -444,67 +403,55 @@ impl Opcode {
                 0
             }
             Cmp => {
-                cpu.fetch();
+                cpu.fetch(bus);
 
-                let temp = cpu.a_reg as u16 - cpu.fetched as u16;
-
-                cpu.set_flag(Carry, cpu.a_reg >= cpu.fetched);
-                cpu.set_flag(Zero, temp & 0x00FF == 0x0000);
-                cpu.set_flag(Negative, (temp & 0x0080) != 0);
+                cpu.compare(cpu.a_reg, cpu.fetched);
 
                 1
             }
             Cpx => {
-                cpu.fetch();
-
-                let temp = cpu.x_reg as u16 - cpu.fetched as u16;
+                cpu.fetch(bus);
 
-                cpu.set_flag(Carry, cpu.x_reg >= cpu.fetched);
-                cpu.set_flag(Zero, (temp & 0x00FF) != 0);
-                cpu.set_flag(Negative, (temp & 0x0080) != 0);
+                cpu.compare(cpu.x_reg, cpu.fetched);
 
                 0
             }
             Cpy => {
-                cpu.fetch();
+                cpu.fetch(bus);
 
-                let temp = cpu.y_reg as u16 - cpu.fetched as u16;
-
-                cpu.set_flag(Carry, cpu.x_reg >= cpu.fetched);
-                cpu.set_flag(Zero, (temp & 0x00FF) != 0);
-                cpu.set_flag(Negative, (temp & 0x0080) != 0);
+                cpu.compare(cpu.y_reg, cpu.fetched);
 
                 0
             }
             Dec => {
-                cpu.fetch();
+                cpu.fetch(bus);
 
-                let temp = cpu.fetched as u16 - 0x0001;
-                cpu.write(cpu.addr_abs, (temp & 0x00FF) as u8);
+                let temp = cpu.fetched.wrapping_sub(1);
+                cpu.write(bus, cpu.addr_abs, temp);
 
-                cpu.set_flag(Zero, (temp & 0x00FF) == 0x0000);
-                cpu.set_flag(Negative, (temp & 0x0080) != 0);
+                cpu.set_flag(Zero, temp == 0x00);
+                cpu.set_flag(Negative, (temp & 0x80) != 0);
 
                 0
             }
             Dex => {
-                cpu.x_reg -= 1;
+                cpu.x_reg = cpu.x_reg.wrapping_sub(1);
 
-                cpu.set_flag(Zero, cpu.x_reg == 0x0000);
+                cpu.set_flag(Zero, cpu.x_reg == 0x00);
                 cpu.set_flag(Negative, (cpu.x_reg & 0x80) != 0);
 
                 0
             }
             Dey => {
-                cpu.y_reg -= 1;
+                cpu.y_reg = cpu.y_reg.wrapping_sub(1);
 
-                cpu.set_flag(Zero, cpu.y_reg == 0x0000);
+                cpu.set_flag(Zero, cpu.y_reg == 0x00);
                 cpu.set_flag(Negative, (cpu.y_reg & 0x80) != 0);
 
                 0
             }
             Eor => {
-                cpu.fetch();
+                cpu.fetch(bus);
 
                 cpu.a_reg ^= cpu.fetched;
 
@@ -514,10 +461,10 @@ impl Opcode {
                 1
             }
             Inc => {
-                cpu.fetch();
+                cpu.fetch(bus);
 
-                let temp = cpu.fetched + 1;
-                cpu.write(cpu.addr_abs, temp);
+                let temp = cpu.fetched.wrapping_add(1);
+                cpu.write(bus, cpu.addr_abs, temp);
 
                 cpu.set_flag(Zero, temp == 0x00);
                 cpu.set_flag(Negative, (temp & 0x80) != 0);
@@ -525,7 +472,7 @@ impl Opcode {
                 0
             }
             Inx => {
-                cpu.x_reg += 1;
+                cpu.x_reg = cpu.x_reg.wrapping_add(1);
 
                 cpu.set_flag(Zero, cpu.x_reg == 0x00);
                 cpu.set_flag(Negative, (cpu.x_reg & 0x80) != 0);
@@ -533,7 +480,7 @@ impl Opcode {
                 0
             }
             Iny => {
-                cpu.y_reg += 1;
+                cpu.y_reg = cpu.y_reg.wrapping_add(1);
 
                 cpu.set_flag(Zero, cpu.y_reg == 0x00);
                 cpu.set_flag(Negative, (cpu.y_reg & 0x80) != 0);
@@ -546,19 +493,18 @@ impl Opcode {
                 0
             }
             Jsr => {
-                cpu.pc -= 1;
+                // The pushed return address is the last byte of the JSR.
+                cpu.pc = cpu.pc.wrapping_sub(1);
 
-                cpu.write(0x0100 + cpu.stk_ptr as u16, ((cpu.pc >> 8) & 0x00FF) as u8);
-                cpu.stk_ptr -= 1;
-                cpu.write(0x0100 + cpu.stk_ptr as u16, (cpu.pc & 0x00FF) as u8);
-                cpu.stk_ptr -= 1;
+                cpu.push(bus, (cpu.pc >> 8) as u8);
+                cpu.push(bus, cpu.pc as u8);
 
                 cpu.pc = cpu.addr_abs;
                 
                 0
             }
             Lda => {
-                cpu.fetch();
+                cpu.fetch(bus);
 
                 cpu.a_reg = cpu.fetched;
 
@@ -568,7 +514,7 @@ impl Opcode {
                 1
             }
             Ldx => {
-                cpu.fetch();
+                cpu.fetch(bus);
 
                 cpu.x_reg = cpu.fetched;
 
@@ -578,7 +524,7 @@ impl Opcode {
                 1
             }
             Ldy => {
-                cpu.fetch();
+                cpu.fetch(bus);
 
                 cpu.y_reg = cpu.fetched;
 
@@ -588,35 +534,31 @@ impl Opcode {
                 1
             }
             Lsr => {
-                cpu.fetch();
+                cpu.fetch(bus);
 
                 let temp = cpu.fetched >> 1;
 
-                cpu.set_flag(Carry, (cpu.fetched & 0x01) != 1);
+                cpu.set_flag(Carry, (cpu.fetched & 0x01) != 0);
                 cpu.set_flag(Zero, temp == 0x00);
-                cpu.set_flag(Negative, (temp & 0x80) != 0);
+                cpu.set_flag(Negative, false);
 
                 if CPU_INSTRUCTIONS[cpu.opcode as usize].addr_mode == Implied{
                     cpu.a_reg = temp;
                 }
                 else {
-                    cpu.write(cpu.addr_abs, temp);
+                    cpu.write(bus, cpu.addr_abs, temp);
                 }
                 
                 0
             }
             Nop => {
-                match cpu.opcode {
-                    0x1C | 0x3C | 0x5C | 0x7C | 0xDC | 0xFC => {
-                        return 1;
-                    }
-                    _ => {
-                        return 0;
-                    }
-                }
+                // The unofficial NOPs with an operand still read it.
+                cpu.fetch(bus);
+
+                1
             }
             Ora => {
-                cpu.fetch();
+                cpu.fetch(bus);
 
                 cpu.a_reg |= cpu.fetched;
 
@@ -626,20 +568,18 @@ impl Opcode {
                 1
             }
             Pha => {
-                cpu.write(0x0100 + cpu.stk_ptr as u16, cpu.a_reg);
-                cpu.stk_ptr -= 1;
+                cpu.push(bus, cpu.a_reg);
 
                 0
             }
             Php => {
-                cpu.write(0x0100 + cpu.stk_ptr as u16, cpu.status);
-                cpu.stk_ptr -= 1;
+                // B only exists on the stack copy, set when pushed by PHP/BRK.
+                cpu.push(bus, cpu.status | BreakCommand as u8 | Unused as u8);
 
                 0
             }
             Pla => {
-                cpu.stk_ptr += 1;
-                cpu.a_reg = cpu.read(0x0100 + cpu.stk_ptr as u16);
+                cpu.a_reg = cpu.pull(bus);
 
                 cpu.set_flag(Zero, cpu.a_reg == 0x00);
                 cpu.set_flag(Negative, (cpu.a_reg & 0x80) != 0);
@@ -647,17 +587,17 @@ impl Opcode {
                 0
             }
             Plp => {
-                cpu.stk_ptr += 1;
-                cpu.status = cpu.read(0x0100 + cpu.stk_ptr as u16);
+                cpu.status = cpu.pull(bus);
 
+                cpu.set_flag(BreakCommand, false);
                 cpu.set_flag(Unused, true);
 
                 0
             }
             Rol => {
-                cpu.fetch();
+                cpu.fetch(bus);
 
-                let temp = (cpu.fetched << 1) as u16 | cpu.get_flag(Carry) as u16;
+                let temp = ((cpu.fetched as u16) << 1) | cpu.get_flag(Carry) as u16;
 
                 cpu.set_flag(Carry, (temp & 0xFF00) != 0);
                 cpu.set_flag(Zero, (temp & 0x00FF) == 0x00);
@@ -667,63 +607,52 @@ impl Opcode {
                     cpu.a_reg = (temp & 0x00FF) as u8;
                 }
                 else {
-                    cpu.write(cpu.addr_abs, (temp & 0x00FF) as u8);
+                    cpu.write(bus, cpu.addr_abs, (temp & 0x00FF) as u8);
                 }
 
                 0
             }
             Ror => {
-                cpu.fetch();
+                cpu.fetch(bus);
 
-                let temp = (cpu.fetched >> 1) as u16 | (cpu.get_flag(Carry) << 7) as u16;
+                let temp = (cpu.fetched >> 1) | (cpu.get_flag(Carry) << 7);
 
-                cpu.set_flag(Carry, (temp & 0x01) != 0);
-                cpu.set_flag(Zero, (temp & 0x00FF) == 0x00);
-                cpu.set_flag(Negative, (temp & 0x0080) != 0);
+                cpu.set_flag(Carry, (cpu.fetched & 0x01) != 0);
+                cpu.set_flag(Zero, temp == 0x00);
+                cpu.set_flag(Negative, (temp & 0x80) != 0);
 
                 if CPU_INSTRUCTIONS[cpu.opcode as usize].addr_mode == Implied {
-                    cpu.a_reg = (temp & 0x00FF) as u8;
+                    cpu.a_reg = temp;
                 }
                 else {
-                    cpu.write(cpu.addr_abs, (temp & 0x00FF) as u8);
+                    cpu.write(bus, cpu.addr_abs, temp);
                 }
 
                 0
             }
             Rti => {
-                cpu.stk_ptr += 1;
-                cpu.status = cpu.read(0x0100 + cpu.stk_ptr as u16);
-                cpu.status &= !(BreakCommand as u8);
-                cpu.status &= !(Unused as u8);
+                cpu.status = cpu.pull(bus);
+                cpu.set_flag(BreakCommand, false);
+                cpu.set_flag(Unused, true);
 
-                cpu.stk_ptr += 1;
-                cpu.pc = cpu.read(0x0100 + cpu.stk_ptr as u16) as u16;
-                cpu.stk_ptr += 1;
-                cpu.pc |= (cpu.read(0x0100 + cpu.stk_ptr as u16) as u16) << 8;
+                let lo = cpu.pull(bus) as u16;
+                let hi = cpu.pull(bus) as u16;
+                cpu.pc = (hi << 8) | lo;
 
                 0
             }
             Rts => {
-                cpu.stk_ptr += 1;
-                cpu.pc = cpu.read(0x0100 + cpu.stk_ptr as u16) as u16 - 1;
-                cpu.stk_ptr += 1;
-                cpu.pc |= (cpu.read(0x0100 + cpu.stk_ptr as u16) as u16) << 8;
+                let lo = cpu.pull(bus) as u16;
+                let hi = cpu.pull(bus) as u16;
+                cpu.pc = ((hi << 8) | lo).wrapping_add(1);
 
                 0
             }
             Sbc => {
-                cpu.fetch();
-
-                let value = (cpu.fetched as u16) ^ 0x00FF;
+                cpu.fetch(bus);
 
-                let temp = cpu.a_reg as u16 + cpu.fetched as u16 + cpu.get_flag(Carry) as u16;
-
-                cpu.set_flag(Carry, temp > 255);
-                cpu.set_flag(Zero, (temp  & 0x00FF) != 0);
-                cpu.set_flag(Overflow, (((cpu.a_reg as u16 ^ temp) & (value ^ temp)) & 0x0080) != 0);
-                cpu.set_flag(Negative, (temp & 0x80) != 0);
-                
-                cpu.a_reg = (temp & 0x00FF) as u8;
+                // A - M - (1 - C) is A + !M + C in two's complement.
+                cpu.add_with_carry(cpu.fetched ^ 0xFF);
 
                 1
             }
@@ -743,17 +672,17 @@ impl Opcode {
                 0
             }
             Sta => {
-                cpu.write(cpu.addr_abs, cpu.a_reg);
+                cpu.write(bus, cpu.addr_abs, cpu.a_reg);
 
                 0
             }
             Stx => {
-                cpu.write(cpu.addr_abs, cpu.x_reg);
+                cpu.write(bus, cpu.addr_abs, cpu.x_reg);
 
                 0
             }
             Sty => {
-                cpu.write(cpu.addr_abs, cpu.y_reg);
+                cpu.write(bus, cpu.addr_abs, cpu.y_reg);
 
                 0
             }
@@ -803,6 +732,205 @@ impl Opcode {
                 0
             }
             Kil => {
+                cpu.jam();
+
+                0
+            }
+            Lax => {
+                cpu.fetch(bus);
+
+                cpu.a_reg = cpu.fetched;
+                cpu.x_reg = cpu.fetched;
+
+                cpu.set_flag(Zero, cpu.a_reg == 0x00);
+                cpu.set_flag(Negative, (cpu.a_reg & 0x80) != 0);
+
+                1
+            }
+            Sax => {
+                cpu.write(bus, cpu.addr_abs, cpu.a_reg & cpu.x_reg);
+
+                0
+            }
+            Dcp => {
+                cpu.fetch(bus);
+
+                let temp = cpu.fetched.wrapping_sub(1);
+                cpu.write(bus, cpu.addr_abs, temp);
+
+                cpu.compare(cpu.a_reg, temp);
+
+                0
+            }
+            Isc => {
+                cpu.fetch(bus);
+
+                let temp = cpu.fetched.wrapping_add(1);
+                cpu.write(bus, cpu.addr_abs, temp);
+
+                cpu.add_with_carry(temp ^ 0xFF);
+
+                0
+            }
+            Slo => {
+                cpu.fetch(bus);
+
+                let temp = cpu.fetched << 1;
+                cpu.write(bus, cpu.addr_abs, temp);
+
+                cpu.set_flag(Carry, (cpu.fetched & 0x80) != 0);
+                cpu.a_reg |= temp;
+
+                cpu.set_flag(Zero, cpu.a_reg == 0x00);
+                cpu.set_flag(Negative, (cpu.a_reg & 0x80) != 0);
+
+                0
+            }
+            Rla => {
+                cpu.fetch(bus);
+
+                let temp = (cpu.fetched << 1) | cpu.get_flag(Carry);
+                cpu.write(bus, cpu.addr_abs, temp);
+
+                cpu.set_flag(Carry, (cpu.fetched & 0x80) != 0);
+                cpu.a_reg &= temp;
+
+                cpu.set_flag(Zero, cpu.a_reg == 0x00);
+                cpu.set_flag(Negative, (cpu.a_reg & 0x80) != 0);
+
+                0
+            }
+            Sre => {
+                cpu.fetch(bus);
+
+                let temp = cpu.fetched >> 1;
+                cpu.write(bus, cpu.addr_abs, temp);
+
+                cpu.set_flag(Carry, (cpu.fetched & 0x01) != 0);
+                cpu.a_reg ^= temp;
+
+                cpu.set_flag(Zero, cpu.a_reg == 0x00);
+                cpu.set_flag(Negative, (cpu.a_reg & 0x80) != 0);
+
+                0
+            }
+            Rra => {
+                cpu.fetch(bus);
+
+                let temp = (cpu.fetched >> 1) | (cpu.get_flag(Carry) << 7);
+                cpu.write(bus, cpu.addr_abs, temp);
+
+                cpu.set_flag(Carry, (cpu.fetched & 0x01) != 0);
+                cpu.add_with_carry(temp);
+
+                0
+            }
+            Anc => {
+                cpu.fetch(bus);
+
+                cpu.a_reg &= cpu.fetched;
+
+                cpu.set_flag(Zero, cpu.a_reg == 0x00);
+                cpu.set_flag(Negative, (cpu.a_reg & 0x80) != 0);
+                cpu.set_flag(Carry, (cpu.a_reg & 0x80) != 0);
+
+                0
+            }
+            Alr => {
+                cpu.fetch(bus);
+
+                let temp = cpu.a_reg & cpu.fetched;
+                cpu.a_reg = temp >> 1;
+
+                cpu.set_flag(Carry, (temp & 0x01) != 0);
+                cpu.set_flag(Zero, cpu.a_reg == 0x00);
+                cpu.set_flag(Negative, false);
+
+                0
+            }
+            Arr => {
+                cpu.fetch(bus);
+
+                cpu.a_reg = ((cpu.a_reg & cpu.fetched) >> 1) | (cpu.get_flag(Carry) << 7);
+
+                cpu.set_flag(Zero, cpu.a_reg == 0x00);
+                cpu.set_flag(Negative, (cpu.a_reg & 0x80) != 0);
+                cpu.set_flag(Carry, (cpu.a_reg & 0x40) != 0);
+                cpu.set_flag(Overflow, (((cpu.a_reg >> 6) ^ (cpu.a_reg >> 5)) & 0x01) != 0);
+
+                0
+            }
+            Axs => {
+                cpu.fetch(bus);
+
+                let temp = cpu.a_reg & cpu.x_reg;
+                cpu.x_reg = temp.wrapping_sub(cpu.fetched);
+
+                cpu.set_flag(Carry, temp >= cpu.fetched);
+                cpu.set_flag(Zero, cpu.x_reg == 0x00);
+                cpu.set_flag(Negative, (cpu.x_reg & 0x80) != 0);
+
+                0
+            }
+            Las => {
+                cpu.fetch(bus);
+
+                let temp = cpu.fetched & cpu.stk_ptr;
+                cpu.a_reg = temp;
+                cpu.x_reg = temp;
+                cpu.stk_ptr = temp;
+
+                cpu.set_flag(Zero, temp == 0x00);
+                cpu.set_flag(Negative, (temp & 0x80) != 0);
+
+                1
+            }
+            Xaa => {
+                cpu.fetch(bus);
+
+                if let UnstableOpcodes::Emulate { magic } = cpu.unstable_opcodes {
+                    cpu.a_reg = (cpu.a_reg | magic) & cpu.x_reg & cpu.fetched;
+
+                    cpu.set_flag(Zero, cpu.a_reg == 0x00);
+                    cpu.set_flag(Negative, (cpu.a_reg & 0x80) != 0);
+                }
+
+                0
+            }
+            Lxa => {
+                cpu.fetch(bus);
+
+                if let UnstableOpcodes::Emulate { magic } = cpu.unstable_opcodes {
+                    cpu.a_reg = (cpu.a_reg | magic) & cpu.fetched;
+                    cpu.x_reg = cpu.a_reg;
+
+                    cpu.set_flag(Zero, cpu.a_reg == 0x00);
+                    cpu.set_flag(Negative, (cpu.a_reg & 0x80) != 0);
+                }
+
+                0
+            }
+            Ahx => {
+                cpu.store_and_high_byte(bus, cpu.a_reg & cpu.x_reg, cpu.y_reg);
+
+                0
+            }
+            Tas => {
+                if cpu.unstable_opcodes != UnstableOpcodes::Nop {
+                    cpu.stk_ptr = cpu.a_reg & cpu.x_reg;
+                }
+                cpu.store_and_high_byte(bus, cpu.stk_ptr, cpu.y_reg);
+
+                0
+            }
+            Shx => {
+                cpu.store_and_high_byte(bus, cpu.x_reg, cpu.y_reg);
+
+                0
+            }
+            Shy => {
+                cpu.store_and_high_byte(bus, cpu.y_reg, cpu.x_reg);
+
                 0
             }
         }
@@ -814,25 +942,32 @@ use AddressingMode::*;
 
 lazy_static!{
     pub static ref CPU_INSTRUCTIONS: [Instruction; 256] = [
-        Instruction::new( Brk, Implied, 7 ),      Instruction::new( Ora, Indirect_X, 6 ),    Instruction::new( Kil, Implied, 2 ),    Instruction::new( Kil, Implied, 8 ),    Instruction::new( Kil, Implied, 3 ),      Instruction::new( Ora, ZeroPage, 3 ),     Instruction::new( Asl, ZeroPage, 5 ),      Instruction::new( Kil, Implied, 5 ),    Instruction::new( Php, Implied, 3 ),    Instruction::new( Ora, Immediate, 2 ),     Instruction::new( Asl, Implied, 2 ),    Instruction::new( Kil, Implied, 2 ),    Instruction::new( Nop, Implied, 4 ),     Instruction::new( Ora, Absolute, 4 ),      Instruction::new( Asl, Absolute, 6 ),      Instruction::new( Kil, Implied, 6 ),
-        Instruction::new( Bpl, Relative, 2 ),     Instruction::new( Ora, Indirect_Y, 5 ),    Instruction::new( Kil, Implied, 2 ),    Instruction::new( Kil, Implied, 8 ),    Instruction::new( Kil, Implied, 4 ),      Instruction::new( Ora, ZeroPage_X, 4 ),   Instruction::new( Asl, ZeroPage_X, 6 ),    Instruction::new( Kil, Implied, 6 ),    Instruction::new( Clc, Implied, 2 ),    Instruction::new( Ora, Absolute_Y, 4 ),    Instruction::new( Nop, Implied, 2 ),    Instruction::new( Kil, Implied, 2 ),    Instruction::new( Nop, Implied, 4 ),     Instruction::new( Ora, Absolute_X, 4 ),    Instruction::new( Asl, Absolute_X, 7 ),    Instruction::new( Kil, Implied, 7 ),
-        Instruction::new( Jsr, Absolute, 6 ),     Instruction::new( And, Indirect_X, 6 ),    Instruction::new( Kil, Implied, 2 ),    Instruction::new( Kil, Implied, 8 ),    Instruction::new( Bit, ZeroPage, 3 ),     Instruction::new( And, ZeroPage, 3 ),     Instruction::new( Rol, ZeroPage, 5 ),      Instruction::new( Kil, Implied, 5 ),    Instruction::new( Plp, Implied, 4 ),    Instruction::new( And, Immediate, 2 ),     Instruction::new( Rol, Implied, 2 ),    Instruction::new( Kil, Implied, 2 ),    Instruction::new( Bit, Absolute, 4 ),    Instruction::new( And, Absolute, 4 ),      Instruction::new( Rol, Absolute, 6 ),      Instruction::new( Kil, Implied, 6 ),
-        Instruction::new( Bmi, Relative, 2 ),     Instruction::new( And, Indirect_Y, 5 ),    Instruction::new( Kil, Implied, 2 ),    Instruction::new( Kil, Implied, 8 ),    Instruction::new( Kil, Implied, 4 ),      Instruction::new( And, ZeroPage_X, 4 ),   Instruction::new( Rol, ZeroPage_X, 6 ),    Instruction::new( Kil, Implied, 6 ),    Instruction::new( Sec, Implied, 2 ),    Instruction::new( And, Absolute_Y, 4 ),    Instruction::new( Nop, Implied, 2 ),    Instruction::new( Kil, Implied, 2 ),    Instruction::new( Nop, Implied, 4 ),     Instruction::new( And, Absolute_X, 4 ),    Instruction::new( Rol, Absolute_X, 7 ),    Instruction::new( Kil, Implied, 7 ),
-        Instruction::new( Rti, Implied, 6 ),      Instruction::new( Eor, Indirect_X, 6 ),    Instruction::new( Kil, Implied, 2 ),    Instruction::new( Kil, Implied, 8 ),    Instruction::new( Kil, Implied, 3 ),      Instruction::new( Eor, ZeroPage, 3 ),     Instruction::new( Lsr, ZeroPage, 5 ),      Instruction::new( Kil, Implied, 5 ),    Instruction::new( Pha, Implied, 3 ),    Instruction::new( Eor, Immediate, 2 ),     Instruction::new( Lsr, Implied, 2 ),    Instruction::new( Kil, Implied, 2 ),    Instruction::new( Jmp, Absolute, 3 ),    Instruction::new( Eor, Absolute, 4 ),      Instruction::new( Lsr, Absolute, 6 ),      Instruction::new( Kil, Implied, 6 ),
-        Instruction::new( Bvc, Relative, 2),      Instruction::new( Eor, Indirect_Y, 5 ),    Instruction::new( Kil, Implied, 2 ),    Instruction::new( Kil, Implied, 8 ),    Instruction::new( Kil, Implied, 4 ),      Instruction::new( Eor, ZeroPage_X, 4 ),   Instruction::new( Lsr, ZeroPage_X, 6 ),    Instruction::new( Kil, Implied, 6 ),    Instruction::new( Cli, Implied, 2 ),    Instruction::new( Eor, Absolute_Y, 4 ),    Instruction::new( Nop, Implied, 2 ),    Instruction::new( Kil, Implied, 2 ),    Instruction::new( Nop, Implied, 4 ),     Instruction::new( Eor, Absolute_X, 4 ),    Instruction::new( Lsr, Absolute_X, 7 ),    Instruction::new( Kil, Implied, 7 ),
-        Instruction::new( Rts, Implied, 6 ),      Instruction::new( Adc, Indirect_X, 6 ),    Instruction::new( Kil, Implied, 2 ),    Instruction::new( Kil, Implied, 8 ),    Instruction::new( Kil, Implied, 3 ),      Instruction::new( Adc, ZeroPage, 3 ),     Instruction::new( Ror, ZeroPage, 5 ),      Instruction::new( Kil, Implied, 5 ),    Instruction::new( Pla, Implied, 4 ),    Instruction::new( Adc, Immediate, 2 ),     Instruction::new( Ror, Implied, 2 ),    Instruction::new( Kil, Implied, 2 ),    Instruction::new( Jmp, Indirect, 5 ),    Instruction::new( Adc, Absolute, 4 ),      Instruction::new( Ror, Absolute, 6 ),      Instruction::new( Kil, Implied, 6 ),
-        Instruction::new( Bvs, Relative, 2 ),     Instruction::new( Adc, Indirect_Y, 5 ),    Instruction::new( Kil, Implied, 2 ),    Instruction::new( Kil, Implied, 8 ),    Instruction::new( Kil, Implied, 4 ),      Instruction::new( Adc, ZeroPage_X, 4 ),   Instruction::new( Ror, ZeroPage_X, 6 ),    Instruction::new( Kil, Implied, 6 ),    Instruction::new( Sei, Implied, 2 ),    Instruction::new( Adc, Absolute_Y, 4 ),    Instruction::new( Nop, Implied, 2 ),    Instruction::new( Kil, Implied, 2 ),    Instruction::new( Nop, Implied, 4 ),     Instruction::new( Adc, Absolute_X, 4 ),    Instruction::new( Ror, Absolute_X, 7 ),    Instruction::new( Kil, Implied, 7 ),
-        Instruction::new( Kil, Implied, 2 ),      Instruction::new( Sta, Indirect_X, 6 ),    Instruction::new( Kil, Implied, 2 ),    Instruction::new( Kil, Implied, 6 ),    Instruction::new( Sty, ZeroPage, 3 ),     Instruction::new( Sta, ZeroPage, 3 ),     Instruction::new( Stx, ZeroPage, 3 ),      Instruction::new( Kil, Implied, 5 ),    Instruction::new( Dey, Implied, 2 ),    Instruction::new( Kil, Implied, 2 ),       Instruction::new( Txa, Implied, 2 ),    Instruction::new( Kil, Implied, 2 ),    Instruction::new( Sty, Absolute, 4 ),    Instruction::new( Sta, Absolute, 4 ),      Instruction::new( Stx, Absolute, 4 ),      Instruction::new( Kil, Implied, 4 ),
-        Instruction::new( Bcc, Relative, 2 ),     Instruction::new( Sta, Indirect_Y, 6 ),    Instruction::new( Kil, Implied, 2 ),    Instruction::new( Kil, Implied, 6 ),    Instruction::new( Sty, ZeroPage_X, 4 ),   Instruction::new( Sta, ZeroPage_X, 4 ),   Instruction::new( Stx, ZeroPage_Y, 4 ),    Instruction::new( Kil, Implied, 6 ),    Instruction::new( Tya, Implied, 2 ),    Instruction::new( Sta, Absolute_Y, 5 ),    Instruction::new( Txs, Implied, 2 ),    Instruction::new( Kil, Implied, 2 ),    Instruction::new( Nop, Implied, 5 ),     Instruction::new( Sta, Absolute_X, 5 ),    Instruction::new( Kil, Implied, 5 ),       Instruction::new( Kil, Implied, 5 ),
-        Instruction::new( Ldy, Immediate, 2 ),    Instruction::new( Lda, Indirect_X, 6 ),    Instruction::new( Ldx, Immediate, 2 ),  Instruction::new( Kil, Implied, 6 ),    Instruction::new( Ldy, ZeroPage, 3 ),     Instruction::new( Lda, ZeroPage, 3 ),     Instruction::new( Ldx, ZeroPage, 3 ),      Instruction::new( Kil, Implied, 5 ),    Instruction::new( Tay, Implied, 2 ),    Instruction::new( Lda, Immediate, 2 ),     Instruction::new( Tax, Implied, 2 ),    Instruction::new( Kil, Implied, 2 ),    Instruction::new( Ldy, Absolute, 4 ),    Instruction::new( Lda, Absolute, 4 ),      Instruction::new( Ldx, Absolute, 4 ),      Instruction::new( Kil, Implied, 4 ),
-        Instruction::new( Bcs, Relative, 2 ),     Instruction::new( Lda, Indirect_Y, 5 ),    Instruction::new( Kil, Implied, 2 ),    Instruction::new( Kil, Implied, 5 ),    Instruction::new( Ldy, ZeroPage_X, 4 ),   Instruction::new( Lda, ZeroPage_X, 4 ),   Instruction::new( Ldx, ZeroPage_Y, 4 ),    Instruction::new( Kil, Implied, 6 ),    Instruction::new( Clv, Implied, 2 ),    Instruction::new( Lda, Absolute_Y, 4 ),    Instruction::new( Tsx, Implied, 2 ),    Instruction::new( Kil, Implied, 2 ),    Instruction::new( Ldy, Absolute_X, 4 ),  Instruction::new( Lda, Absolute_X, 4 ),    Instruction::new( Ldx, Absolute_Y, 4 ),    Instruction::new( Kil, Implied, 4 ),
-        Instruction::new( Cpy, Immediate, 2 ),    Instruction::new( Cmp, Indirect_X, 6 ),    Instruction::new( Kil, Implied, 2 ),    Instruction::new( Kil, Implied, 8 ),    Instruction::new( Cpy, ZeroPage, 3 ),     Instruction::new( Cmp, ZeroPage, 3 ),     Instruction::new( Dec, ZeroPage, 5 ),      Instruction::new( Kil, Implied, 5 ),    Instruction::new( Iny, Implied, 2 ),    Instruction::new( Cmp, Immediate, 2 ),     Instruction::new( Dex, Implied, 2 ),    Instruction::new( Kil, Implied, 2 ),    Instruction::new( Cpy, Absolute, 4 ),    Instruction::new( Cmp, Absolute, 4 ),      Instruction::new( Dec, Absolute, 6 ),      Instruction::new( Kil, Implied, 6 ),
-        Instruction::new( Bne, Relative, 2 ),     Instruction::new( Cmp, Indirect_Y, 5 ),    Instruction::new( Kil, Implied, 2 ),    Instruction::new( Kil, Implied, 8 ),    Instruction::new( Kil, Implied, 4 ),      Instruction::new( Cmp, ZeroPage_X, 4 ),   Instruction::new( Dec, ZeroPage_X, 6 ),    Instruction::new( Kil, Implied, 6 ),    Instruction::new( Cld, Implied, 2 ),    Instruction::new( Cmp, Absolute_Y, 4 ),    Instruction::new( Nop, Implied, 2 ),    Instruction::new( Kil, Implied, 2 ),    Instruction::new( Nop, Implied, 4 ),     Instruction::new( Cmp, Absolute_X, 4 ),    Instruction::new( Dec, Absolute_X, 7 ),    Instruction::new( Kil, Implied, 7 ),
-        Instruction::new( Cpx, Immediate, 2 ),    Instruction::new( Sbc, Indirect_X, 6 ),    Instruction::new( Kil, Implied, 2 ),    Instruction::new( Kil, Implied, 8 ),    Instruction::new( Cpx, ZeroPage, 3 ),     Instruction::new( Sbc, ZeroPage, 3 ),     Instruction::new( Inc, ZeroPage, 5 ),      Instruction::new( Kil, Implied, 5 ),    Instruction::new( Inx, Implied, 2 ),    Instruction::new( Sbc, Immediate, 2 ),     Instruction::new( Nop, Implied, 2 ),    Instruction::new( Kil, Implied, 2 ),    Instruction::new( Cpx, Absolute, 4 ),    Instruction::new( Sbc, Absolute, 4 ),      Instruction::new( Inc, Absolute, 6 ),      Instruction::new( Kil, Implied, 6 ),
-        Instruction::new( Beq, Relative, 2 ),     Instruction::new( Sbc, Indirect_Y, 5 ),    Instruction::new( Kil, Implied, 2 ),    Instruction::new( Kil, Implied, 8 ),    Instruction::new( Kil, Implied, 4 ),      Instruction::new( Sbc, ZeroPage_X, 4 ),   Instruction::new( Inc, ZeroPage_X, 6 ),    Instruction::new( Kil, Implied, 6 ),    Instruction::new( Sed, Implied, 2 ),    Instruction::new( Sbc, Absolute_Y, 4 ),    Instruction::new( Nop, Implied, 2 ),    Instruction::new( Kil, Implied, 2 ),    Instruction::new( Nop, Implied, 4 ),     Instruction::new( Sbc, Absolute_X, 4 ),    Instruction::new( Inc, Absolute_X, 7 ),    Instruction::new( Kil, Implied, 7 ),   
+        Instruction::new( Brk, Implied, 7 ),    Instruction::new( Ora, Indirect_X, 6 ), Instruction::new( Kil, Implied, 2 ),    Instruction::new( Slo, Indirect_X, 8 ), Instruction::new( Nop, ZeroPage, 3 ),   Instruction::new( Ora, ZeroPage, 3 ),   Instruction::new( Asl, ZeroPage, 5 ),   Instruction::new( Slo, ZeroPage, 5 ),   Instruction::new( Php, Implied, 3 ),    Instruction::new( Ora, Immediate, 2 ),  Instruction::new( Asl, Implied, 2 ),    Instruction::new( Anc, Immediate, 2 ),  Instruction::new( Nop, Absolute, 4 ),   Instruction::new( Ora, Absolute, 4 ),   Instruction::new( Asl, Absolute, 6 ),   Instruction::new( Slo, Absolute, 6 ),
+        Instruction::new( Bpl, Relative, 2 ),   Instruction::new( Ora, Indirect_Y, 5 ), Instruction::new( Kil, Implied, 2 ),    Instruction::new( Slo, Indirect_Y, 8 ), Instruction::new( Nop, ZeroPage_X, 4 ), Instruction::new( Ora, ZeroPage_X, 4 ), Instruction::new( Asl, ZeroPage_X, 6 ), Instruction::new( Slo, ZeroPage_X, 6 ), Instruction::new( Clc, Implied, 2 ),    Instruction::new( Ora, Absolute_Y, 4 ), Instruction::new( Nop, Implied, 2 ),    Instruction::new( Slo, Absolute_Y, 7 ), Instruction::new( Nop, Absolute_X, 4 ), Instruction::new( Ora, Absolute_X, 4 ), Instruction::new( Asl, Absolute_X, 7 ), Instruction::new( Slo, Absolute_X, 7 ),
+        Instruction::new( Jsr, Absolute, 6 ),   Instruction::new( And, Indirect_X, 6 ), Instruction::new( Kil, Implied, 2 ),    Instruction::new( Rla, Indirect_X, 8 ), Instruction::new( Bit, ZeroPage, 3 ),   Instruction::new( And, ZeroPage, 3 ),   Instruction::new( Rol, ZeroPage, 5 ),   Instruction::new( Rla, ZeroPage, 5 ),   Instruction::new( Plp, Implied, 4 ),    Instruction::new( And, Immediate, 2 ),  Instruction::new( Rol, Implied, 2 ),    Instruction::new( Anc, Immediate, 2 ),  Instruction::new( Bit, Absolute, 4 ),   Instruction::new( And, Absolute, 4 ),   Instruction::new( Rol, Absolute, 6 ),   Instruction::new( Rla, Absolute, 6 ),
+        Instruction::new( Bmi, Relative, 2 ),   Instruction::new( And, Indirect_Y, 5 ), Instruction::new( Kil, Implied, 2 ),    Instruction::new( Rla, Indirect_Y, 8 ), Instruction::new( Nop, ZeroPage_X, 4 ), Instruction::new( And, ZeroPage_X, 4 ), Instruction::new( Rol, ZeroPage_X, 6 ), Instruction::new( Rla, ZeroPage_X, 6 ), Instruction::new( Sec, Implied, 2 ),    Instruction::new( And, Absolute_Y, 4 ), Instruction::new( Nop, Implied, 2 ),    Instruction::new( Rla, Absolute_Y, 7 ), Instruction::new( Nop, Absolute_X, 4 ), Instruction::new( And, Absolute_X, 4 ), Instruction::new( Rol, Absolute_X, 7 ), Instruction::new( Rla, Absolute_X, 7 ),
+        Instruction::new( Rti, Implied, 6 ),    Instruction::new( Eor, Indirect_X, 6 ), Instruction::new( Kil, Implied, 2 ),    Instruction::new( Sre, Indirect_X, 8 ), Instruction::new( Nop, ZeroPage, 3 ),   Instruction::new( Eor, ZeroPage, 3 ),   Instruction::new( Lsr, ZeroPage, 5 ),   Instruction::new( Sre, ZeroPage, 5 ),   Instruction::new( Pha, Implied, 3 ),    Instruction::new( Eor, Immediate, 2 ),  Instruction::new( Lsr, Implied, 2 ),    Instruction::new( Alr, Immediate, 2 ),  Instruction::new( Jmp, Absolute, 3 ),   Instruction::new( Eor, Absolute, 4 ),   Instruction::new( Lsr, Absolute, 6 ),   Instruction::new( Sre, Absolute, 6 ),
+        Instruction::new( Bvc, Relative, 2 ),   Instruction::new( Eor, Indirect_Y, 5 ), Instruction::new( Kil, Implied, 2 ),    Instruction::new( Sre, Indirect_Y, 8 ), Instruction::new( Nop, ZeroPage_X, 4 ), Instruction::new( Eor, ZeroPage_X, 4 ), Instruction::new( Lsr, ZeroPage_X, 6 ), Instruction::new( Sre, ZeroPage_X, 6 ), Instruction::new( Cli, Implied, 2 ),    Instruction::new( Eor, Absolute_Y, 4 ), Instruction::new( Nop, Implied, 2 ),    Instruction::new( Sre, Absolute_Y, 7 ), Instruction::new( Nop, Absolute_X, 4 ), Instruction::new( Eor, Absolute_X, 4 ), Instruction::new( Lsr, Absolute_X, 7 ), Instruction::new( Sre, Absolute_X, 7 ),
+        Instruction::new( Rts, Implied, 6 ),    Instruction::new( Adc, Indirect_X, 6 ), Instruction::new( Kil, Implied, 2 ),    Instruction::new( Rra, Indirect_X, 8 ), Instruction::new( Nop, ZeroPage, 3 ),   Instruction::new( Adc, ZeroPage, 3 ),   Instruction::new( Ror, ZeroPage, 5 ),   Instruction::new( Rra, ZeroPage, 5 ),   Instruction::new( Pla, Implied, 4 ),    Instruction::new( Adc, Immediate, 2 ),  Instruction::new( Ror, Implied, 2 ),    Instruction::new( Arr, Immediate, 2 ),  Instruction::new( Jmp, Indirect, 5 ),   Instruction::new( Adc, Absolute, 4 ),   Instruction::new( Ror, Absolute, 6 ),   Instruction::new( Rra, Absolute, 6 ),
+        Instruction::new( Bvs, Relative, 2 ),   Instruction::new( Adc, Indirect_Y, 5 ), Instruction::new( Kil, Implied, 2 ),    Instruction::new( Rra, Indirect_Y, 8 ), Instruction::new( Nop, ZeroPage_X, 4 ), Instruction::new( Adc, ZeroPage_X, 4 ), Instruction::new( Ror, ZeroPage_X, 6 ), Instruction::new( Rra, ZeroPage_X, 6 ), Instruction::new( Sei, Implied, 2 ),    Instruction::new( Adc, Absolute_Y, 4 ), Instruction::new( Nop, Implied, 2 ),    Instruction::new( Rra, Absolute_Y, 7 ), Instruction::new( Nop, Absolute_X, 4 ), Instruction::new( Adc, Absolute_X, 4 ), Instruction::new( Ror, Absolute_X, 7 ), Instruction::new( Rra, Absolute_X, 7 ),
+        Instruction::new( Nop, Immediate, 2 ),  Instruction::new( Sta, Indirect_X, 6 ), Instruction::new( Nop, Immediate, 2 ),  Instruction::new( Sax, Indirect_X, 6 ), Instruction::new( Sty, ZeroPage, 3 ),   Instruction::new( Sta, ZeroPage, 3 ),   Instruction::new( Stx, ZeroPage, 3 ),   Instruction::new( Sax, ZeroPage, 3 ),   Instruction::new( Dey, Implied, 2 ),    Instruction::new( Nop, Immediate, 2 ),  Instruction::new( Txa, Implied, 2 ),    Instruction::new( Xaa, Immediate, 2 ),  Instruction::new( Sty, Absolute, 4 ),   Instruction::new( Sta, Absolute, 4 ),   Instruction::new( Stx, Absolute, 4 ),   Instruction::new( Sax, Absolute, 4 ),
+        Instruction::new( Bcc, Relative, 2 ),   Instruction::new( Sta, Indirect_Y, 6 ), Instruction::new( Kil, Implied, 2 ),    Instruction::new( Ahx, Indirect_Y, 6 ), Instruction::new( Sty, ZeroPage_X, 4 ), Instruction::new( Sta, ZeroPage_X, 4 ), Instruction::new( Stx, ZeroPage_Y, 4 ), Instruction::new( Sax, ZeroPage_Y, 4 ), Instruction::new( Tya, Implied, 2 ),    Instruction::new( Sta, Absolute_Y, 5 ), Instruction::new( Txs, Implied, 2 ),    Instruction::new( Tas, Absolute_Y, 5 ), Instruction::new( Shy, Absolute_X, 5 ), Instruction::new( Sta, Absolute_X, 5 ), Instruction::new( Shx, Absolute_Y, 5 ), Instruction::new( Ahx, Absolute_Y, 5 ),
+        Instruction::new( Ldy, Immediate, 2 ),  Instruction::new( Lda, Indirect_X, 6 ), Instruction::new( Ldx, Immediate, 2 ),  Instruction::new( Lax, Indirect_X, 6 ), Instruction::new( Ldy, ZeroPage, 3 ),   Instruction::new( Lda, ZeroPage, 3 ),   Instruction::new( Ldx, ZeroPage, 3 ),   Instruction::new( Lax, ZeroPage, 3 ),   Instruction::new( Tay, Implied, 2 ),    Instruction::new( Lda, Immediate, 2 ),  Instruction::new( Tax, Implied, 2 ),    Instruction::new( Lxa, Immediate, 2 ),  Instruction::new( Ldy, Absolute, 4 ),   Instruction::new( Lda, Absolute, 4 ),   Instruction::new( Ldx, Absolute, 4 ),   Instruction::new( Lax, Absolute, 4 ),
+        Instruction::new( Bcs, Relative, 2 ),   Instruction::new( Lda, Indirect_Y, 5 ), Instruction::new( Kil, Implied, 2 ),    Instruction::new( Lax, Indirect_Y, 5 ), Instruction::new( Ldy, ZeroPage_X, 4 ), Instruction::new( Lda, ZeroPage_X, 4 ), Instruction::new( Ldx, ZeroPage_Y, 4 ), Instruction::new( Lax, ZeroPage_Y, 4 ), Instruction::new( Clv, Implied, 2 ),    Instruction::new( Lda, Absolute_Y, 4 ), Instruction::new( Tsx, Implied, 2 ),    Instruction::new( Las, Absolute_Y, 4 ), Instruction::new( Ldy, Absolute_X, 4 ), Instruction::new( Lda, Absolute_X, 4 ), Instruction::new( Ldx, Absolute_Y, 4 ), Instruction::new( Lax, Absolute_Y, 4 ),
+        Instruction::new( Cpy, Immediate, 2 ),  Instruction::new( Cmp, Indirect_X, 6 ), Instruction::new( Nop, Immediate, 2 ),  Instruction::new( Dcp, Indirect_X, 8 ), Instruction::new( Cpy, ZeroPage, 3 ),   Instruction::new( Cmp, ZeroPage, 3 ),   Instruction::new( Dec, ZeroPage, 5 ),   Instruction::new( Dcp, ZeroPage, 5 ),   Instruction::new( Iny, Implied, 2 ),    Instruction::new( Cmp, Immediate, 2 ),  Instruction::new( Dex, Implied, 2 ),    Instruction::new( Axs, Immediate, 2 ),  Instruction::new( Cpy, Absolute, 4 ),   Instruction::new( Cmp, Absolute, 4 ),   Instruction::new( Dec, Absolute, 6 ),   Instruction::new( Dcp, Absolute, 6 ),
+        Instruction::new( Bne, Relative, 2 ),   Instruction::new( Cmp, Indirect_Y, 5 ), Instruction::new( Kil, Implied, 2 ),    Instruction::new( Dcp, Indirect_Y, 8 ), Instruction::new( Nop, ZeroPage_X, 4 ), Instruction::new( Cmp, ZeroPage_X, 4 ), Instruction::new( Dec, ZeroPage_X, 6 ), Instruction::new( Dcp, ZeroPage_X, 6 ), Instruction::new( Cld, Implied, 2 ),    Instruction::new( Cmp, Absolute_Y, 4 ), Instruction::new( Nop, Implied, 2 ),    Instruction::new( Dcp, Absolute_Y, 7 ), Instruction::new( Nop, Absolute_X, 4 ), Instruction::new( Cmp, Absolute_X, 4 ), Instruction::new( Dec, Absolute_X, 7 ), Instruction::new( Dcp, Absolute_X, 7 ),
+        Instruction::new( Cpx, Immediate, 2 ),  Instruction::new( Sbc, Indirect_X, 6 ), Instruction::new( Nop, Immediate, 2 ),  Instruction::new( Isc, Indirect_X, 8 ), Instruction::new( Cpx, ZeroPage, 3 ),   Instruction::new( Sbc, ZeroPage, 3 ),   Instruction::new( Inc, ZeroPage, 5 ),   Instruction::new( Isc, ZeroPage, 5 ),   Instruction::new( Inx, Implied, 2 ),    Instruction::new( Sbc, Immediate, 2 ),  Instruction::new( Nop, Implied, 2 ),    Instruction::new( Sbc, Immediate, 2 ),  Instruction::new( Cpx, Absolute, 4 ),   Instruction::new( Sbc, Absolute, 4 ),   Instruction::new( Inc, Absolute, 6 ),   Instruction::new( Isc, Absolute, 6 ),
+        Instruction::new( Beq, Relative, 2 ),   Instruction::new( Sbc, Indirect_Y, 5 ), Instruction::new( Kil, Implied, 2 ),    Instruction::new( Isc, Indirect_Y, 8 ), Instruction::new( Nop, ZeroPage_X, 4 ), Instruction::new( Sbc, ZeroPage_X, 4 ), Instruction::new( Inc, ZeroPage_X, 6 ), Instruction::new( Isc, ZeroPage_X, 6 ), Instruction::new( Sed, Implied, 2 ),    Instruction::new( Sbc, Absolute_Y, 4 ), Instruction::new( Nop, Implied, 2 ),    Instruction::new( Isc, Absolute_Y, 7 ), Instruction::new( Nop, Absolute_X, 4 ), Instruction::new( Sbc, Absolute_X, 4 ), Instruction::new( Inc, Absolute_X, 7 ), Instruction::new( Isc, Absolute_X, 7 ),
     ];
 }
 
+/// Whether `opcode` is one of the 151 documented encodings.
+pub fn is_official_opcode(opcode: u8) -> bool {
+    let instruction = &CPU_INSTRUCTIONS[opcode as usize];
+
+    instruction.opcode.is_official() && (instruction.opcode != Nop || opcode == 0xEA) && opcode != 0xEB
+}
+
 impl Display for Opcode {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let formatted_opcode = match self {
@@ -892,7 +1027,26 @@ impl Display for Opcode {
             Txa => "TXA",
             Txs => "TXS",
             Tya => "TYA",
-            Kil => "KIL"
+            Kil => "KIL",
+            Lax => "LAX",
+            Sax => "SAX",
+            Dcp => "DCP",
+            Isc => "ISC",
+            Slo => "SLO",
+            Rla => "RLA",
+            Sre => "SRE",
+            Rra => "RRA",
+            Anc => "ANC",
+            Alr => "ALR",
+            Arr => "ARR",
+            Axs => "AXS",
+            Las => "LAS",
+            Xaa => "XAA",
+            Lxa => "LXA",
+            Ahx => "AHX",
+            Tas => "TAS",
+            Shx => "SHX",
+            Shy => "SHY",
         };
 
         write!(f, "{}", formatted_opcode)
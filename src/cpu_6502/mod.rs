@@ -1,15 +1,18 @@
 pub mod instruction;
+pub mod disassembler;
+pub mod assembler;
+mod cycle;
 mod tests;
 
-extern crate fxhash;
+use std::collections::VecDeque;
 
-use fxhash::FxHashMap;
 use instruction::*;
-use crate::bus::*;
+use crate::bus::BusInterface;
 
 const NME_BASE: u16 = 0xFFFA;
 const RSR_BASE: u16 = 0xFFFC;
 const IRQ_BASE: u16 = 0xFFFE;
+const STACK_BASE: u16 = 0x0100;
 
 #[derive(Debug)]
 pub struct Cpu {
@@ -19,14 +22,114 @@ pub struct Cpu {
     stk_ptr: u8, // Stack Pointer points to a location on the Bus
     pc: u16,       // Program Counter
     status: u8,   // Status Register
-    bus: *mut Bus,
     fetched: u8,
     cycles_remaining: u8,
     clock_count: usize,
     addr_abs: u16,
     addr_rel: u16,
     opcode: u8,
-    next_instruction: Option<Instruction>,
+    unstable_opcodes: UnstableOpcodes,
+    jammed: bool,
+    events: VecDeque<CpuEvent>,
+    execution_mode: ExecutionMode,
+    step: u8,
+    ptr: u16,
+    interrupt_vector: Option<u16>,
+    irq_sources: u8,
+    nmi_line: bool,
+    nmi_pending: bool,
+    /// What the CPU decided the last time it looked at its interrupt
+    /// inputs. It is only acted on between instructions.
+    interrupt_poll: bool,
+    /// Instruction mode only: the I flag the poll at the end of the current
+    /// instruction sees, or None while an interrupt sequence runs.
+    poll_i_flag: Option<bool>,
+    /// Whether the last cycle went to a DMA instead of the CPU.
+    stalled: bool,
+}
+
+/// The devices that can pull the IRQ line low. The line is wired-OR, so it
+/// stays asserted until every source has let go.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IrqSource {
+    FrameCounter = 0x01,
+    Dmc = 0x02,
+    Mapper = 0x04,
+    External = 0x08,
+}
+
+/// The programmer-visible registers plus the number of cycles run so far.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CpuState {
+    pub a: u8,
+    pub x: u8,
+    pub y: u8,
+    pub sp: u8,
+    pub pc: u16,
+    pub p: u8,
+    pub cycles: usize,
+}
+
+/// One instruction run by `Cpu::step_instruction`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Step {
+    /// Where the opcode was fetched from.
+    pub addr: u16,
+    pub opcode: u8,
+    pub instruction: &'static Instruction,
+    /// The bytes after the opcode, little endian; `None` for implied and
+    /// accumulator instructions.
+    pub operand: Option<u16>,
+    pub cycles: usize,
+    /// The registers once the instruction has finished.
+    pub state: CpuState,
+}
+
+/// How much work a single `Cpu::clock` call does.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ExecutionMode {
+    /// The whole instruction runs on its first cycle, the remaining cycles
+    /// only pass time. Fast, and exact at instruction boundaries.
+    #[default]
+    Instruction,
+    /// Every clock performs the one bus read or write the 6502 does on that
+    /// cycle, dummy accesses included. Needed when other devices look at the
+    /// bus mid-instruction (PPU register polling, mapper IRQ counters, DMA).
+    Cycle,
+}
+
+/// Something the embedding application should know about, queued by the
+/// CPU and drained with `Cpu::poll_event`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CpuEvent {
+    /// A KIL/JAM opcode at `addr` locked the CPU up until the next reset.
+    Jammed { addr: u16, opcode: u8 },
+}
+
+impl std::fmt::Display for CpuEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CpuEvent::Jammed { addr, opcode } => write!(f, "CPU jammed at ${:04X} (opcode ${:02X})", addr, opcode),
+        }
+    }
+}
+
+/// How the unstable unofficial opcodes (XAA, LXA, AHX, TAS, SHX, SHY)
+/// behave. Their results depend on the individual chip and temperature, so
+/// games never rely on them, but some test ROMs do.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UnstableOpcodes {
+    /// XAA and LXA OR A with `magic` before ANDing, the stores AND their value
+    /// with the target's high byte + 1 and corrupt the address on page crossings.
+    Emulate { magic: u8 },
+    /// Treat them as NOPs of the same length and timing.
+    Nop,
+}
+
+impl Default for UnstableOpcodes {
+    fn default() -> Self {
+        UnstableOpcodes::Emulate { magic: 0xEE }
+    }
 }
 
 pub enum Flags6502 {
@@ -40,6 +143,12 @@ pub enum Flags6502 {
     Negative = 1 << 7,
 }
 
+impl Default for Cpu {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 //public
 impl Cpu {
     pub fn new() -> Self {
@@ -50,266 +159,274 @@ impl Cpu {
             stk_ptr: 0x00,
             pc: 0x0000,
             status: 0x00,
-            bus: std::ptr::null_mut(),
             fetched: 0,
             cycles_remaining: 0,
             clock_count: 0,
             addr_abs: 0x0000,
             addr_rel: 0x0000,
             opcode: 0x00,
-            next_instruction: None,
+            unstable_opcodes: UnstableOpcodes::default(),
+            jammed: false,
+            events: VecDeque::new(),
+            execution_mode: ExecutionMode::default(),
+            step: 0,
+            ptr: 0x0000,
+            interrupt_vector: None,
+            irq_sources: 0x00,
+            nmi_line: false,
+            nmi_pending: false,
+            interrupt_poll: false,
+            poll_i_flag: None,
+            stalled: false,
         }
     }
 
-    pub fn connect_to_bus(&mut self, bus_ptr: *mut Bus) {
-        self.bus = bus_ptr;
-    }
-
-    pub fn read(&self, addr: u16) -> u8 {
-        unsafe {
-            return (*self.bus).read(addr, true);
-        }
-    }
-    
-    pub fn write(&self, addr: u16, data: u8) {
-        unsafe {
-            (*self.bus).write(addr, data);
-        }
-    }
-
-    pub fn reset(&mut self) {
-        self.addr_abs = RSR_BASE;
-        let lo: u16 = self.read(self.addr_abs + 0) as u16;
-        let hi: u16 = self.read(self.addr_abs + 1) as u16;
-        self.pc = hi << 8 | lo;
+    pub fn reset(&mut self, bus: &mut impl BusInterface) {
+        self.pc = self.read_word(bus, RSR_BASE);
 
         self.a_reg = 0x00;
         self.x_reg = 0x00;
         self.y_reg = 0x00;
         self.stk_ptr = 0xFD;
-        self.status = 0x00 | Flags6502::Unused as u8;
+        self.status = Flags6502::Unused as u8 | Flags6502::InterruptDisable as u8;
 
         self.addr_abs = 0x0000;
         self.addr_rel = 0x0000;
 
         self.fetched = 0x00;
 
-        self.cycles_remaining = 8;
+        self.jammed = false;
+        self.step = 0;
+        self.interrupt_vector = None;
+        self.nmi_pending = false;
+        self.interrupt_poll = false;
+        self.poll_i_flag = None;
+
+        self.cycles_remaining = 7;
     }
 
-    pub fn irq(&mut self) {
-        if self.get_flag(Flags6502::InterruptDisable) == 0 {
-            self.write(0x0100 + self.stk_ptr as u16, (self.pc >> 8) as u8 & 0x00FF);
-            self.stk_ptr -= 1;
-            self.write(0x0100 + self.stk_ptr as u16, self.pc as u8 & 0x00FF);
-            self.stk_ptr -= 1;
+    /// Asserts or releases one source's hold on the level-triggered IRQ
+    /// line. While any source holds it and interrupts are enabled the CPU
+    /// takes an IRQ after every instruction.
+    pub fn set_irq(&mut self, source: IrqSource, asserted: bool) {
+        match asserted {
+            true => self.irq_sources |= source as u8,
+            false => self.irq_sources &= !(source as u8),
+        }
+    }
 
-            self.set_flag(Flags6502::BreakCommand, false);
-            self.set_flag(Flags6502::InterruptDisable, true);
-            self.set_flag(Flags6502::Unused, true);
-            self.write(0x0000 + self.stk_ptr as u16, self.status);
-            self.stk_ptr -= 1;
-
-            self.addr_abs = IRQ_BASE;
-            let lo: u16 = self.addr_abs + 0;
-            let hi: u16 = self.addr_abs + 1;
-            self.pc = hi << 8 | lo;
-    
-            self.cycles_remaining = 7;
+    pub fn irq_line(&self) -> bool {
+        self.irq_sources != 0
+    }
+
+    /// Drives the NMI input, e.g. from the PPU's vblank output. NMI is edge
+    /// triggered: only a change from released to asserted raises one, no
+    /// matter how long the line is then held.
+    pub fn set_nmi_line(&mut self, asserted: bool) {
+        if asserted && !self.nmi_line {
+            self.nmi_pending = true;
         }
+        self.nmi_line = asserted;
     }
 
-    pub fn nmi(&mut self) {
-        self.write(0x0100 + self.stk_ptr as u16, (self.pc >> 8) as u8 & 0x00FF);
-            self.stk_ptr -= 1;
-            self.write(0x0100 + self.stk_ptr as u16, self.pc as u8 & 0x00FF);
-            self.stk_ptr -= 1;
+    pub fn clock(&mut self, bus: &mut impl BusInterface) {
+        // A jammed CPU stops fetching but time keeps passing for the rest of
+        // the system; only reset gets it going again.
+        if self.jammed {
+            self.clock_count += 1;
+            return;
+        }
 
-            self.set_flag(Flags6502::BreakCommand, false);
-            self.set_flag(Flags6502::InterruptDisable, true);
-            self.set_flag(Flags6502::Unused, true);
-            self.write(0x0000 + self.stk_ptr as u16, self.status);
-            self.stk_ptr -= 1;
+        // DMA halts the CPU between bus accesses, and only ahead of a read.
+        // Instruction mode only has instruction boundaries, where the next
+        // access is always the opcode fetch, so there a halt that would
+        // have landed partway through an instruction is late by up to the
+        // rest of it.
+        let at_access = self.execution_mode == ExecutionMode::Cycle || self.cycles_remaining == 0;
+        let next_is_read = match self.execution_mode {
+            ExecutionMode::Cycle => self.cycles_remaining > 0 || self.next_cycle_reads(),
+            _ => true,
+        };
+        self.stalled = at_access && bus.dma_cycle(next_is_read);
+        if self.stalled {
+            self.clock_count += 1;
+            return;
+        }
 
-            self.addr_abs = NME_BASE;
-            let lo: u16 = self.addr_abs + 0;
-            let hi: u16 = self.addr_abs + 1;
-            self.pc = hi << 8 | lo;
-    
-            self.cycles_remaining = 8;
-    }
+        if self.execution_mode == ExecutionMode::Cycle {
+            if self.cycles_remaining > 0 {
+                self.cycles_remaining -= 1;
+            }
+            else {
+                // Which vector gets used is only settled when P is pushed,
+                // see `interrupt_cycle`.
+                if self.step == 0 && self.interrupt_vector.is_none() && self.interrupt_poll {
+                    self.interrupt_poll = false;
+                    self.interrupt_vector = Some(IRQ_BASE);
+                }
+                self.clock_cycle(bus);
+            }
+            self.clock_count += 1;
+            return;
+        }
 
-    pub fn clock(&mut self) {
-        if self.cycles_remaining == 0 {
-            self.opcode = self.read(self.pc);
+        if self.cycles_remaining == 0 && self.interrupt_poll {
+            let vector = match self.nmi_pending {
+                true => NME_BASE,
+                false => IRQ_BASE,
+            };
+            self.nmi_pending = false;
+            self.interrupt_poll = false;
+            self.poll_i_flag = None;
+
+            self.interrupt(bus, vector);
+            self.cycles_remaining = 7;
+        }
+        else if self.cycles_remaining == 0 {
+            let i_flag = self.get_flag(Flags6502::InterruptDisable) != 0;
+
+            self.opcode = self.read(bus, self.pc);
 
             self.set_flag(Flags6502::Unused, true);
 
-            self.pc += 1;
+            self.pc = self.pc.wrapping_add(1);
 
             self.cycles_remaining = CPU_INSTRUCTIONS[self.opcode as usize].cycles;
 
-            let additional_cycle_addr_mode: u8 = (CPU_INSTRUCTIONS[self.opcode as usize].addr_mode).addr_mode_operation(self);
+            let additional_cycle_addr_mode: u8 = (CPU_INSTRUCTIONS[self.opcode as usize].addr_mode).addr_mode_operation(self, bus);
 
-            let additional_cycle_opcode: u8 = (CPU_INSTRUCTIONS[self.opcode as usize].opcode).opcode_operation(self);
+            let additional_cycle_opcode: u8 = (CPU_INSTRUCTIONS[self.opcode as usize].opcode).opcode_operation(self, bus);
 
             self.cycles_remaining += additional_cycle_addr_mode & additional_cycle_opcode;
 
             self.set_flag(Flags6502::Unused, true);
+
+            // The whole instruction has already run, so the poll has to be
+            // told what I was before CLI, SEI or PLP changed it. RTI is the
+            // exception: its new I is in place in time for its own poll.
+            self.poll_i_flag = match CPU_INSTRUCTIONS[self.opcode as usize].opcode {
+                Opcode::Brk => None,
+                Opcode::Rti => Some(self.get_flag(Flags6502::InterruptDisable) != 0),
+                _ => Some(i_flag),
+            };
         }
         self.clock_count += 1;
         self.cycles_remaining -= 1;
+
+        if self.cycles_remaining == 0 {
+            if let Some(i_flag) = self.poll_i_flag {
+                self.interrupt_poll = self.nmi_pending || (self.irq_line() && !i_flag);
+            }
+        }
     }
 
-    pub fn is_complete(&self) -> bool {
-        self.cycles_remaining == 0
+    /// Finishes whatever is in flight (reset, interrupt or a partly clocked
+    /// instruction), then runs exactly one instruction. A DMA stall that
+    /// holds the instruction up is counted in its cycles.
+    pub fn step_instruction(&mut self, bus: &mut impl BusInterface) -> Step {
+        while !self.is_complete() || (self.interrupt_poll && !self.jammed) {
+            self.clock(bus);
+        }
+
+        let addr = self.pc;
+        let opcode = bus.peek(addr);
+        let instruction: &'static Instruction = &CPU_INSTRUCTIONS[opcode as usize];
+        let operand = match instruction.addr_mode.operand_len() {
+            0 => None,
+            1 => Some(bus.peek(addr.wrapping_add(1)) as u16),
+            _ => Some(bus.peek(addr.wrapping_add(1)) as u16 | (bus.peek(addr.wrapping_add(2)) as u16) << 8),
+        };
+
+        let start = self.clock_count;
+        self.clock(bus);
+        while self.stalled || !self.is_complete() {
+            self.clock(bus);
+        }
+
+        Step {
+            addr,
+            opcode,
+            instruction,
+            operand,
+            cycles: self.clock_count - start,
+            state: self.state(),
+        }
     }
 
-    pub fn disassemble(&self, start: u16, stop: u16) -> FxHashMap<u16, String> {
-        use AddressingMode::*;
-        use std::fmt::Write;
+    pub fn state(&self) -> CpuState {
+        CpuState {
+            a: self.a_reg,
+            x: self.x_reg,
+            y: self.y_reg,
+            sp: self.stk_ptr,
+            pc: self.pc,
+            p: self.status,
+            cycles: self.clock_count,
+        }
+    }
 
-        let mut addr = start as u32;
-        let mut value = 0x00;
-        let mut lo: u8 = 0x00;
-        let mut hi: u8 = 0x00;
-        let mut map_lines: FxHashMap<u16, String> = FxHashMap::default();
-        let mut line_addr: u16 = 0;
+    pub fn set_a(&mut self, a: u8) {
+        self.a_reg = a;
+    }
 
-        fn hex_converter(mut n: u32, d: u8) -> String {
-            let mut s = vec!['0'; d as usize];
-            let hex_chars = "0123456789ABCEDF".chars().collect::<Vec<char>>();
+    pub fn set_x(&mut self, x: u8) {
+        self.x_reg = x;
+    }
 
-            for i in (0..d).rev() {
-                s[i as usize] = hex_chars[(n & 0xF) as usize];
-                n >>= 4;
-            }
+    pub fn set_y(&mut self, y: u8) {
+        self.y_reg = y;
+    }
 
-            s.into_iter().collect()
-        }
+    pub fn set_sp(&mut self, sp: u8) {
+        self.stk_ptr = sp;
+    }
 
-        while addr <= stop as u32 {
-            line_addr = addr as u16;
+    pub fn set_pc(&mut self, pc: u16) {
+        self.pc = pc;
+    }
 
-            let mut inst = String::from("$".to_string() + hex_converter(addr, 4).as_str() + ": ");
+    pub fn set_status(&mut self, p: u8) {
+        self.status = p;
+    }
 
-            let opcode;
-            unsafe {
-                opcode = (*self.bus).read(addr as u16, true);
-            }
-            addr += 1;
-            write!(inst, "{} ", CPU_INSTRUCTIONS[opcode as usize].opcode).unwrap();
+    pub fn set_unstable_opcodes(&mut self, unstable_opcodes: UnstableOpcodes) {
+        self.unstable_opcodes = unstable_opcodes;
+    }
 
-            match CPU_INSTRUCTIONS[opcode as usize].addr_mode {
-                Implied => {
-                    write!(inst, " {{IMP}}").unwrap();
-                }
-                Immediate => {
-                    unsafe {
-                        value = (*self.bus).read(addr as u16, true);
-                    }
-                    addr += 1;
-                    write!(inst, "#${} {{IMM}}", hex_converter(value as u32, 2)).unwrap();
-                }
-                ZeroPage => {
-                    unsafe {
-                        lo = (*self.bus).read(addr as u16, true);
-                    }
-                    addr += 1;
-                    hi = 0x00;
-                    write!(inst, "${} {{ZP0}}", hex_converter(lo as u32, 2)).unwrap();
-                }
-                ZeroPage_X => {
-                    unsafe {
-                        lo = (*self.bus).read(addr as u16, true);
-                    }
-                    addr += 1;
-                    hi = 0x00;
-                    write!(inst, "${}, X {{ZPX}}", hex_converter(lo as u32, 2)).unwrap();
-                }
-                ZeroPage_Y => {
-                    unsafe {
-                        lo = (*self.bus).read(addr as u16, true);
-                    }
-                    addr += 1;
-                    hi = 0x00;
-                    write!(inst, "${}, Y {{ZPY}}", hex_converter(lo as u32, 2)).unwrap();
-                }
-                Indirect_X => {
-                    unsafe {
-                        lo = (*self.bus).read(addr as u16, true);
-                    }
-                    addr += 1;
-                    hi = 0x00;
-                    write!(inst, "(${}, X) {{IZX}}", hex_converter(lo as u32, 2)).unwrap();
-                }
-                Indirect_Y => {
-                    unsafe {
-                        lo = (*self.bus).read(addr as u16, true);
-                    }
-                    addr += 0;
-                    hi = 0x00;
-                    write!(inst, "(${}), Y {{IZY}}", hex_converter(lo as u32, 2)).unwrap();
-                }
-                Absolute => {
-                    unsafe {
-                        lo = (*self.bus).read(addr as u16, true);
-                        addr += 1;
-                        hi = (*self.bus).read(addr as u16, true);
-                        addr += 1;
-                    }
-                    write!(inst, "${} {{ABS}}", hex_converter(((hi as u32 )<< 8) | lo as u32, 4)).unwrap();
-                }
-                Absolute_X => {
-                    unsafe {
-                        lo = (*self.bus).read(addr as u16, true);
-                        addr += 1;
-                        hi = (*self.bus).read(addr as u16, true);
-                        addr += 1;
-                    }
-                    write!(inst, "${}, X {{ABX}}", hex_converter(((hi as u32) << 8) | lo as u32, 4)).unwrap();
-                }
-                Absolute_Y => {
-                    unsafe {
-                        lo = (*self.bus).read(addr as u16, true);
-                        addr += 1;
-                        hi = (*self.bus).read(addr as u16, true);
-                        addr += 1;
-                    }
-                    write!(inst, "${}, Y {{ABX}}", hex_converter(((hi as u32) << 8) | lo as u32, 4)).unwrap();
-                }
-                Indirect => {
-                    unsafe {
-                        lo = (*self.bus).read(addr as u16, true);
-                        addr += 1;
-                        hi = (*self.bus).read(addr as u16, true);
-                        addr += 1;
-                    }
-                    write!(inst, "(${}) {{IND}}", hex_converter(((hi as u32) << 8) | lo as u32, 4)).unwrap();
-                }
-                Relative => {
-                    unsafe {
-                        value = (*self.bus).read(addr as u16, true);
-                    }
-                    write!(inst, "${} [${}] {{REL}}", hex_converter(value as u32, 2), hex_converter(addr, 4)).unwrap();
-                }
-            }
+    pub fn is_halted(&self) -> bool {
+        self.jammed
+    }
 
-            map_lines.insert(line_addr, inst);
-        }
+    pub fn poll_event(&mut self) -> Option<CpuEvent> {
+        self.events.pop_front()
+    }
 
-        map_lines
+    /// Only switch at instruction boundaries, i.e. when `is_complete`.
+    pub fn set_execution_mode(&mut self, execution_mode: ExecutionMode) {
+        self.execution_mode = execution_mode;
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.jammed || (self.cycles_remaining == 0 && self.step == 0 && self.interrupt_vector.is_none())
     }
 }
 
 //private
 impl Cpu {
+    fn read(&self, bus: &mut impl BusInterface, addr: u16) -> u8 {
+        bus.read(addr, false)
+    }
+
+    fn write(&self, bus: &mut impl BusInterface, addr: u16, data: u8) {
+        bus.write(addr, data);
+    }
+
     fn get_flag(&self, flag: Flags6502) -> u8 {
         if (self.status & flag as u8) > 0 {
-            return 1;
+            1
         } 
         else {
-            return 0;
+            0
         }
     }
 
@@ -321,9 +438,122 @@ impl Cpu {
         }
     }
 
-    fn fetch(&mut self) {
-        if !(CPU_INSTRUCTIONS[self.opcode as usize].addr_mode == AddressingMode::Implied) {
-            self.fetched = self.read(self.addr_abs);
+    fn read_word(&self, bus: &mut impl BusInterface, addr: u16) -> u16 {
+        let lo = self.read(bus, addr) as u16;
+        let hi = self.read(bus, addr.wrapping_add(1)) as u16;
+
+        (hi << 8) | lo
+    }
+
+    // Zero page pointers wrap within the zero page: ($FF) reads $FF and $00.
+    fn read_zero_page_word(&self, bus: &mut impl BusInterface, ptr: u8) -> u16 {
+        let lo = self.read(bus, ptr as u16) as u16;
+        let hi = self.read(bus, ptr.wrapping_add(1) as u16) as u16;
+
+        (hi << 8) | lo
+    }
+
+    fn push(&mut self, bus: &mut impl BusInterface, data: u8) {
+        self.write(bus, STACK_BASE + self.stk_ptr as u16, data);
+        self.stk_ptr = self.stk_ptr.wrapping_sub(1);
+    }
+
+    fn pull(&mut self, bus: &mut impl BusInterface) -> u8 {
+        self.stk_ptr = self.stk_ptr.wrapping_add(1);
+        self.read(bus, STACK_BASE + self.stk_ptr as u16)
+    }
+
+    /// Whether an interrupt should follow the current instruction, as the
+    /// 6502 samples it before each instruction's last cycle.
+    fn poll_interrupts(&mut self) {
+        self.interrupt_poll = self.nmi_pending || (self.irq_line() && self.get_flag(Flags6502::InterruptDisable) == 0);
+    }
+
+    /// An NMI that arrives before P is pushed takes over an IRQ or BRK
+    /// sequence already in progress, which then jumps through $FFFA.
+    fn hijack_vector(&mut self, vector: u16) -> u16 {
+        match self.nmi_pending {
+            true => {
+                self.nmi_pending = false;
+                NME_BASE
+            }
+            false => vector,
+        }
+    }
+
+    fn interrupt(&mut self, bus: &mut impl BusInterface, vector: u16) {
+        self.push(bus, (self.pc >> 8) as u8);
+        self.push(bus, self.pc as u8);
+
+        self.set_flag(Flags6502::BreakCommand, false);
+        self.set_flag(Flags6502::Unused, true);
+        self.push(bus, self.status);
+        self.set_flag(Flags6502::InterruptDisable, true);
+
+        self.pc = self.read_word(bus, vector);
+    }
+
+    // Taken branches cost one extra cycle, two if they land on another page.
+    fn branch(&mut self, condition: bool) {
+        if condition {
+            self.cycles_remaining += 1;
+            self.addr_abs = self.pc.wrapping_add(self.addr_rel);
+
+            if (self.addr_abs & 0xFF00) != (self.pc & 0xFF00) {
+                self.cycles_remaining += 1;
+            }
+
+            self.pc = self.addr_abs;
+        }
+    }
+
+    fn compare(&mut self, register: u8, value: u8) {
+        let temp = register.wrapping_sub(value);
+
+        self.set_flag(Flags6502::Carry, register >= value);
+        self.set_flag(Flags6502::Zero, temp == 0x00);
+        self.set_flag(Flags6502::Negative, (temp & 0x80) != 0);
+    }
+
+    // Shared by ADC and SBC; the 2A03 has no decimal mode.
+    fn add_with_carry(&mut self, value: u8) {
+        let temp = self.a_reg as u16 + value as u16 + self.get_flag(Flags6502::Carry) as u16;
+
+        self.set_flag(Flags6502::Carry, temp > 0x00FF);
+        self.set_flag(Flags6502::Zero, (temp & 0x00FF) == 0);
+        self.set_flag(Flags6502::Overflow, ((self.a_reg as u16 ^ temp) & (value as u16 ^ temp) & 0x0080) != 0);
+        self.set_flag(Flags6502::Negative, (temp & 0x0080) != 0);
+
+        self.a_reg = (temp & 0x00FF) as u8;
+    }
+
+    // AHX/TAS/SHX/SHY store `value & (H + 1)`, H being the high byte of the
+    // unindexed base address. When indexing crosses a page the stored value
+    // also replaces the high byte of the target address.
+    fn store_and_high_byte(&mut self, bus: &mut impl BusInterface, value: u8, index: u8) {
+        if self.unstable_opcodes == UnstableOpcodes::Nop {
+            return;
+        }
+
+        let base = self.addr_abs.wrapping_sub(index as u16);
+        let data = value & ((base >> 8) as u8).wrapping_add(1);
+
+        if (base & 0xFF00) != (self.addr_abs & 0xFF00) {
+            self.addr_abs = ((data as u16) << 8) | (self.addr_abs & 0x00FF);
+        }
+
+        self.write(bus, self.addr_abs, data);
+    }
+
+    fn jam(&mut self) {
+        self.pc = self.pc.wrapping_sub(1);
+        self.jammed = true;
+        self.events.push_back(CpuEvent::Jammed { addr: self.pc, opcode: self.opcode });
+    }
+
+    fn fetch(&mut self, bus: &mut impl BusInterface) {
+        if CPU_INSTRUCTIONS[self.opcode as usize].addr_mode != AddressingMode::Implied {
+            self.fetched = self.read(bus, self.addr_abs);
         }
     }
 }
@@ -1,4 +1,902 @@
 #[cfg(test)]
 mod cpu6502_tests {
-    
-}
\ No newline at end of file
+    use crate::bus::*;
+    use crate::cpu_6502::*;
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    enum Access {
+        Read(u16),
+        Write(u16, u8),
+    }
+
+    use Access::*;
+
+    struct RecordingBus {
+        mem: Vec<u8>,
+        accesses: Vec<Access>,
+    }
+
+    impl RecordingBus {
+        fn new() -> Self {
+            RecordingBus {
+                mem: vec![0x00; 64 * 1024],
+                accesses: Vec::new(),
+            }
+        }
+    }
+
+    impl BusInterface for RecordingBus {
+        fn read(&mut self, addr: u16, _readonly: bool) -> u8 {
+            self.accesses.push(Read(addr));
+            self.mem[addr as usize]
+        }
+
+        fn write(&mut self, addr: u16, data: u8) {
+            self.accesses.push(Write(addr, data));
+            self.mem[addr as usize] = data;
+        }
+
+        fn peek(&self, addr: u16) -> u8 {
+            self.mem[addr as usize]
+        }
+    }
+
+    #[test]
+    fn reset_loads_pc_from_vector_on_any_bus() {
+        let mut bus = RecordingBus::new();
+        bus.mem[0xFFFC] = 0x34;
+        bus.mem[0xFFFD] = 0x12;
+
+        let mut cpu = Cpu::new();
+        cpu.reset(&mut bus);
+
+        assert_eq!(cpu.pc, 0x1234);
+        assert_eq!(bus.accesses, vec![Read(0xFFFC), Read(0xFFFD)]);
+    }
+
+    #[test]
+    fn clock_fetches_through_the_supplied_bus() {
+        let mut bus = RecordingBus::new();
+        bus.mem[0xFFFC] = 0x00;
+        bus.mem[0xFFFD] = 0x80;
+        bus.mem[0x8000] = 0xEA; // NOP
+
+        let mut cpu = Cpu::new();
+        cpu.reset(&mut bus);
+        while !cpu.is_complete() {
+            cpu.clock(&mut bus);
+        }
+
+        cpu.clock(&mut bus);
+        assert_eq!(cpu.pc, 0x8001);
+        while !cpu.is_complete() {
+            cpu.clock(&mut bus);
+        }
+        assert_eq!(cpu.clock_count, 9);
+    }
+
+    /// The CPU-visible fields of one nestest.log line. The disassembly and
+    /// PPU columns are ignored so logs from other emulators compare too.
+    #[derive(Debug, PartialEq)]
+    struct TraceLine {
+        pc: u16,
+        a: u8,
+        x: u8,
+        y: u8,
+        p: u8,
+        sp: u8,
+        cyc: usize,
+    }
+
+    impl TraceLine {
+        fn parse(line: &str) -> Self {
+            let field = |key: &str| {
+                line.split_whitespace()
+                    .find_map(|token| token.strip_prefix(key))
+                    .unwrap_or_else(|| panic!("missing {} in {:?}", key, line))
+            };
+            let hex = |key: &str| u8::from_str_radix(field(key), 16).unwrap();
+
+            TraceLine {
+                pc: u16::from_str_radix(&line[0..4], 16).unwrap(),
+                a: hex("A:"),
+                x: hex("X:"),
+                y: hex("Y:"),
+                p: hex("P:"),
+                sp: hex("SP:"),
+                cyc: field("CYC:").parse().unwrap(),
+            }
+        }
+
+        fn capture(cpu: &Cpu) -> Self {
+            let state = cpu.state();
+
+            TraceLine {
+                pc: state.pc,
+                a: state.a,
+                x: state.x,
+                y: state.y,
+                p: state.p,
+                sp: state.sp,
+                cyc: state.cycles,
+            }
+        }
+    }
+
+    /// Starts at $C000 in nestest's automation mode and checks the CPU state
+    /// before every instruction against `lines`.
+    fn run_against_log<'a>(bus: &mut impl BusInterface, lines: impl Iterator<Item = &'a str>, mode: ExecutionMode) -> usize {
+        let mut cpu = Cpu::new();
+        cpu.set_execution_mode(mode);
+        cpu.reset(bus);
+        while !cpu.is_complete() {
+            cpu.clock(bus);
+        }
+        cpu.set_pc(0xC000);
+
+        let mut checked = 0;
+        for (number, line) in lines.enumerate() {
+            let expected = TraceLine::parse(line);
+            assert_eq!(TraceLine::capture(&cpu), expected, "log line {}: {}", number + 1, line);
+            checked += 1;
+
+            cpu.step_instruction(bus);
+        }
+
+        checked
+    }
+
+    /// Exercises every official mnemonic, the zero page and page-crossing
+    /// index modes, the JMP ($xxFF) bug and BRK/RTI.
+    const OFFICIAL_OPCODES_PROGRAM: &[(u16, &[u8])] = &[
+        (0xC000, &[
+            0xA2, 0x05, 0xA0, 0x80, 0xA9, 0x00, 0x85, 0x10, 0xA9, 0x7F, 0x69, 0x01, 0xB8, 0x38, 0xE9, 0x01,
+            0x18, 0x2A, 0x6A, 0x0A, 0x4A, 0x95, 0x10, 0xB5, 0x10, 0xE6, 0x15, 0xC6, 0x10, 0x24, 0x15, 0x29,
+            0x0F, 0x09, 0xF0, 0x49, 0x0F, 0xC9, 0xF0, 0xE0, 0x06, 0xC0, 0x80, 0xAA, 0xA8, 0x8A, 0x98, 0xBA,
+            0x9A, 0xE8, 0xC8, 0xCA, 0x88, 0x48, 0x08, 0xA9, 0x00, 0x28, 0x68, 0x20, 0x50, 0xC0, 0x78, 0x58,
+            0xF8, 0xD8, 0x8D, 0x00, 0x02, 0x8E, 0x01, 0x02, 0x8C, 0x02, 0x02, 0x4C, 0x60, 0xC0,
+        ]),
+        (0xC050, &[
+            0x60,
+        ]),
+        (0xC060, &[
+            0xA2, 0x00, 0xD0, 0x02, 0xF0, 0x02, 0xEA, 0xEA, 0xA9, 0xFF, 0x10, 0x02, 0x30, 0x02, 0xEA, 0xEA,
+            0x90, 0x02, 0xB0, 0x02, 0xEA, 0xEA, 0x69, 0x01, 0x70, 0x02, 0x50, 0x02, 0xEA, 0xEA, 0xA0, 0x02,
+            0xB9, 0xFF, 0xC0, 0xBE, 0xFF, 0xC0, 0xBD, 0x81, 0xC0, 0xA9, 0x00, 0x85, 0x20, 0xA9, 0x02, 0x85,
+            0x21, 0xA2, 0x10, 0xA1, 0x10, 0xA0, 0x01, 0xB1, 0x20, 0x91, 0x20, 0x96, 0xF0, 0xB6, 0xF0, 0xA9,
+            0xB0, 0x8D, 0xFF, 0x03, 0xA9, 0xC0, 0x8D, 0x00, 0x03, 0x6C, 0xFF, 0x03,
+        ]),
+        (0xC0B0, &[
+            0x00, 0xFF, 0x0E, 0x00, 0x02, 0x4E, 0x00, 0x02, 0x2E, 0x00, 0x02, 0x6E, 0x00, 0x02, 0xEE, 0x00,
+            0x02, 0xCE, 0x00, 0x02, 0xEA, 0x4C, 0xC5, 0xC0,
+        ]),
+        (0xC0E0, &[
+            0x40,
+        ]),
+        (0xC101, &[
+            0x80,
+        ]),
+    ];
+
+    #[test]
+    fn official_opcodes_match_golden_log() {
+        for mode in [ExecutionMode::Instruction, ExecutionMode::Cycle] {
+            let mut bus = RecordingBus::new();
+            for (origin, bytes) in OFFICIAL_OPCODES_PROGRAM {
+                let start = *origin as usize;
+                bus.mem[start..start + bytes.len()].copy_from_slice(bytes);
+            }
+            bus.mem[0xFFFE] = 0xE0;
+            bus.mem[0xFFFF] = 0xC0;
+
+            let log = include_str!("test_data/official_opcodes.log");
+            let checked = run_against_log(&mut bus, log.lines(), mode);
+
+            assert_eq!(checked, log.lines().count());
+            assert_eq!(&bus.mem[0x0200..0x0203], &[0x70, 0xFD, 0xF0]);
+            assert_eq!(bus.mem[0x00F1], 0x10);
+        }
+    }
+
+    /// Needs kevtris' nestest.nes and nestest.log, which are not
+    /// redistributed here: `cargo test -- --ignored` with both in `roms/`.
+    #[test]
+    #[ignore]
+    fn nestest_full_log() {
+        let root = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("roms");
+        let rom = std::fs::read(root.join("nestest.nes")).unwrap();
+        let log = std::fs::read_to_string(root.join("nestest.log")).unwrap();
+
+        let prg_size = rom[4] as usize * 16 * 1024;
+        let prg = &rom[16..16 + prg_size];
+        for mode in [ExecutionMode::Instruction, ExecutionMode::Cycle] {
+            let mut bus = RecordingBus::new();
+            bus.mem[0x8000..0xC000].copy_from_slice(&prg[..0x4000]);
+            bus.mem[0xC000..0x10000].copy_from_slice(&prg[prg_size - 0x4000..]);
+
+            assert_eq!(run_against_log(&mut bus, log.lines(), mode), log.lines().count());
+        }
+    }
+
+    /// Runs `program` from $8000 until the PC falls off its end, returning
+    /// the CPU and the number of cycles spent.
+    fn run_program(program: &[u8], setup: impl FnOnce(&mut Cpu, &mut RecordingBus)) -> (Cpu, RecordingBus, usize) {
+        run_program_in(ExecutionMode::Instruction, program, setup)
+    }
+
+    fn run_program_in(mode: ExecutionMode, program: &[u8], setup: impl FnOnce(&mut Cpu, &mut RecordingBus)) -> (Cpu, RecordingBus, usize) {
+        let mut bus = RecordingBus::new();
+        bus.mem[0x8000..0x8000 + program.len()].copy_from_slice(program);
+        bus.mem[0xFFFC] = 0x00;
+        bus.mem[0xFFFD] = 0x80;
+
+        let mut cpu = Cpu::new();
+        cpu.set_execution_mode(mode);
+        cpu.reset(&mut bus);
+        while !cpu.is_complete() {
+            cpu.clock(&mut bus);
+        }
+        setup(&mut cpu, &mut bus);
+        bus.accesses.clear();
+
+        let start = cpu.clock_count;
+        while cpu.pc < 0x8000 + program.len() as u16 {
+            cpu.clock(&mut bus);
+            while !cpu.is_complete() {
+                cpu.clock(&mut bus);
+            }
+        }
+
+        let cycles = cpu.clock_count - start;
+        (cpu, bus, cycles)
+    }
+
+    #[test]
+    fn lax_and_sax() {
+        let (cpu, bus, cycles) = run_program(&[0xA7, 0x10, 0x87, 0x11], |cpu, bus| {
+            bus.mem[0x0010] = 0x8C;
+            cpu.a_reg = 0xFF;
+        });
+
+        assert_eq!((cpu.a_reg, cpu.x_reg), (0x8C, 0x8C));
+        assert_eq!(cpu.status & Flags6502::Negative as u8, Flags6502::Negative as u8);
+        assert_eq!(bus.mem[0x0011], 0x8C);
+        assert_eq!(cycles, 3 + 3);
+    }
+
+    #[test]
+    fn read_modify_write_combinations() {
+        // DCP, ISC, SLO, RLA, SRE, RRA on $10-$15
+        let program = [0xC7, 0x10, 0xE7, 0x11, 0x07, 0x12, 0x27, 0x13, 0x47, 0x14, 0x67, 0x15];
+        let (cpu, bus, cycles) = run_program(&program, |cpu, bus| {
+            bus.mem[0x0010..0x0016].copy_from_slice(&[0x41, 0x0F, 0x81, 0x40, 0x03, 0x02]);
+            cpu.a_reg = 0x40;
+        });
+
+        assert_eq!(&bus.mem[0x0010..0x0016], &[0x40, 0x10, 0x02, 0x81, 0x01, 0x81]);
+        // 0x40 - 0x10 = 0x30, | 0x02 = 0x32, & 0x81 = 0x00, ^ 0x01 = 0x01, + 0x81
+        assert_eq!(cpu.a_reg, 0x82);
+        assert_eq!(cycles, 6 * 5);
+    }
+
+    #[test]
+    fn immediate_combinations() {
+        // ANC #$80, ALR #$FF, ARR #$FF, AXS #$01
+        let (cpu, _, _) = run_program(&[0x0B, 0x80, 0x4B, 0xFF], |cpu, _| {
+            cpu.a_reg = 0xC1;
+        });
+        assert_eq!(cpu.a_reg, 0x40);
+        assert_eq!(cpu.get_flag(Flags6502::Carry), 0);
+
+        let (cpu, _, _) = run_program(&[0x38, 0x6B, 0xFF], |cpu, _| {
+            cpu.a_reg = 0x80;
+        });
+        assert_eq!(cpu.a_reg, 0xC0);
+        assert_eq!(cpu.get_flag(Flags6502::Carry), 1);
+        assert_eq!(cpu.get_flag(Flags6502::Overflow), 1);
+
+        let (cpu, _, _) = run_program(&[0xCB, 0x01], |cpu, _| {
+            cpu.a_reg = 0x0F;
+            cpu.x_reg = 0xFC;
+        });
+        assert_eq!(cpu.x_reg, 0x0B);
+        assert_eq!(cpu.get_flag(Flags6502::Carry), 1);
+    }
+
+    #[test]
+    fn unofficial_cycle_counts() {
+        let y_crossing = |cpu: &mut Cpu, _: &mut RecordingBus| cpu.y_reg = 0x10;
+
+        // LAX $80F8,Y crosses a page and pays for it
+        assert_eq!(run_program(&[0xBF, 0xF8, 0x80], y_crossing).2, 5);
+        // SLO $80F8,Y always takes 7
+        assert_eq!(run_program(&[0x1B, 0xF8, 0x80], y_crossing).2, 7);
+        // NOP $80F8,X crosses a page
+        assert_eq!(run_program(&[0x1C, 0xF8, 0x80], |cpu, _| cpu.x_reg = 0x10).2, 5);
+        // NOP #$00, NOP $00, NOP $00,X
+        assert_eq!(run_program(&[0x80, 0x00, 0x04, 0x00, 0x14, 0x00], |_, _| {}).2, 2 + 3 + 4);
+    }
+
+    #[test]
+    fn unstable_opcodes_are_configurable() {
+        let xaa = |mode| {
+            run_program(&[0x8B, 0xFF], |cpu, _| {
+                cpu.set_unstable_opcodes(mode);
+                cpu.a_reg = 0x01;
+                cpu.x_reg = 0x3F;
+            }).0.a_reg
+        };
+        assert_eq!(xaa(UnstableOpcodes::Emulate { magic: 0xEE }), 0x2F);
+        assert_eq!(xaa(UnstableOpcodes::Emulate { magic: 0xFF }), 0x3F);
+        assert_eq!(xaa(UnstableOpcodes::Nop), 0x01);
+
+        // SHX $02F0,Y: stores X & $03, and the page crossing moves the write to $0300 & X
+        let (_, bus, cycles) = run_program(&[0x9E, 0xF0, 0x02], |cpu, _| {
+            cpu.x_reg = 0xFF;
+            cpu.y_reg = 0x20;
+        });
+        assert_eq!(bus.mem[0x0310], 0x03);
+        assert_eq!(cycles, 5);
+
+        let (_, bus, _) = run_program(&[0x9E, 0xF0, 0x02], |cpu, _| {
+            cpu.set_unstable_opcodes(UnstableOpcodes::Nop);
+            cpu.x_reg = 0xFF;
+            cpu.y_reg = 0x20;
+        });
+        assert!(bus.mem[0x0300..0x0320].iter().all(|&data| data == 0x00));
+    }
+
+    #[test]
+    fn kil_jams_until_reset_and_reports_it() {
+        let mut bus = RecordingBus::new();
+        bus.mem[0xFFFC] = 0x00;
+        bus.mem[0xFFFD] = 0x80;
+        bus.mem[0x8000..0x8003].copy_from_slice(&[0xE8, 0x02, 0xE8]); // INX, KIL, INX
+
+        let mut cpu = Cpu::new();
+        cpu.reset(&mut bus);
+        for _ in 0..50 {
+            cpu.clock(&mut bus);
+        }
+        cpu.set_nmi_line(true);
+        for _ in 0..50 {
+            cpu.clock(&mut bus);
+        }
+
+        assert!(cpu.is_halted());
+        assert!(cpu.is_complete());
+        assert_eq!(cpu.pc, 0x8001);
+        assert_eq!(cpu.x_reg, 0x01);
+        assert_eq!(cpu.clock_count, 100);
+
+        let event = cpu.poll_event().unwrap();
+        assert_eq!(event, CpuEvent::Jammed { addr: 0x8001, opcode: 0x02 });
+        assert_eq!(event.to_string(), "CPU jammed at $8001 (opcode $02)");
+        assert_eq!(cpu.poll_event(), None);
+
+        cpu.reset(&mut bus);
+        assert!(!cpu.is_halted());
+        assert_eq!(cpu.pc, 0x8000);
+    }
+
+    /// Every opcode, with the index registers set so indexed modes both stay
+    /// on and cross pages, must leave the same state after the same number
+    /// of cycles in both execution modes, with one bus access per cycle.
+    #[test]
+    fn cycle_mode_matches_instruction_mode() {
+        for opcode in 0x00..=0xFFu8 {
+            if CPU_INSTRUCTIONS[opcode as usize].opcode == Opcode::Kil {
+                continue;
+            }
+
+            for (operand, index) in [(0x20, 0x01), (0xF0, 0x20)] {
+                let program = [opcode, operand, 0x12];
+                let setup = |cpu: &mut Cpu, bus: &mut RecordingBus| {
+                    cpu.a_reg = 0x5A;
+                    cpu.x_reg = index;
+                    cpu.y_reg = index;
+                    cpu.status |= Flags6502::Carry as u8;
+                    bus.mem[0x0000..0x0100].iter_mut().enumerate().for_each(|(i, data)| *data = i as u8 ^ 0x12);
+                    bus.mem[0x1200..0x1400].iter_mut().enumerate().for_each(|(i, data)| *data = i as u8);
+                    bus.mem[0xFFFE] = 0x03;
+                    bus.mem[0xFFFF] = 0x80;
+                };
+
+                let (instruction_cpu, instruction_bus, instruction_cycles) = run_one(ExecutionMode::Instruction, &program, setup);
+                let (cycle_cpu, cycle_bus, cycle_cycles) = run_one(ExecutionMode::Cycle, &program, setup);
+
+                let context = format!("opcode ${:02X} operand ${:02X} index ${:02X}", opcode, operand, index);
+                assert_eq!(cycle_cpu.pc, instruction_cpu.pc, "{}", context);
+                assert_eq!(cycle_cycles, instruction_cycles, "{}", context);
+                assert_eq!(
+                    (cycle_cpu.a_reg, cycle_cpu.x_reg, cycle_cpu.y_reg, cycle_cpu.stk_ptr, cycle_cpu.status),
+                    (instruction_cpu.a_reg, instruction_cpu.x_reg, instruction_cpu.y_reg, instruction_cpu.stk_ptr, instruction_cpu.status),
+                    "{}", context
+                );
+                assert!(cycle_bus.mem == instruction_bus.mem, "{}", context);
+                assert_eq!(cycle_bus.accesses.len(), cycle_cycles, "{}", context);
+            }
+        }
+    }
+
+    /// Runs exactly one instruction of `program` from $8000.
+    fn run_one(mode: ExecutionMode, program: &[u8], setup: impl FnOnce(&mut Cpu, &mut RecordingBus)) -> (Cpu, RecordingBus, usize) {
+        let (mut cpu, mut bus, _) = run_program_in(mode, &[], |_, _| {});
+        bus.mem[0x8000..0x8000 + program.len()].copy_from_slice(program);
+        cpu.pc = 0x8000;
+        setup(&mut cpu, &mut bus);
+        bus.accesses.clear();
+
+        let start = cpu.clock_count;
+        cpu.clock(&mut bus);
+        while !cpu.is_complete() {
+            cpu.clock(&mut bus);
+        }
+
+        let cycles = cpu.clock_count - start;
+        (cpu, bus, cycles)
+    }
+
+    fn cycle_accesses(program: &[u8], setup: impl FnOnce(&mut Cpu, &mut RecordingBus)) -> Vec<Access> {
+        run_one(ExecutionMode::Cycle, program, setup).1.accesses
+    }
+
+    #[test]
+    fn read_modify_write_dummy_accesses() {
+        // INC $1234,X
+        let accesses = cycle_accesses(&[0xFE, 0x34, 0x12], |cpu, bus| {
+            cpu.x_reg = 0x01;
+            bus.mem[0x1235] = 0x41;
+        });
+
+        assert_eq!(accesses, vec![
+            Read(0x8000), Read(0x8001), Read(0x8002),
+            Read(0x1235), Read(0x1235), Write(0x1235, 0x41), Write(0x1235, 0x42),
+        ]);
+    }
+
+    #[test]
+    fn indexed_dummy_reads_hit_the_unfixed_page() {
+        // LDA $12FF,X crossing into $1300
+        let accesses = cycle_accesses(&[0xBD, 0xFF, 0x12], |cpu, _| cpu.x_reg = 0x01);
+        assert_eq!(accesses, vec![Read(0x8000), Read(0x8001), Read(0x8002), Read(0x1200), Read(0x1300)]);
+
+        // LDA $1200,X stays on the page: no dummy read
+        let accesses = cycle_accesses(&[0xBD, 0x00, 0x12], |cpu, _| cpu.x_reg = 0x01);
+        assert_eq!(accesses, vec![Read(0x8000), Read(0x8001), Read(0x8002), Read(0x1201)]);
+
+        // STA ($10),Y always reads before writing
+        let accesses = cycle_accesses(&[0x91, 0x10], |cpu, bus| {
+            cpu.a_reg = 0x77;
+            cpu.y_reg = 0x02;
+            bus.mem[0x0010] = 0xFF;
+            bus.mem[0x0011] = 0x12;
+        });
+        assert_eq!(accesses, vec![
+            Read(0x8000), Read(0x8001), Read(0x0010), Read(0x0011), Read(0x1201), Write(0x1301, 0x77),
+        ]);
+    }
+
+    #[test]
+    fn stack_and_branch_cycle_sequences() {
+        // JSR $9000
+        let accesses = cycle_accesses(&[0x20, 0x00, 0x90], |_, _| {});
+        assert_eq!(accesses, vec![
+            Read(0x8000), Read(0x8001), Read(0x01FD), Write(0x01FD, 0x80), Write(0x01FC, 0x02), Read(0x8002),
+        ]);
+
+        // BNE -5 taken, crossing back into page $7F
+        let accesses = cycle_accesses(&[0xD0, 0xFB], |_, _| {});
+        assert_eq!(accesses, vec![Read(0x8000), Read(0x8001), Read(0x8002), Read(0x80FD)]);
+
+        // PLA
+        let accesses = cycle_accesses(&[0x68], |_, _| {});
+        assert_eq!(accesses, vec![Read(0x8000), Read(0x8001), Read(0x01FD), Read(0x01FE)]);
+    }
+
+    #[test]
+    fn interrupts_take_seven_bus_cycles() {
+        let (mut cpu, mut bus, _) = run_program_in(ExecutionMode::Cycle, &[], |_, _| {});
+        cpu.pc = 0x8122;
+        cpu.status = Flags6502::Unused as u8;
+        bus.mem[0x8122] = 0xEA; // NOP
+        bus.mem[0xFFFA] = 0x00;
+        bus.mem[0xFFFB] = 0x90;
+
+        cpu.set_nmi_line(true);
+        cpu.step_instruction(&mut bus);
+        bus.accesses.clear();
+
+        let mut cycles = 0;
+        loop {
+            cpu.clock(&mut bus);
+            cycles += 1;
+            if cpu.is_complete() {
+                break;
+            }
+        }
+
+        assert_eq!(cycles, 7);
+        assert_eq!(cpu.pc, 0x9000);
+        assert_eq!(bus.accesses, vec![
+            Read(0x8123), Read(0x8123), Write(0x01FD, 0x81), Write(0x01FC, 0x23), Write(0x01FB, 0x20),
+            Read(0xFFFA), Read(0xFFFB),
+        ]);
+    }
+
+    #[test]
+    fn step_instruction_reports_what_ran() {
+        let mut bus = RecordingBus::new();
+        bus.mem[0xFFFC] = 0x00;
+        bus.mem[0xFFFD] = 0x80;
+        let program = assembler::assemble("LDA $12FF,X\nNOP", 0x8000).unwrap();
+        bus.mem[0x8000..0x8004].copy_from_slice(&program);
+        bus.mem[0x1300] = 0x80;
+
+        let mut cpu = Cpu::new();
+        cpu.reset(&mut bus);
+        cpu.set_x(0x01);
+        cpu.set_status(0x24);
+
+        let step = cpu.step_instruction(&mut bus);
+        assert_eq!(step.addr, 0x8000);
+        assert_eq!(step.opcode, 0xBD);
+        assert_eq!(step.instruction.opcode, Opcode::Lda);
+        assert_eq!(step.instruction.addr_mode, AddressingMode::Absolute_X);
+        assert_eq!(step.operand, Some(0x12FF));
+        assert_eq!(step.cycles, 5);
+        assert_eq!(step.state, CpuState { a: 0x80, x: 0x01, y: 0x00, sp: 0xFD, pc: 0x8003, p: 0xA4, cycles: 7 + 5 });
+
+        let step = cpu.step_instruction(&mut bus);
+        assert_eq!(step.operand, None);
+        assert_eq!(step.cycles, 2);
+        assert_eq!(step.state.pc, 0x8004);
+    }
+
+    #[test]
+    fn register_setters() {
+        let mut cpu = Cpu::new();
+        cpu.set_a(0x01);
+        cpu.set_x(0x02);
+        cpu.set_y(0x03);
+        cpu.set_sp(0x04);
+        cpu.set_pc(0x0506);
+        cpu.set_status(0x07);
+
+        assert_eq!(cpu.state(), CpuState { a: 0x01, x: 0x02, y: 0x03, sp: 0x04, pc: 0x0506, p: 0x07, cycles: 0 });
+    }
+
+    // BNE -2 ; LDA ($10),Y ; ASL A ; JMP ($1234) ; LDA $0012,X ; DCP $10 ; NOP $10 ; KIL
+    const DISASSEMBLY_PROGRAM: &[u8] = &[
+        0xD0, 0xFE, 0xB1, 0x10, 0x0A, 0x6C, 0x34, 0x12, 0xBD, 0x12, 0x00, 0xC7, 0x10, 0x04, 0x10, 0x02,
+    ];
+
+    #[test]
+    fn disassembles_into_structured_instructions() {
+        use crate::cpu_6502::disassembler::*;
+
+        let listing = disassemble_bytes(DISASSEMBLY_PROGRAM, 0x8000);
+        let addrs = listing.iter().map(|i| i.addr).collect::<Vec<u16>>();
+        assert_eq!(addrs, [0x8000, 0x8002, 0x8004, 0x8005, 0x8008, 0x800B, 0x800D, 0x800F]);
+
+        assert_eq!(listing[0], DisassembledInstruction {
+            addr: 0x8000,
+            bytes: vec![0xD0, 0xFE],
+            opcode: Opcode::Bne,
+            addr_mode: AddressingMode::Relative,
+            operand: Some(0xFE),
+            target: Some(0x8000),
+        });
+        assert_eq!(listing[1].operand, Some(0x10));
+        assert_eq!(listing[1].addr_mode, AddressingMode::Indirect_Y);
+        assert_eq!(listing[3].operand, Some(0x1234));
+        assert!(listing[2].is_accumulator());
+        assert!(!listing[5].is_official());
+
+        let mut bus = RecordingBus::new();
+        bus.mem[0x8000..0x8000 + DISASSEMBLY_PROGRAM.len()].copy_from_slice(DISASSEMBLY_PROGRAM);
+        assert_eq!(disassemble(&bus, 0x8000, 0x800F), listing);
+        assert!(bus.accesses.is_empty());
+
+        // Stops short of an instruction that does not fit.
+        assert_eq!(disassemble_bytes(&[0xEA, 0xAD, 0x00], 0).len(), 1);
+        assert_eq!(disassemble(&bus, 0xFFFF, 0xFFFF).len(), 1);
+    }
+
+    #[test]
+    fn disassembly_syntaxes() {
+        use crate::cpu_6502::disassembler::*;
+
+        let listing = disassemble_bytes(DISASSEMBLY_PROGRAM, 0x8000);
+        let format = |syntax: &dyn Fn(&DisassembledInstruction) -> String| listing.iter().map(syntax).collect::<Vec<String>>();
+
+        assert_eq!(format(&|i| i.format(&Ca65)), [
+            "bne $8000",
+            "lda ($10),y",
+            "asl a",
+            "jmp ($1234)",
+            "lda a:$0012,x",
+            "dcp $10",
+            ".byte $04,$10",
+            "jam",
+        ]);
+        assert_eq!(format(&|i| i.format(&Asm6)), [
+            "BNE $8000",
+            "LDA ($10),Y",
+            "ASL A",
+            "JMP ($1234)",
+            ".db $BD,$12,$00",
+            ".db $C7,$10",
+            ".db $04,$10",
+            ".db $02",
+        ]);
+        assert_eq!(format(&|i| i.to_string()), [
+            "8000  D0 FE     BNE $8000",
+            "8002  B1 10     LDA ($10),Y",
+            "8004  0A        ASL A",
+            "8005  6C 34 12  JMP ($1234)",
+            "8008  BD 12 00  LDA $0012,X",
+            "800B  C7 10    *DCP $10",
+            "800D  04 10    *NOP $10",
+            "800F  02       *KIL",
+        ]);
+    }
+
+    #[test]
+    fn official_opcode_count() {
+        assert_eq!((0..=255u8).filter(|&opcode| is_official_opcode(opcode)).count(), 151);
+    }
+
+    #[test]
+    fn assembles_labels_constants_and_data() {
+        use crate::cpu_6502::assembler::*;
+
+        let source = "
+            PPUCTRL = $2000
+            ptr = $10
+
+            start:  ldx #0          ; count up
+            loop:   lda table,x
+                    sta (ptr),y
+                    sta PPUCTRL
+                    sta a:ptr
+                    inx
+                    cpx #end-table
+                    bne loop
+                    jmp done
+            table:  .byte $01, %10, 3
+            end:
+            done:   asl A
+                    .word start, >done
+        ";
+
+        assert_eq!(assemble(source, 0xC000).unwrap(), [
+            0xA2, 0x00,
+            0xBD, 0x15, 0xC0,
+            0x91, 0x10,
+            0x8D, 0x00, 0x20,
+            0x8D, 0x10, 0x00,
+            0xE8,
+            0xE0, 0x03,
+            0xD0, 0xF0,
+            0x4C, 0x18, 0xC0,
+            0x01, 0x02, 0x03,
+            0x0A,
+            0x00, 0xC0, 0xC0, 0x00,
+        ]);
+    }
+
+    #[test]
+    fn ca65_disassembly_assembles_back_to_the_same_bytes() {
+        use crate::cpu_6502::assembler::*;
+        use crate::cpu_6502::disassembler::*;
+
+        for opcode in 0..=255u8 {
+            let instruction = &disassemble_bytes(&[opcode, 0x34, 0x12], 0x8000)[0];
+            let source = instruction.format(&Ca65);
+
+            assert_eq!(assemble(&source, 0x8000).as_deref(), Ok(&instruction.bytes[..]), "{}", source);
+        }
+    }
+
+    #[test]
+    fn assembler_errors_name_the_line() {
+        use crate::cpu_6502::assembler::*;
+
+        let error = |source| assemble(source, 0x8000).unwrap_err();
+
+        assert_eq!(error("nop\nfoo #1"), AssembleError { line: 2, kind: AssembleErrorKind::UnknownMnemonic("foo".to_string()) });
+        assert_eq!(error("stx $1234,x").kind, AssembleErrorKind::UnsupportedOperand { opcode: Opcode::Stx, operand: "$1234,x".to_string() });
+        assert_eq!(error("jmp nowhere").kind, AssembleErrorKind::UndefinedSymbol("nowhere".to_string()));
+        assert_eq!(error("x: nop\nx: nop").kind, AssembleErrorKind::DuplicateSymbol("x".to_string()));
+        assert_eq!(error("lda #$100").kind, AssembleErrorKind::ValueOutOfRange(0x100));
+        assert_eq!(error("beq far\n.word 0\nfar = $9000").to_string(), "line 1: branch at $8000 cannot reach $9000");
+    }
+
+    #[test]
+    fn irq_line_is_level_triggered() {
+        let program = assembler::assemble("
+                    cli
+            loop:   jmp loop
+            irq:    inc $10
+                    rti
+        ", 0x8000).unwrap();
+
+        for mode in [ExecutionMode::Instruction, ExecutionMode::Cycle] {
+            let mut bus = RecordingBus::new();
+            bus.mem[0x8000..0x8000 + program.len()].copy_from_slice(&program);
+            bus.mem[0xFFFC..=0xFFFF].copy_from_slice(&[0x00, 0x80, 0x04, 0x80]);
+
+            let mut cpu = Cpu::new();
+            cpu.set_execution_mode(mode);
+            cpu.reset(&mut bus);
+
+            // Masked until CLI runs, and for the instruction after it
+            cpu.set_irq(IrqSource::Mapper, true);
+            cpu.set_irq(IrqSource::FrameCounter, true);
+            cpu.step_instruction(&mut bus);
+            assert_eq!(bus.mem[0x10], 0);
+
+            // Taken again after every RTI for as long as the line is held,
+            // stopping short of the third RTI's poll
+            for _ in 0..3 + 18 * 3 - 6 {
+                cpu.clock(&mut bus);
+            }
+            assert_eq!(bus.mem[0x10], 3, "{:?}", mode);
+
+            // Wired-OR: one source letting go is not enough
+            cpu.set_irq(IrqSource::Mapper, false);
+            assert!(cpu.irq_line());
+            cpu.set_irq(IrqSource::FrameCounter, false);
+            assert!(!cpu.irq_line());
+            while !cpu.is_complete() {
+                cpu.clock(&mut bus);
+            }
+            for _ in 0..100 {
+                cpu.clock(&mut bus);
+            }
+            assert_eq!(bus.mem[0x10], 3, "{:?}", mode);
+        }
+    }
+
+    /// `loop: jmp loop` at $8000 with `inc $10; rti` at $8003 as the NMI
+    /// handler and `inc $11; rti` at $8008 as the IRQ handler.
+    fn interrupt_test_cpu(mode: ExecutionMode, program: &str) -> (Cpu, RecordingBus) {
+        let handlers = assembler::assemble("
+                    jmp $8000
+                    inc $10
+                    rti
+                    inc $11
+                    rti
+        ", 0x8000).unwrap();
+        let program = assembler::assemble(program, 0x8100).unwrap();
+
+        let mut bus = RecordingBus::new();
+        bus.mem[0x8000..0x8000 + handlers.len()].copy_from_slice(&handlers);
+        bus.mem[0x8100..0x8100 + program.len()].copy_from_slice(&program);
+        bus.mem[0xFFFA..=0xFFFF].copy_from_slice(&[0x03, 0x80, 0x00, 0x81, 0x08, 0x80]);
+
+        let mut cpu = Cpu::new();
+        cpu.set_execution_mode(mode);
+        cpu.reset(&mut bus);
+        while !cpu.is_complete() {
+            cpu.clock(&mut bus);
+        }
+        bus.accesses.clear();
+
+        (cpu, bus)
+    }
+
+    #[test]
+    fn nmi_is_edge_triggered() {
+        for mode in [ExecutionMode::Instruction, ExecutionMode::Cycle] {
+            let (mut cpu, mut bus) = interrupt_test_cpu(mode, "jmp $8000");
+
+            cpu.set_nmi_line(true);
+            for _ in 0..200 {
+                cpu.clock(&mut bus);
+            }
+            assert_eq!(bus.mem[0x10], 1, "{:?}", mode);
+
+            cpu.set_nmi_line(false);
+            cpu.set_nmi_line(true);
+            for _ in 0..200 {
+                cpu.clock(&mut bus);
+            }
+            assert_eq!(bus.mem[0x10], 2, "{:?}", mode);
+
+            // Interrupt disable has no say over NMI
+            assert_ne!(cpu.status & Flags6502::InterruptDisable as u8, 0);
+        }
+    }
+
+    #[test]
+    fn cli_takes_effect_one_instruction_late() {
+        for mode in [ExecutionMode::Instruction, ExecutionMode::Cycle] {
+            let (mut cpu, mut bus) = interrupt_test_cpu(mode, "cli\ninx\ninx");
+            cpu.set_irq(IrqSource::External, true);
+
+            cpu.step_instruction(&mut bus);
+            let step = cpu.step_instruction(&mut bus);
+            assert_eq!(step.instruction.opcode, Opcode::Inx, "{:?}", mode);
+
+            let step = cpu.step_instruction(&mut bus);
+            assert_eq!(step.addr, 0x8008, "{:?}", mode);
+            assert_eq!(cpu.x_reg, 0x01);
+        }
+    }
+
+    #[test]
+    fn taken_branch_polls_before_its_last_cycle_only_on_a_page_cross() {
+        // NOP: an NMI raised before its second (last) cycle is taken next
+        let (mut cpu, mut bus) = interrupt_test_cpu(ExecutionMode::Cycle, "nop\ninx");
+        cpu.clock(&mut bus);
+        cpu.set_nmi_line(true);
+        cpu.clock(&mut bus);
+        assert_eq!(cpu.step_instruction(&mut bus).addr, 0x8003);
+
+        // BEQ taken without a page cross: the poll before cycle 3 is skipped,
+        // so the INX after it still runs first
+        let (mut cpu, mut bus) = interrupt_test_cpu(ExecutionMode::Cycle, "beq next\nnext: inx");
+        cpu.set_flag(Flags6502::Zero, true);
+        cpu.clock(&mut bus);
+        cpu.clock(&mut bus);
+        cpu.set_nmi_line(true);
+        cpu.clock(&mut bus);
+        assert!(cpu.is_complete());
+        assert_eq!(cpu.step_instruction(&mut bus).instruction.opcode, Opcode::Inx);
+        assert_eq!(cpu.step_instruction(&mut bus).addr, 0x8003);
+    }
+
+    #[test]
+    fn nmi_hijacks_brk_and_irq_sequences() {
+        // BRK: still pushes B set, but jumps through the NMI vector
+        let (mut cpu, mut bus) = interrupt_test_cpu(ExecutionMode::Cycle, "brk");
+        for _ in 0..4 {
+            cpu.clock(&mut bus);
+        }
+        cpu.set_nmi_line(true);
+        while !cpu.is_complete() {
+            cpu.clock(&mut bus);
+        }
+        assert_eq!(cpu.pc, 0x8003);
+        assert_eq!(bus.accesses[4], Write(0x01FB, 0x34));
+        assert_eq!(bus.accesses[5], Read(0xFFFA));
+
+        // The handler's first instruction runs before anything else is taken
+        cpu.set_nmi_line(false);
+        cpu.set_nmi_line(true);
+        assert_eq!(cpu.step_instruction(&mut bus).addr, 0x8003);
+
+        // IRQ: an NMI after P has been pushed is too late and waits its turn
+        let (mut cpu, mut bus) = interrupt_test_cpu(ExecutionMode::Cycle, "cli\nnop");
+        cpu.step_instruction(&mut bus);
+        cpu.set_irq(IrqSource::Dmc, true);
+        cpu.step_instruction(&mut bus);
+        bus.accesses.clear();
+        for _ in 0..3 {
+            cpu.clock(&mut bus);
+        }
+        cpu.set_nmi_line(true);
+        while !cpu.is_complete() {
+            cpu.clock(&mut bus);
+        }
+        assert_eq!(cpu.pc, 0x8003);
+        assert_eq!(bus.accesses[4], Write(0x01FB, 0x20));
+
+        let (mut cpu, mut bus) = interrupt_test_cpu(ExecutionMode::Cycle, "cli\nnop");
+        cpu.step_instruction(&mut bus);
+        cpu.set_irq(IrqSource::Dmc, true);
+        cpu.step_instruction(&mut bus);
+        for _ in 0..5 {
+            cpu.clock(&mut bus);
+        }
+        cpu.set_nmi_line(true);
+        while !cpu.is_complete() {
+            cpu.clock(&mut bus);
+        }
+        assert_eq!(cpu.pc, 0x8008);
+        assert_eq!(cpu.step_instruction(&mut bus).addr, 0x8008);
+        assert_eq!(cpu.step_instruction(&mut bus).addr, 0x8003);
+    }
+}
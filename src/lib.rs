@@ -0,0 +1,7 @@
+pub mod apu;
+pub mod bus;
+pub mod cartridge;
+pub mod cpu_6502;
+pub mod nes;
+pub mod ppu;
+pub mod wav;
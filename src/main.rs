@@ -1,6 +1,101 @@
-mod bus;
-mod cpu_6502;
+mod tests;
 
-fn main() {
-    
+use std::process::ExitCode;
+
+use my_rusty_nes::apu::SAMPLE_RATES;
+use my_rusty_nes::cartridge::Cartridge;
+use my_rusty_nes::nes::Nes;
+
+const USAGE: &str = "usage: my_rusty_nes <rom.nes>
+       my_rusty_nes audio <rom.nes> <frames> <out.wav> [--stems] [--sample-rate <hz>]";
+
+/// What `audio` was asked to do.
+struct AudioOptions {
+    rom: String,
+    frames: u64,
+    output: String,
+    stems: bool,
+    sample_rate: Option<u32>,
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    match args.first().map(String::as_str) {
+        Some("audio") => match parse_audio_options(&args[1..]) {
+            Some(options) => record_audio(options),
+            None => usage(),
+        },
+        Some(path) if args.len() == 1 => print_header(path),
+        _ => usage(),
+    }
+}
+
+fn usage() -> ExitCode {
+    eprintln!("{}", USAGE);
+    ExitCode::from(2)
+}
+
+fn print_header(path: &str) -> ExitCode {
+    match Cartridge::from_file(path) {
+        Ok(cartridge) => {
+            println!("{:#?}", cartridge.header());
+            ExitCode::SUCCESS
+        }
+        Err(error) => {
+            eprintln!("{}: {}", path, error);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn parse_audio_options(args: &[String]) -> Option<AudioOptions> {
+    let [rom, frames, output, flags @ ..] = args else {
+        return None;
+    };
+
+    let mut options = AudioOptions {
+        rom: rom.clone(),
+        frames: frames.parse().ok()?,
+        output: output.clone(),
+        stems: false,
+        sample_rate: None,
+    };
+
+    let mut flags = flags.iter();
+    while let Some(flag) = flags.next() {
+        match flag.as_str() {
+            "--stems" => options.stems = true,
+            "--sample-rate" => options.sample_rate = Some(flags.next()?.parse().ok().filter(|rate| SAMPLE_RATES.contains(rate))?),
+            _ => return None,
+        }
+    }
+
+    Some(options)
+}
+
+/// Runs the ROM headless for the given number of frames and saves what it
+/// played.
+fn record_audio(options: AudioOptions) -> ExitCode {
+    let cartridge = match Cartridge::from_file(&options.rom) {
+        Ok(cartridge) => cartridge,
+        Err(error) => {
+            eprintln!("{}: {}", options.rom, error);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut nes = Nes::new(cartridge);
+    if let Some(sample_rate) = options.sample_rate {
+        nes.set_sample_rate(sample_rate);
+    }
+
+    let recording = nes.record_audio(options.frames, options.stems);
+    match recording.save(&options.output) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("{}: {}", options.output, error);
+            ExitCode::FAILURE
+        }
+    }
 }
@@ -0,0 +1,142 @@
+mod recording;
+mod region;
+mod tests;
+
+use std::cell::{Ref, RefCell};
+use std::rc::Rc;
+
+use crate::apu::Apu;
+use crate::bus::{Bus, BusDevice, BusInterface, Buttons, Port};
+use crate::cartridge::Cartridge;
+use crate::cpu_6502::{Cpu, IrqSource};
+use crate::ppu::Ppu;
+pub use recording::AudioRecording;
+pub use region::Region;
+
+/// A whole console with a cartridge plugged in, and the scheduler that
+/// keeps its parts in step: every `clock` is one CPU cycle, after which the
+/// rest of the system catches up through `Bus::clock`.
+pub struct Nes {
+    cpu: Cpu,
+    bus: Bus,
+    ppu: Rc<RefCell<Ppu>>,
+    apu: Rc<RefCell<Apu>>,
+    cartridge: Rc<RefCell<Cartridge>>,
+    region: Region,
+}
+
+//public
+impl Nes {
+    /// Wires everything up and presses reset. The region comes from the
+    /// cartridge header; `set_region` overrides it.
+    pub fn new(cartridge: Cartridge) -> Self {
+        let region = Region::from(cartridge.header().timing);
+        let cartridge = Rc::new(RefCell::new(cartridge));
+
+        let ppu = Rc::new(RefCell::new(Ppu::new()));
+        ppu.borrow_mut().connect_cartridge(cartridge.clone());
+        ppu.borrow_mut().set_region(region);
+
+        let apu = Rc::new(RefCell::new(Apu::new()));
+        apu.borrow_mut().set_region(region);
+
+        let mut bus = Bus::new();
+        bus.connect_ppu(Box::new(ppu.clone()));
+        bus.connect_apu_io(Box::new(apu.clone()));
+        bus.connect_cartridge(Box::new(cartridge.clone()));
+
+        let mut nes = Nes { cpu: Cpu::new(), bus, ppu, apu, cartridge, region };
+        nes.reset();
+        nes
+    }
+
+    pub fn region(&self) -> Region {
+        self.region
+    }
+
+    pub fn set_region(&mut self, region: Region) {
+        self.region = region;
+        self.ppu.borrow_mut().set_region(region);
+        self.apu.borrow_mut().set_region(region);
+    }
+
+    /// The rate `take_samples` produces audio at. Panics if it is outside
+    /// `apu::SAMPLE_RATES`.
+    pub fn set_sample_rate(&mut self, sample_rate: u32) {
+        self.apu.borrow_mut().set_sample_rate(sample_rate);
+    }
+
+    /// What the player on `port` is holding for the frames to come.
+    pub fn set_buttons(&mut self, port: Port, buttons: Buttons) {
+        self.bus.set_buttons(port, buttons);
+    }
+
+    pub fn reset(&mut self) {
+        self.ppu.borrow_mut().reset();
+        self.apu.borrow_mut().reset();
+        self.bus.cancel_dma();
+        self.cpu.reset(&mut self.bus);
+    }
+
+    /// Runs one CPU cycle.
+    pub fn clock(&mut self) {
+        self.cpu.set_nmi_line(self.bus.nmi());
+        self.cpu.set_irq(IrqSource::FrameCounter, self.apu.borrow().frame_irq());
+        self.cpu.set_irq(IrqSource::Dmc, self.apu.borrow().dmc_irq());
+        self.cpu.set_irq(IrqSource::Mapper, self.cartridge.borrow().irq());
+        self.cpu.clock(&mut self.bus);
+        self.bus.clock();
+    }
+
+    /// Runs until the PPU enters vblank, which is when `ppu().frame()` holds
+    /// a finished picture.
+    pub fn run_frame(&mut self) {
+        let frame = self.ppu.borrow().frame_count();
+
+        while self.ppu.borrow().frame_count() == frame && !self.cpu.is_halted() {
+            self.clock();
+        }
+    }
+
+    /// The audio produced since the last call; see `Apu::take_samples`.
+    pub fn take_samples(&mut self) -> Vec<f32> {
+        self.apu.borrow_mut().take_samples()
+    }
+
+    pub fn take_samples_i16(&mut self) -> Vec<i16> {
+        self.apu.borrow_mut().take_samples_i16()
+    }
+
+    pub fn cpu(&self) -> &Cpu {
+        &self.cpu
+    }
+
+    pub fn cpu_mut(&mut self) -> &mut Cpu {
+        &mut self.cpu
+    }
+
+    pub fn bus(&self) -> &Bus {
+        &self.bus
+    }
+
+    pub fn bus_mut(&mut self) -> &mut Bus {
+        &mut self.bus
+    }
+
+    pub fn ppu(&self) -> Ref<'_, Ppu> {
+        self.ppu.borrow()
+    }
+
+    pub fn apu(&self) -> Ref<'_, Apu> {
+        self.apu.borrow()
+    }
+
+    pub fn cartridge(&self) -> Ref<'_, Cartridge> {
+        self.cartridge.borrow()
+    }
+
+    /// What the CPU reads at `addr`, without side effects.
+    pub fn peek(&self, addr: u16) -> u8 {
+        self.bus.peek(addr)
+    }
+}
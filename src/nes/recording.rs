@@ -0,0 +1,68 @@
+use std::io;
+use std::path::Path;
+
+use super::*;
+use crate::apu::{to_i16, Channel};
+use crate::wav::save_wav;
+
+/// Audio captured by `Nes::record_audio`, as 16 bit PCM.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AudioRecording {
+    pub sample_rate: u32,
+    pub mix: Vec<i16>,
+    /// Each channel on its own, if they were asked for.
+    pub stems: Vec<(Channel, Vec<i16>)>,
+}
+
+impl AudioRecording {
+    /// Saves the mix as a WAV file at `path`, and each stem next to it
+    /// with the channel's name before the extension: song.wav,
+    /// song.pulse1.wav, song.triangle.wav...
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        save_wav(path, self.sample_rate, &self.mix)?;
+
+        for (channel, samples) in &self.stems {
+            save_wav(path.with_extension(format!("{}.wav", channel.name())), self.sample_rate, samples)?;
+        }
+        Ok(())
+    }
+}
+
+impl Nes {
+    /// Runs `frames` frames with nothing but the audio being kept, e.g. to
+    /// check a music engine against a known good recording. Audio nobody
+    /// took before the call is left out.
+    pub fn record_audio(&mut self, frames: u64, stems: bool) -> AudioRecording {
+        let mut mix = vec![];
+        let mut stem_samples = Channel::ALL.map(|_| vec![]);
+
+        {
+            let mut apu = self.apu.borrow_mut();
+            apu.take_samples();
+            apu.set_stems_enabled(stems);
+        }
+
+        for _ in 0..frames {
+            self.run_frame();
+
+            let mut apu = self.apu.borrow_mut();
+            mix.extend(apu.take_samples());
+            for (samples, channel) in stem_samples.iter_mut().zip(Channel::ALL) {
+                samples.extend(apu.take_stem_samples(channel));
+            }
+        }
+
+        let mut apu = self.apu.borrow_mut();
+        apu.set_stems_enabled(false);
+
+        AudioRecording {
+            sample_rate: apu.sample_rate(),
+            mix: to_i16(&mix),
+            stems: match stems {
+                true => Channel::ALL.into_iter().zip(stem_samples.iter().map(|samples| to_i16(samples))).collect(),
+                false => vec![],
+            },
+        }
+    }
+}
@@ -0,0 +1,81 @@
+use crate::cartridge::Timing;
+
+/// Which console is being emulated. The CPU and PPU both run off the same
+/// master clock through different dividers, so the regions differ in the
+/// ratio between the two as well as in how many lines make up a frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Region {
+    /// 2A03 and 2C02: 262 lines, 3 dots per CPU cycle.
+    #[default]
+    Ntsc,
+    /// 2A07 and 2C07: 312 lines, 3.2 dots per CPU cycle and a 70 line
+    /// vblank.
+    Pal,
+    /// The famiclones: PAL's 312 lines and master clock, but a CPU divider
+    /// that keeps NTSC's 3 dots per cycle, and vblank starting 50 lines late
+    /// so its length matches NTSC.
+    Dendy,
+}
+
+impl Region {
+    /// The master clock in Hz.
+    pub fn master_clock_rate(self) -> f64 {
+        match self {
+            Region::Ntsc => 236.25e6 / 11.0,
+            Region::Pal | Region::Dendy => 26_601_712.0,
+        }
+    }
+
+    /// Master clock cycles per CPU cycle.
+    pub fn cpu_divider(self) -> u8 {
+        match self {
+            Region::Ntsc => 12,
+            Region::Pal => 16,
+            Region::Dendy => 15,
+        }
+    }
+
+    /// Master clock cycles per PPU dot.
+    pub fn ppu_divider(self) -> u8 {
+        match self {
+            Region::Ntsc => 4,
+            Region::Pal | Region::Dendy => 5,
+        }
+    }
+
+    /// The CPU (and APU) clock in Hz.
+    pub fn cpu_clock_rate(self) -> f64 {
+        self.master_clock_rate() / self.cpu_divider() as f64
+    }
+
+    pub fn scanlines_per_frame(self) -> u16 {
+        match self {
+            Region::Ntsc => 262,
+            Region::Pal | Region::Dendy => 312,
+        }
+    }
+
+    /// The line on whose second dot the vblank flag goes up.
+    pub fn vblank_scanline(self) -> u16 {
+        match self {
+            Region::Ntsc | Region::Pal => 241,
+            Region::Dendy => 291,
+        }
+    }
+
+    /// Only the NTSC PPU drops a dot from every other rendered frame.
+    pub fn skips_odd_frame_dot(self) -> bool {
+        self == Region::Ntsc
+    }
+}
+
+/// Multi-region games get the NTSC console.
+impl From<Timing> for Region {
+    fn from(timing: Timing) -> Self {
+        match timing {
+            Timing::Ntsc | Timing::MultiRegion => Region::Ntsc,
+            Timing::Pal => Region::Pal,
+            Timing::Dendy => Region::Dendy,
+        }
+    }
+}
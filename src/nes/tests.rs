@@ -0,0 +1,260 @@
+#[cfg(test)]
+mod nes_tests {
+    use crate::apu::Channel;
+    use crate::bus::{Buttons, Port};
+    use crate::cartridge::*;
+    use crate::cpu_6502::assembler::assemble;
+    use crate::nes::*;
+
+    /// Turns on NMI and counts them at $10.
+    const NMI_COUNTER: &str = "
+        start:  lda #$80
+                sta $2000
+        loop:   jmp loop
+        nmi:    inc $10
+                rti
+                .word nmi, start, start
+    ";
+
+    /// A NES 2.0 NROM board with 16KB of PRG ROM and 8KB of CHR RAM,
+    /// running `source` from $C000. The last six bytes of the program are
+    /// the NMI, reset and IRQ vectors.
+    fn cartridge(timing: u8, source: &str) -> Cartridge {
+        let program = assemble(source, 0xC000).unwrap();
+        let (code, vectors) = program.split_at(program.len() - 6);
+
+        let mut rom = vec![b'N', b'E', b'S', 0x1A, 1, 0, 0x00, 0x08, 0, 0, 0, 0x07, timing, 0, 0, 0];
+        rom.extend_from_slice(code);
+        rom.resize(16 + 16 * 1024, 0xEA);
+        rom[16 + 0x3FFA..].copy_from_slice(vectors);
+
+        Cartridge::from_bytes(&rom).unwrap()
+    }
+
+    #[test]
+    fn region_comes_from_the_header() {
+        for (timing, region) in [(0, Region::Ntsc), (1, Region::Pal), (2, Region::Ntsc), (3, Region::Dendy)] {
+            let nes = Nes::new(cartridge(timing, NMI_COUNTER));
+
+            assert_eq!(nes.region(), region);
+            assert_eq!(nes.ppu().region(), region);
+        }
+
+        let mut nes = Nes::new(cartridge(0, NMI_COUNTER));
+        nes.set_region(Region::Dendy);
+        assert_eq!(nes.ppu().region(), Region::Dendy);
+    }
+
+    #[test]
+    fn frames_take_as_many_cpu_cycles_as_the_region_says() {
+        // Whole numbers of cycles: 89342 / 3, 106392 / 3.2 and 106392 / 3
+        // dots per frame.
+        for (region, frames, cycles) in [(Region::Ntsc, 3, 89342), (Region::Pal, 2, 66495), (Region::Dendy, 1, 35464)] {
+            let mut nes = Nes::new(cartridge(0, NMI_COUNTER));
+            nes.set_region(region);
+            nes.run_frame();
+
+            let target = nes.ppu().frame_count() + frames;
+            let mut clocks = 0;
+            while nes.ppu().frame_count() < target {
+                nes.clock();
+                clocks += 1;
+            }
+
+            assert_eq!(clocks, cycles, "{:?}", region);
+        }
+    }
+
+    #[test]
+    fn takes_one_nmi_per_frame() {
+        for region in [Region::Ntsc, Region::Pal, Region::Dendy] {
+            let mut nes = Nes::new(cartridge(0, NMI_COUNTER));
+            nes.set_region(region);
+
+            for _ in 0..11 {
+                nes.run_frame();
+            }
+
+            assert_eq!(nes.peek(0x0010), 10, "{:?}", region);
+        }
+    }
+
+    #[test]
+    fn frame_counter_irq_reaches_the_cpu() {
+        let source = "
+            start:  cli
+            loop:   jmp loop
+            irq:    inc $10
+                    lda $4015
+            nmi:    rti
+                    .word nmi, start, irq
+        ";
+        let mut nes = Nes::new(cartridge(0, source));
+
+        for _ in 0..3 * 29830 + 100 {
+            nes.clock();
+        }
+
+        assert_eq!(nes.peek(0x0010), 3);
+        assert!(!nes.cpu().irq_line());
+    }
+
+    #[test]
+    fn dmc_irq_reaches_the_cpu() {
+        let source = "
+            start:  lda #$40        ; no frame counter IRQ
+                    sta $4017
+                    lda #$8F        ; one byte at $C000, IRQ when done
+                    sta $4010
+                    lda #$00
+                    sta $4012
+                    sta $4013
+                    lda #$10
+                    sta $4015
+                    cli
+            loop:   jmp loop
+            irq:    inc $10
+                    lda #$00
+                    sta $4015
+            nmi:    rti
+                    .word nmi, start, irq
+        ";
+        let mut nes = Nes::new(cartridge(0, source));
+
+        for _ in 0..5000 {
+            nes.clock();
+        }
+
+        assert_eq!(nes.peek(0x0010), 1);
+        assert!(!nes.apu().dmc_irq());
+    }
+
+    #[test]
+    fn each_frame_brings_a_frames_worth_of_audio() {
+        for (region, per_frame) in [(Region::Ntsc, 734), (Region::Pal, 882)] {
+            let mut nes = Nes::new(cartridge(0, NMI_COUNTER));
+            nes.set_region(region);
+            nes.run_frame();
+            nes.take_samples();
+
+            for _ in 0..3 {
+                nes.run_frame();
+                let samples = nes.take_samples_i16();
+                assert!(samples.len().abs_diff(per_frame) <= 1, "{:?} {}", region, samples.len());
+            }
+        }
+    }
+
+    /// Pulse 1 and the triangle playing forever.
+    const TWO_CHANNELS: &str = "
+        start:  lda #$05
+                sta $4015
+                lda #$BF
+                sta $4000
+                lda #$6F
+                sta $4002
+                lda #$08
+                sta $4003
+                lda #$FF
+                sta $4008
+                lda #$80
+                sta $400A
+                lda #$08
+                sta $400B
+        loop:   jmp loop
+        nmi:    rti
+                .word nmi, start, start
+    ";
+
+    #[test]
+    fn records_audio_with_stems() {
+        let mut nes = Nes::new(cartridge(0, TWO_CHANNELS));
+        let recording = nes.record_audio(10, true);
+
+        assert_eq!(recording.sample_rate, 44_100);
+        // The first frame after power on is cut short by the first vblank.
+        assert!((9 * 734..=10 * 734 + 2).contains(&recording.mix.len()), "{}", recording.mix.len());
+        assert_eq!(recording.stems.iter().map(|(channel, _)| *channel).collect::<Vec<_>>(), Channel::ALL);
+
+        let loudness = |samples: &[i16]| samples[samples.len() / 2..].iter().map(|sample| sample.unsigned_abs()).max().unwrap();
+        assert!(loudness(&recording.mix) > 1000);
+        for (channel, samples) in &recording.stems {
+            assert_eq!(samples.len(), recording.mix.len());
+            let playing = matches!(channel, Channel::Pulse1 | Channel::Triangle);
+            assert_eq!(loudness(samples) > 1000, playing, "{:?}", channel);
+        }
+
+        // Stems go back off afterwards.
+        assert!(nes.record_audio(1, false).stems.is_empty());
+        assert!(nes.apu.borrow_mut().take_stem_samples(Channel::Pulse1).is_empty());
+    }
+
+    #[test]
+    fn saves_the_mix_and_stems_side_by_side() {
+        let mut nes = Nes::new(cartridge(0, TWO_CHANNELS));
+        nes.set_sample_rate(48_000);
+        let recording = nes.record_audio(2, true);
+
+        let directory = std::env::temp_dir().join(format!("my_rusty_nes_{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        recording.save(directory.join("song.wav")).unwrap();
+
+        let size = |name: &str| std::fs::metadata(directory.join(name)).unwrap().len();
+        let expected = 44 + 2 * recording.mix.len() as u64;
+        assert_eq!(size("song.wav"), expected);
+        for channel in Channel::ALL {
+            assert_eq!(size(&format!("song.{}.wav", channel.name())), expected);
+        }
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn reads_the_buttons_set_for_each_frame() {
+        // Reads controller 1 into $10 every NMI, A in bit 7.
+        let source = "
+            start:  lda #$80
+                    sta $2000
+            loop:   jmp loop
+            nmi:    lda #$01
+                    sta $4016
+                    lda #$00
+                    sta $4016
+                    ldx #$08
+            read:   lda $4016
+                    lsr a
+                    rol $10
+                    dex
+                    bne read
+                    rti
+                    .word nmi, start, start
+        ";
+        let mut nes = Nes::new(cartridge(0, source));
+
+        nes.set_buttons(Port::One, Buttons::A | Buttons::RIGHT);
+        nes.set_buttons(Port::Two, Buttons::START);
+        nes.run_frame();
+        nes.run_frame();
+        assert_eq!(nes.peek(0x0010), 0x81);
+
+        nes.set_buttons(Port::One, Buttons::START);
+        nes.run_frame();
+        assert_eq!(nes.peek(0x0010), 0x10);
+    }
+
+    #[test]
+    fn reset_drops_a_sprite_dma_in_progress() {
+        let mut nes = Nes::new(cartridge(0, NMI_COUNTER));
+        for _ in 0..10 {
+            nes.clock();
+        }
+
+        nes.bus_mut().write(0x4014, 0x02);
+        for _ in 0..10 {
+            nes.clock();
+        }
+        nes.reset();
+
+        assert!(!nes.bus_mut().dma_cycle(true));
+    }
+}
@@ -0,0 +1,508 @@
+mod render;
+mod sprites;
+mod tests;
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::bus::BusDevice;
+use crate::cartridge::{Cartridge, Mirroring, NAMETABLE_SIZE};
+use crate::nes::Region;
+use sprites::SpriteSlot;
+
+pub const SCREEN_WIDTH: usize = 256;
+pub const SCREEN_HEIGHT: usize = 240;
+
+const DOTS_PER_SCANLINE: u16 = 341;
+
+const PPUCTRL: u16 = 0x2000;
+const PPUMASK: u16 = 0x2001;
+const PPUSTATUS: u16 = 0x2002;
+const OAMADDR: u16 = 0x2003;
+const OAMDATA: u16 = 0x2004;
+const PPUSCROLL: u16 = 0x2005;
+const PPUADDR: u16 = 0x2006;
+const PPUDATA: u16 = 0x2007;
+
+const CTRL_NAMETABLE: u8 = 0x03;
+const CTRL_INCREMENT_32: u8 = 0x04;
+const CTRL_SPRITE_TABLE: u8 = 0x08;
+const CTRL_BACKGROUND_TABLE: u8 = 0x10;
+const CTRL_SPRITE_8X16: u8 = 0x20;
+const CTRL_NMI_ENABLE: u8 = 0x80;
+
+const MASK_GRAYSCALE: u8 = 0x01;
+const MASK_BACKGROUND_LEFT: u8 = 0x02;
+const MASK_SPRITES_LEFT: u8 = 0x04;
+const MASK_BACKGROUND: u8 = 0x08;
+const MASK_SPRITES: u8 = 0x10;
+
+const STATUS_SPRITE_OVERFLOW: u8 = 0x20;
+const STATUS_SPRITE_0_HIT: u8 = 0x40;
+const STATUS_VBLANK: u8 = 0x80;
+
+const SPRITE_PALETTE: u8 = 0x03;
+/// Bits 2-4 of the attribute byte don't exist and read back as 0.
+const SPRITE_ATTRIBUTE_MASK: u8 = 0xE3;
+const SPRITE_BEHIND_BACKGROUND: u8 = 0x20;
+const SPRITE_FLIP_HORIZONTAL: u8 = 0x40;
+const SPRITE_FLIP_VERTICAL: u8 = 0x80;
+
+const PATTERN_END: u16 = 0x1FFF;
+const NAMETABLE_START: u16 = 0x2000;
+const NAMETABLE_END: u16 = 0x3EFF;
+const ATTRIBUTE_START: u16 = 0x23C0;
+const PALETTE_START: u16 = 0x3F00;
+const ADDR_MASK: u16 = 0x3FFF;
+
+// The fields of v and t, laid out as yyy NN YYYYY XXXXX.
+const COARSE_X: u16 = 0x001F;
+const COARSE_Y: u16 = 0x03E0;
+const NAMETABLE_X: u16 = 0x0400;
+const NAMETABLE_Y: u16 = 0x0800;
+const FINE_Y: u16 = 0x7000;
+
+/// The 2C02. It sits on the CPU bus at $2000-$2007 and has its own 14 bit
+/// address space behind it: pattern tables on the cartridge, 2KB of
+/// nametable RAM and the palette.
+///
+/// Every dot it renders lands in `frame` as a 6 bit index into the NES
+/// master palette; turning that into RGB is up to the front end.
+///
+/// The frame layout and the number of dots per CPU cycle follow the
+/// `Region`, NTSC unless told otherwise.
+pub struct Ppu {
+    region: Region,
+    /// Master clock cycles handed over by the CPU and not yet spent on
+    /// dots. PAL's 3.2 dots per CPU cycle leave some over.
+    master_clock: u8,
+    cartridge: Option<Rc<RefCell<Cartridge>>>,
+    ciram: [u8; 2 * NAMETABLE_SIZE],
+    palette: [u8; 32],
+    oam: [u8; 256],
+    secondary_oam: [u8; 32],
+
+    ctrl: u8,
+    mask: u8,
+    status: u8,
+    oam_addr: u8,
+    read_buffer: u8,
+    /// The last value written to any register; write-only registers read
+    /// back as this.
+    io_latch: u8,
+
+    // Loopy's scroll registers: the current and temporary VRAM address,
+    // fine X scroll and the shared $2005/$2006 write toggle.
+    v: u16,
+    t: u16,
+    x: u8,
+    w: bool,
+
+    scanline: u16,
+    dot: u16,
+    odd_frame: bool,
+    frame_count: u64,
+    frame: Vec<u8>,
+
+    next_tile: u8,
+    next_attribute: u8,
+    next_pattern_low: u8,
+    next_pattern_high: u8,
+    pattern_low: u16,
+    pattern_high: u16,
+    attribute_low: u16,
+    attribute_high: u16,
+
+    // Filled in by evaluation for the next line...
+    next_sprite_count: u8,
+    sprite_zero_next: bool,
+    overflow_at: Option<u16>,
+    // ...and what the current line is drawn from.
+    sprite_count: u8,
+    sprite_zero_on_line: bool,
+    sprites: [SpriteSlot; 8],
+}
+
+impl Default for Ppu {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+//public
+impl Ppu {
+    pub fn new() -> Self {
+        Ppu {
+            region: Region::Ntsc,
+            master_clock: 0,
+            cartridge: None,
+            ciram: [0x00; 2 * NAMETABLE_SIZE],
+            palette: [0x00; 32],
+            oam: [0x00; 256],
+            secondary_oam: [0xFF; 32],
+            ctrl: 0x00,
+            mask: 0x00,
+            status: 0x00,
+            oam_addr: 0x00,
+            read_buffer: 0x00,
+            io_latch: 0x00,
+            v: 0x0000,
+            t: 0x0000,
+            x: 0,
+            w: false,
+            scanline: 0,
+            dot: 0,
+            odd_frame: false,
+            frame_count: 0,
+            frame: vec![0x00; SCREEN_WIDTH * SCREEN_HEIGHT],
+            next_tile: 0x00,
+            next_attribute: 0x00,
+            next_pattern_low: 0x00,
+            next_pattern_high: 0x00,
+            pattern_low: 0x0000,
+            pattern_high: 0x0000,
+            attribute_low: 0x0000,
+            attribute_high: 0x0000,
+            next_sprite_count: 0,
+            sprite_zero_next: false,
+            overflow_at: None,
+            sprite_count: 0,
+            sprite_zero_on_line: false,
+            sprites: [SpriteSlot::default(); 8],
+        }
+    }
+
+    /// The PPU shares the cartridge with the CPU bus, so connect the same
+    /// one to both.
+    pub fn connect_cartridge(&mut self, cartridge: Rc<RefCell<Cartridge>>) {
+        self.cartridge = Some(cartridge);
+    }
+
+    /// Switches the frame layout and clock ratio to another console. The
+    /// position in the frame is kept unless the new frame is too short for
+    /// it.
+    pub fn set_region(&mut self, region: Region) {
+        self.region = region;
+        self.master_clock = 0;
+
+        if self.scanline >= region.scanlines_per_frame() {
+            self.scanline = 0;
+            self.dot = 0;
+        }
+    }
+
+    pub fn region(&self) -> Region {
+        self.region
+    }
+
+    /// What the reset button does: the PPU registers are cleared, but the
+    /// memories and the position in the frame are left alone.
+    pub fn reset(&mut self) {
+        self.ctrl = 0x00;
+        self.mask = 0x00;
+        self.read_buffer = 0x00;
+        self.t = 0x0000;
+        self.x = 0;
+        self.w = false;
+        self.odd_frame = false;
+    }
+
+    /// Advances one dot.
+    pub fn tick(&mut self) {
+        let pre_render = self.scanline == self.pre_render_scanline();
+        let visible = (self.scanline as usize) < SCREEN_HEIGHT;
+
+        if (visible || pre_render) && self.rendering_enabled() {
+            self.background_dot(pre_render);
+            self.sprite_dot(pre_render);
+        }
+
+        if visible && (1..=SCREEN_WIDTH as u16).contains(&self.dot) {
+            self.render_pixel();
+        }
+
+        if self.dot == 1 && self.scanline == self.region.vblank_scanline() {
+            self.status |= STATUS_VBLANK;
+            self.frame_count += 1;
+        }
+        if self.dot == 1 && pre_render {
+            self.status &= !(STATUS_VBLANK | STATUS_SPRITE_0_HIT | STATUS_SPRITE_OVERFLOW);
+        }
+
+        self.advance();
+    }
+
+    /// The last complete frame, or the one being drawn if called while
+    /// rendering: one master palette index per pixel, row by row.
+    pub fn frame(&self) -> &[u8] {
+        &self.frame
+    }
+
+    /// How many frames have been finished, counted at the start of vblank.
+    pub fn frame_count(&self) -> u64 {
+        self.frame_count
+    }
+
+    pub fn scanline(&self) -> u16 {
+        self.scanline
+    }
+
+    pub fn dot(&self) -> u16 {
+        self.dot
+    }
+}
+
+//private
+impl Ppu {
+    fn rendering_enabled(&self) -> bool {
+        self.mask & (MASK_BACKGROUND | MASK_SPRITES) != 0
+    }
+
+    fn rendering(&self) -> bool {
+        self.rendering_enabled() && (self.scanline < SCREEN_HEIGHT as u16 || self.scanline == self.pre_render_scanline())
+    }
+
+    /// The last line of the frame, whatever the region.
+    fn pre_render_scanline(&self) -> u16 {
+        self.region.scanlines_per_frame() - 1
+    }
+
+    fn advance(&mut self) {
+        self.dot += 1;
+
+        // On NTSC odd frames are one dot short when rendering, skipping the
+        // last dot of the pre-render line.
+        if self.region.skips_odd_frame_dot()
+            && self.scanline == self.pre_render_scanline()
+            && self.dot == DOTS_PER_SCANLINE - 1
+            && self.odd_frame
+            && self.rendering_enabled()
+        {
+            self.dot = DOTS_PER_SCANLINE;
+        }
+
+        if self.dot == DOTS_PER_SCANLINE {
+            self.dot = 0;
+            self.scanline += 1;
+
+            if self.scanline == self.region.scanlines_per_frame() {
+                self.scanline = 0;
+                self.odd_frame = !self.odd_frame;
+            }
+        }
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        match &self.cartridge {
+            Some(cartridge) => cartridge.borrow().mirroring(),
+            None => Mirroring::Horizontal,
+        }
+    }
+
+    /// The mirroring is asked for on every access, as mappers can change it
+    /// at any time.
+    fn read_nametable(&self, addr: u16) -> u8 {
+        let page = self.mirroring().nametable_page(addr);
+        let offset = addr as usize % NAMETABLE_SIZE;
+
+        match (page, &self.cartridge) {
+            (0 | 1, _) => self.ciram[page * NAMETABLE_SIZE + offset],
+            (_, Some(cartridge)) => cartridge.borrow().read_nametable_ram((page - 2) * NAMETABLE_SIZE + offset),
+            (_, None) => 0x00,
+        }
+    }
+
+    fn write_nametable(&mut self, addr: u16, data: u8) {
+        let page = self.mirroring().nametable_page(addr);
+        let offset = addr as usize % NAMETABLE_SIZE;
+
+        match (page, &self.cartridge) {
+            (0 | 1, _) => self.ciram[page * NAMETABLE_SIZE + offset] = data,
+            (_, Some(cartridge)) => cartridge.borrow_mut().write_nametable_ram((page - 2) * NAMETABLE_SIZE + offset, data),
+            (_, None) => {}
+        }
+    }
+
+    /// $3F10, $3F14, $3F18 and $3F1C are mirrors of the entries below them.
+    fn palette_index(addr: u16) -> usize {
+        let index = (addr & 0x1F) as usize;
+
+        match index & 0x13 == 0x10 {
+            true => index & 0x0F,
+            false => index,
+        }
+    }
+
+    fn read_palette(&self, addr: u16) -> u8 {
+        let color = self.palette[Self::palette_index(addr)] & 0x3F;
+
+        match self.mask & MASK_GRAYSCALE != 0 {
+            true => color & 0x30,
+            false => color,
+        }
+    }
+
+    fn read_memory(&mut self, addr: u16) -> u8 {
+        let addr = addr & ADDR_MASK;
+
+        match addr {
+            0x0000..=PATTERN_END => self.cartridge.as_ref().map_or(0x00, |cartridge| cartridge.borrow_mut().ppu_read(addr)),
+            NAMETABLE_START..=NAMETABLE_END => self.read_nametable(addr),
+            _ => self.read_palette(addr),
+        }
+    }
+
+    fn write_memory(&mut self, addr: u16, data: u8) {
+        let addr = addr & ADDR_MASK;
+
+        match addr {
+            0x0000..=PATTERN_END => {
+                if let Some(cartridge) = &self.cartridge {
+                    cartridge.borrow_mut().ppu_write(addr, data);
+                }
+            }
+            NAMETABLE_START..=NAMETABLE_END => self.write_nametable(addr, data),
+            _ => self.palette[Self::palette_index(addr)] = data,
+        }
+    }
+
+    /// After each $2007 access. While rendering, the increment goes through
+    /// the scroll counters instead and mangles the scroll.
+    fn increment_vram_addr(&mut self) {
+        if self.rendering() {
+            self.increment_x();
+            self.increment_y();
+            return;
+        }
+
+        let step = match self.ctrl & CTRL_INCREMENT_32 != 0 {
+            true => 32,
+            false => 1,
+        };
+        self.v = self.v.wrapping_add(step) & 0x7FFF;
+    }
+
+    /// Secondary OAM is being cleared to $FF for the first 64 dots of a
+    /// rendered line, and that is what shows through $2004.
+    fn read_oam(&self) -> u8 {
+        match self.rendering() && (1..=64).contains(&self.dot) {
+            true => 0xFF,
+            false => self.oam[self.oam_addr as usize],
+        }
+    }
+
+    fn read_status(&self) -> u8 {
+        (self.status & 0xE0) | (self.io_latch & 0x1F)
+    }
+
+    /// Palette reads come straight back, but still refill the buffer with
+    /// the nametable byte underneath.
+    fn read_data(&mut self) -> u8 {
+        let addr = self.v & ADDR_MASK;
+
+        let data = match addr >= PALETTE_START {
+            true => {
+                self.read_buffer = self.read_memory(addr - 0x1000);
+                self.read_palette(addr) | (self.io_latch & 0xC0)
+            }
+            false => {
+                let data = self.read_buffer;
+                self.read_buffer = self.read_memory(addr);
+                data
+            }
+        };
+
+        self.increment_vram_addr();
+        data
+    }
+}
+
+impl BusDevice for Ppu {
+    fn read(&mut self, addr: u16) -> u8 {
+        let data = match addr {
+            PPUSTATUS => {
+                let data = self.read_status();
+                self.status &= !STATUS_VBLANK;
+                self.w = false;
+                data
+            }
+            OAMDATA => self.read_oam(),
+            PPUDATA => self.read_data(),
+            _ => self.io_latch,
+        };
+
+        self.io_latch = data;
+        data
+    }
+
+    fn write(&mut self, addr: u16, data: u8) {
+        self.io_latch = data;
+
+        match addr {
+            PPUCTRL => {
+                self.ctrl = data;
+                self.t = (self.t & !(NAMETABLE_X | NAMETABLE_Y)) | ((data & CTRL_NAMETABLE) as u16) << 10;
+            }
+            PPUMASK => self.mask = data,
+            OAMADDR => self.oam_addr = data,
+            // While rendering, OAMADDR belongs to sprite evaluation and a
+            // write only bumps it to the next sprite.
+            OAMDATA if self.rendering() => self.oam_addr = self.oam_addr.wrapping_add(4),
+            OAMDATA => {
+                self.oam[self.oam_addr as usize] = match self.oam_addr & 0x03 {
+                    2 => data & SPRITE_ATTRIBUTE_MASK,
+                    _ => data,
+                };
+                self.oam_addr = self.oam_addr.wrapping_add(1);
+            }
+            PPUSCROLL if !self.w => {
+                self.t = (self.t & !COARSE_X) | (data >> 3) as u16;
+                self.x = data & 0x07;
+                self.w = true;
+            }
+            PPUSCROLL => {
+                self.t = (self.t & !(COARSE_Y | FINE_Y)) | ((data >> 3) as u16) << 5 | ((data & 0x07) as u16) << 12;
+                self.w = false;
+            }
+            PPUADDR if !self.w => {
+                self.t = (self.t & 0x00FF) | ((data & 0x3F) as u16) << 8;
+                self.w = true;
+            }
+            PPUADDR => {
+                self.t = (self.t & 0xFF00) | data as u16;
+                self.v = self.t;
+                self.w = false;
+            }
+            PPUDATA => {
+                self.write_memory(self.v, data);
+                self.increment_vram_addr();
+            }
+            _ => {}
+        }
+    }
+
+    fn peek(&self, addr: u16) -> u8 {
+        match addr {
+            PPUSTATUS => self.read_status(),
+            OAMDATA => self.read_oam(),
+            PPUDATA if self.v & ADDR_MASK >= PALETTE_START => self.read_palette(self.v) | (self.io_latch & 0xC0),
+            PPUDATA => self.read_buffer,
+            _ => self.io_latch,
+        }
+    }
+
+    /// Runs the dots that fit in one CPU cycle's worth of master clock:
+    /// always 3 on NTSC and Dendy, 3 or 4 on PAL.
+    fn clock(&mut self) {
+        self.master_clock += self.region.cpu_divider();
+
+        while self.master_clock >= self.region.ppu_divider() {
+            self.master_clock -= self.region.ppu_divider();
+            self.tick();
+        }
+    }
+
+    fn nmi(&self) -> bool {
+        self.ctrl & CTRL_NMI_ENABLE != 0 && self.status & STATUS_VBLANK != 0
+    }
+}
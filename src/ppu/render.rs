@@ -0,0 +1,172 @@
+use super::*;
+
+impl Ppu {
+    /// The background half of a rendering dot: one step of the 8 dot
+    /// nametable, attribute, pattern low, pattern high fetch pattern, plus
+    /// the scroll counter updates. Only runs on the visible and pre-render
+    /// lines, and only while rendering is enabled.
+    pub(super) fn background_dot(&mut self, pre_render: bool) {
+        let dot = self.dot;
+
+        if (2..=257).contains(&dot) || (321..=337).contains(&dot) {
+            self.shift_background();
+
+            match (dot - 1) % 8 {
+                0 => {
+                    self.load_background_shifters();
+                    self.next_tile = self.read_memory(NAMETABLE_START | (self.v & 0x0FFF));
+                }
+                2 => {
+                    let addr = ATTRIBUTE_START | (self.v & (NAMETABLE_X | NAMETABLE_Y)) | ((self.v >> 4) & 0x38) | ((self.v >> 2) & 0x07);
+                    // Each attribute byte covers 4x4 tiles; coarse X and Y
+                    // bit 1 pick its 2x2 quadrant.
+                    let shift = ((self.v >> 4) & 0x04) | (self.v & 0x02);
+                    self.next_attribute = (self.read_memory(addr) >> shift) & 0x03;
+                }
+                4 => self.next_pattern_low = self.read_memory(self.background_pattern_addr()),
+                6 => self.next_pattern_high = self.read_memory(self.background_pattern_addr() + 8),
+                7 => self.increment_x(),
+                _ => {}
+            }
+        }
+
+        match dot {
+            256 => self.increment_y(),
+            257 => self.copy_x(),
+            280..=304 if pre_render => self.copy_y(),
+            // Two unused nametable fetches end the line.
+            338 | 340 => {
+                self.next_tile = self.read_memory(NAMETABLE_START | (self.v & 0x0FFF));
+            }
+            _ => {}
+        }
+    }
+
+    /// Works out the colour of the pixel at the current dot.
+    pub(super) fn render_pixel(&mut self) {
+        let x = (self.dot - 1) as usize;
+        let y = self.scanline as usize;
+
+        let color = match self.rendering_enabled() {
+            true => {
+                let (pixel, palette) = self.compose_pixel(x);
+                match pixel {
+                    0 => self.read_palette(PALETTE_START),
+                    _ => self.read_palette(PALETTE_START | (palette as u16) << 2 | pixel as u16),
+                }
+            }
+            // With rendering off the backdrop colour is drawn, unless v
+            // points into the palette, in which case that entry is.
+            false if self.v & ADDR_MASK >= PALETTE_START => self.read_palette(self.v),
+            false => self.read_palette(PALETTE_START),
+        };
+
+        self.frame[y * SCREEN_WIDTH + x] = color;
+    }
+
+    /// Picks between the background and sprite pixel at column `x`, and
+    /// checks for sprite 0 hit along the way.
+    fn compose_pixel(&mut self, x: usize) -> (u8, u8) {
+        let background = self.background_pixel(x);
+        let Some(sprite) = self.sprite_pixel(x) else {
+            return background;
+        };
+
+        if background.0 == 0 {
+            return (sprite.pixel, sprite.palette);
+        }
+
+        // Both opaque. Column 255 never hits.
+        if sprite.sprite_zero && x != SCREEN_WIDTH - 1 {
+            self.status |= STATUS_SPRITE_0_HIT;
+        }
+
+        match sprite.behind_background {
+            true => background,
+            false => (sprite.pixel, sprite.palette),
+        }
+    }
+
+    pub(super) fn increment_x(&mut self) {
+        if self.v & COARSE_X == COARSE_X {
+            self.v &= !COARSE_X;
+            self.v ^= NAMETABLE_X;
+        } else {
+            self.v += 1;
+        }
+    }
+
+    /// Fine Y first, then coarse Y. Row 29 is the last row of tiles, so
+    /// that is where the vertical nametable flips; rows 30 and 31 (scrolled
+    /// into the attribute table) wrap without flipping.
+    pub(super) fn increment_y(&mut self) {
+        if self.v & FINE_Y != FINE_Y {
+            self.v += 0x1000;
+            return;
+        }
+
+        self.v &= !FINE_Y;
+        let coarse_y = match (self.v & COARSE_Y) >> 5 {
+            29 => {
+                self.v ^= NAMETABLE_Y;
+                0
+            }
+            31 => 0,
+            coarse_y => coarse_y + 1,
+        };
+        self.v = (self.v & !COARSE_Y) | coarse_y << 5;
+    }
+
+    fn copy_x(&mut self) {
+        let bits = COARSE_X | NAMETABLE_X;
+        self.v = (self.v & !bits) | (self.t & bits);
+    }
+
+    fn copy_y(&mut self) {
+        let bits = FINE_Y | NAMETABLE_Y | COARSE_Y;
+        self.v = (self.v & !bits) | (self.t & bits);
+    }
+
+    fn background_pattern_addr(&self) -> u16 {
+        let table = match self.ctrl & CTRL_BACKGROUND_TABLE != 0 {
+            true => 0x1000,
+            false => 0x0000,
+        };
+
+        table | (self.next_tile as u16) << 4 | (self.v & FINE_Y) >> 12
+    }
+
+    /// The next tile goes into the low byte of the shifters; the high byte
+    /// is the tile being drawn.
+    fn load_background_shifters(&mut self) {
+        let spread = |bit: bool| if bit { 0xFF } else { 0x00 };
+
+        self.pattern_low = (self.pattern_low & 0xFF00) | self.next_pattern_low as u16;
+        self.pattern_high = (self.pattern_high & 0xFF00) | self.next_pattern_high as u16;
+        self.attribute_low = (self.attribute_low & 0xFF00) | spread(self.next_attribute & 0x01 != 0);
+        self.attribute_high = (self.attribute_high & 0xFF00) | spread(self.next_attribute & 0x02 != 0);
+    }
+
+    fn shift_background(&mut self) {
+        self.pattern_low <<= 1;
+        self.pattern_high <<= 1;
+        self.attribute_low <<= 1;
+        self.attribute_high <<= 1;
+    }
+
+    /// The 2 bit pixel and its palette number at screen column `x`, with
+    /// fine X picking the bit out of the shifters.
+    fn background_pixel(&self, x: usize) -> (u8, u8) {
+        if self.mask & MASK_BACKGROUND == 0 || (x < 8 && self.mask & MASK_BACKGROUND_LEFT == 0) {
+            return (0, 0);
+        }
+
+        let bit = 0x8000 >> self.x;
+        let plane = |shifter: u16| (shifter & bit != 0) as u8;
+
+        let pixel = plane(self.pattern_high) << 1 | plane(self.pattern_low);
+        let palette = plane(self.attribute_high) << 1 | plane(self.attribute_low);
+
+        (pixel, palette)
+    }
+}
@@ -0,0 +1,172 @@
+use super::*;
+
+/// Dot on which sprite evaluation for the next line starts, once secondary
+/// OAM has been cleared.
+const EVALUATION_START: u16 = 65;
+const SPRITE_FETCH_START: u16 = 257;
+const SPRITE_FETCH_END: u16 = 320;
+
+/// One of the eight sprites loaded for the line being drawn.
+#[derive(Debug, Clone, Copy, Default)]
+pub(super) struct SpriteSlot {
+    x: u8,
+    attribute: u8,
+    /// Already flipped, so bit 7 is always the leftmost pixel.
+    pattern_low: u8,
+    pattern_high: u8,
+}
+
+/// A sprite pixel that survived clipping and transparency.
+pub(super) struct SpritePixel {
+    pub(super) pixel: u8,
+    pub(super) palette: u8,
+    pub(super) behind_background: bool,
+    pub(super) sprite_zero: bool,
+}
+
+impl Ppu {
+    /// The sprite half of a rendering dot: evaluation of the next line's
+    /// sprites into secondary OAM, then their pattern fetches on dots
+    /// 257-320. Fetches happen for all eight slots, empty or not, which is
+    /// what the MMC3 counts scanlines with.
+    pub(super) fn sprite_dot(&mut self, pre_render: bool) {
+        let dot = self.dot;
+
+        match dot {
+            EVALUATION_START if !pre_render => self.evaluate_sprites(),
+            SPRITE_FETCH_START => {
+                self.sprite_count = if pre_render { 0 } else { self.next_sprite_count };
+                self.sprite_zero_on_line = !pre_render && self.sprite_zero_next;
+            }
+            _ => {}
+        }
+
+        if self.overflow_at == Some(dot) {
+            self.status |= STATUS_SPRITE_OVERFLOW;
+            self.overflow_at = None;
+        }
+
+        if (SPRITE_FETCH_START..=SPRITE_FETCH_END).contains(&dot) {
+            self.oam_addr = 0;
+
+            let slot = ((dot - SPRITE_FETCH_START) / 8) as usize;
+            match (dot - SPRITE_FETCH_START) % 8 {
+                4 => {
+                    self.sprites[slot].x = self.secondary_oam[slot * 4 + 3];
+                    self.sprites[slot].attribute = self.secondary_oam[slot * 4 + 2];
+                    self.sprites[slot].pattern_low = self.fetch_sprite_pattern(slot, 0);
+                }
+                6 => self.sprites[slot].pattern_high = self.fetch_sprite_pattern(slot, 8),
+                _ => {}
+            }
+        }
+    }
+
+    /// The first opaque sprite pixel at screen column `x`; lower slots win.
+    pub(super) fn sprite_pixel(&self, x: usize) -> Option<SpritePixel> {
+        if self.mask & MASK_SPRITES == 0 || (x < 8 && self.mask & MASK_SPRITES_LEFT == 0) {
+            return None;
+        }
+
+        self.sprites[..self.sprite_count as usize].iter().enumerate().find_map(|(slot, sprite)| {
+            let offset = x.checked_sub(sprite.x as usize).filter(|&offset| offset < 8)?;
+            let bit = 0x80 >> offset;
+            let pixel = ((sprite.pattern_high & bit != 0) as u8) << 1 | (sprite.pattern_low & bit != 0) as u8;
+
+            (pixel != 0).then_some(SpritePixel {
+                pixel,
+                palette: 4 | (sprite.attribute & SPRITE_PALETTE),
+                behind_background: sprite.attribute & SPRITE_BEHIND_BACKGROUND != 0,
+                sprite_zero: slot == 0 && self.sprite_zero_on_line,
+            })
+        })
+    }
+
+    fn sprite_height(&self) -> i16 {
+        match self.ctrl & CTRL_SPRITE_8X16 != 0 {
+            true => 16,
+            false => 8,
+        }
+    }
+
+    /// Copies the first eight sprites in range of this line into secondary
+    /// OAM. After the eighth, the hardware keeps looking for a ninth to set
+    /// the overflow flag, but it wrongly steps through the bytes of each
+    /// entry as well as the entries, so it compares tiles, attributes and X
+    /// positions against the line as if they were Y coordinates.
+    ///
+    /// The work is done in one go; `overflow_at` holds the dot on which the
+    /// flag would really have been set, counting two dots per byte looked
+    /// at.
+    fn evaluate_sprites(&mut self) {
+        let height = self.sprite_height();
+        let scanline = self.scanline as i16;
+        let in_range = |y: u8| (0..height).contains(&(scanline - y as i16));
+
+        self.secondary_oam = [0xFF; 32];
+        self.sprite_zero_next = false;
+        self.overflow_at = None;
+
+        let mut found = 0;
+        let mut n = 0;
+        let mut dot = EVALUATION_START;
+
+        while n < 64 && found < 8 {
+            let entry = &self.oam[n * 4..n * 4 + 4];
+            self.secondary_oam[found * 4] = entry[0];
+
+            if in_range(entry[0]) {
+                self.secondary_oam[found * 4..found * 4 + 4].copy_from_slice(entry);
+                self.sprite_zero_next |= n == 0;
+                found += 1;
+                dot += 8;
+            } else {
+                dot += 2;
+            }
+            n += 1;
+        }
+
+        let mut m = 0;
+        while n < 64 {
+            if in_range(self.oam[n * 4 + m]) {
+                self.overflow_at = Some(dot + 1);
+                break;
+            }
+
+            n += 1;
+            m = (m + 1) & 0x03;
+            dot += 2;
+        }
+
+        self.next_sprite_count = found as u8;
+    }
+
+    /// One bitplane of the slot's row for the next line. Empty slots fetch
+    /// tile $FF like the hardware does, but come out transparent.
+    fn fetch_sprite_pattern(&mut self, slot: usize, plane: u16) -> u8 {
+        let entry = &self.secondary_oam[slot * 4..slot * 4 + 4];
+        let (y, tile, attribute) = (entry[0], entry[1], entry[2]);
+
+        let height = self.sprite_height();
+        let mut row = (self.scanline as i16 - y as i16).clamp(0, height - 1) as u16;
+        if attribute & SPRITE_FLIP_VERTICAL != 0 {
+            row = height as u16 - 1 - row;
+        }
+
+        let (table, tile) = match height {
+            16 => ((tile as u16 & 0x01) << 12, (tile & 0xFE) as u16 + row / 8),
+            _ => match self.ctrl & CTRL_SPRITE_TABLE != 0 {
+                true => (0x1000, tile as u16),
+                false => (0x0000, tile as u16),
+            },
+        };
+
+        let data = self.read_memory(table | tile << 4 | plane | (row & 0x07));
+
+        match (slot < self.sprite_count as usize, attribute & SPRITE_FLIP_HORIZONTAL != 0) {
+            (false, _) => 0x00,
+            (true, true) => data.reverse_bits(),
+            (true, false) => data,
+        }
+    }
+}
@@ -0,0 +1,661 @@
+#[cfg(test)]
+mod ppu_tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use crate::bus::*;
+    use crate::cartridge::*;
+    use crate::nes::Region;
+    use crate::ppu::*;
+
+    const DOTS_PER_FRAME: u32 = 341 * 262;
+
+    /// 32KB of PRG ROM and 8KB of CHR RAM, so tests can draw their own
+    /// tiles.
+    fn cartridge_with(flags_6: u8) -> Rc<RefCell<Cartridge>> {
+        let mut rom = vec![b'N', b'E', b'S', 0x1A, 2, 0, flags_6, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+        rom.resize(rom.len() + 32 * 1024, 0xEA);
+
+        Rc::new(RefCell::new(Cartridge::from_bytes(&rom).unwrap()))
+    }
+
+    /// NROM with the given mirroring.
+    fn cartridge(mirroring: Mirroring) -> Rc<RefCell<Cartridge>> {
+        cartridge_with(match mirroring {
+            Mirroring::Vertical => 0x01,
+            Mirroring::FourScreen => 0x08,
+            _ => 0x00,
+        })
+    }
+
+    fn ppu(mirroring: Mirroring) -> Ppu {
+        let mut ppu = Ppu::new();
+        ppu.connect_cartridge(cartridge(mirroring));
+        ppu
+    }
+
+    fn set_vram_addr(ppu: &mut Ppu, addr: u16) {
+        ppu.write(0x2006, (addr >> 8) as u8);
+        ppu.write(0x2006, addr as u8);
+    }
+
+    fn write_vram(ppu: &mut Ppu, addr: u16, data: &[u8]) {
+        set_vram_addr(ppu, addr);
+        for &byte in data {
+            ppu.write(0x2007, byte);
+        }
+    }
+
+    fn run_to(ppu: &mut Ppu, scanline: u16, dot: u16) {
+        while ppu.scanline() != scanline || ppu.dot() != dot {
+            ppu.tick();
+        }
+    }
+
+    fn run_frames(ppu: &mut Ppu, frames: u64) {
+        let target = ppu.frame_count() + frames;
+        while ppu.frame_count() < target {
+            ppu.tick();
+        }
+    }
+
+    #[test]
+    fn scroll_register_writes_follow_loopy() {
+        let mut ppu = ppu(Mirroring::Horizontal);
+
+        ppu.write(0x2000, 0x03);
+        assert_eq!(ppu.t, 0x0C00);
+        ppu.write(0x2000, 0x00);
+        ppu.read(0x2002);
+        assert!(!ppu.w);
+
+        ppu.write(0x2005, 0x7D);
+        assert_eq!((ppu.t, ppu.x, ppu.w), (0x000F, 0x05, true));
+        ppu.write(0x2005, 0x5E);
+        assert_eq!((ppu.t, ppu.x, ppu.w), (0x616F, 0x05, false));
+
+        ppu.write(0x2006, 0x3D);
+        assert_eq!((ppu.t, ppu.w), (0x3D6F, true));
+        ppu.write(0x2006, 0xF0);
+        assert_eq!((ppu.t, ppu.v, ppu.w), (0x3DF0, 0x3DF0, false));
+    }
+
+    #[test]
+    fn ppudata_reads_go_through_the_buffer() {
+        let mut ppu = ppu(Mirroring::Vertical);
+        write_vram(&mut ppu, 0x2400, &[0x11, 0x22]);
+
+        // $2C00 mirrors $2400 with vertical mirroring
+        set_vram_addr(&mut ppu, 0x2C00);
+        ppu.read(0x2007);
+        assert_eq!(ppu.read(0x2007), 0x11);
+        assert_eq!(ppu.peek(0x2007), 0x22);
+        assert_eq!(ppu.read(0x2007), 0x22);
+
+        // Going down a column
+        ppu.write(0x2000, 0x04);
+        write_vram(&mut ppu, 0x2000, &[0x33, 0x44]);
+        assert_eq!(ppu.ciram[0x0020], 0x44);
+
+        // Pattern tables land on the cartridge
+        ppu.write(0x2000, 0x00);
+        write_vram(&mut ppu, 0x0010, &[0x55]);
+        assert_eq!(ppu.cartridge.as_ref().unwrap().borrow().chr()[0x0010], 0x55);
+    }
+
+    #[test]
+    fn horizontal_mirroring_pairs_the_top_tables() {
+        let mut ppu = ppu(Mirroring::Horizontal);
+        write_vram(&mut ppu, 0x2405, &[0x66]);
+        write_vram(&mut ppu, 0x2805, &[0x77]);
+
+        set_vram_addr(&mut ppu, 0x2005);
+        ppu.read(0x2007);
+        assert_eq!(ppu.read(0x2007), 0x66);
+
+        set_vram_addr(&mut ppu, 0x2C05);
+        ppu.read(0x2007);
+        assert_eq!(ppu.read(0x2007), 0x77);
+    }
+
+    /// Writes a different byte to each of the four nametables, then reads
+    /// them back.
+    fn nametables_seen(ppu: &mut Ppu) -> Vec<u8> {
+        for (i, addr) in [0x2000, 0x2400, 0x2800, 0x2C00].into_iter().enumerate() {
+            write_vram(ppu, addr, &[i as u8]);
+        }
+
+        [0x2000, 0x2400, 0x2800, 0x2C00]
+            .into_iter()
+            .map(|addr| {
+                set_vram_addr(ppu, addr);
+                ppu.read(0x2007);
+                ppu.read(0x2007)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn four_screen_boards_keep_all_four_nametables() {
+        let mut ppu = ppu(Mirroring::FourScreen);
+        assert_eq!(nametables_seen(&mut ppu), vec![0, 1, 2, 3]);
+        assert_eq!(ppu.cartridge.as_ref().unwrap().borrow().read_nametable_ram(0x0400), 3);
+    }
+
+    #[test]
+    fn mappers_switch_mirroring_at_run_time() {
+        // AxROM: bit 4 of the bank register picks the single screen
+        let cartridge = cartridge_with(0x70);
+        let mut ppu = Ppu::new();
+        ppu.connect_cartridge(cartridge.clone());
+
+        assert_eq!(nametables_seen(&mut ppu), vec![3, 3, 3, 3]);
+        cartridge.borrow_mut().write(0x8000, 0x10);
+        assert_eq!(nametables_seen(&mut ppu), vec![3, 3, 3, 3]);
+        cartridge.borrow_mut().write(0x8000, 0x00);
+        write_vram(&mut ppu, 0x2000, &[0x40]);
+        cartridge.borrow_mut().write(0x8000, 0x10);
+        assert_eq!(ppu.ciram[0x0400], 3);
+        assert_eq!(ppu.ciram[0x0000], 0x40);
+
+        // MMC1: the low bits of the control register, loaded serially
+        let cartridge = cartridge_with(0x10);
+        let mut ppu = Ppu::new();
+        ppu.connect_cartridge(cartridge.clone());
+
+        let load_control = |value: u8| {
+            for bit in 0..5 {
+                let mut cartridge = cartridge.borrow_mut();
+                cartridge.clock();
+                cartridge.write(0x8000, value >> bit & 1);
+                cartridge.clock();
+            }
+        };
+
+        load_control(0x02);
+        assert_eq!(nametables_seen(&mut ppu), vec![2, 3, 2, 3]);
+        load_control(0x03);
+        assert_eq!(nametables_seen(&mut ppu), vec![1, 1, 3, 3]);
+        load_control(0x00);
+        assert_eq!(nametables_seen(&mut ppu), vec![3, 3, 3, 3]);
+    }
+
+    #[test]
+    fn palette_reads_skip_the_buffer() {
+        let mut ppu = ppu(Mirroring::Horizontal);
+        write_vram(&mut ppu, 0x2F00, &[0x99]);
+        write_vram(&mut ppu, 0x3F10, &[0x2A]);
+
+        set_vram_addr(&mut ppu, 0x3F00);
+        assert_eq!(ppu.read(0x2007), 0x2A);
+        // The nametable byte underneath was buffered
+        assert_eq!(ppu.read_buffer, 0x99);
+
+        ppu.write(0x2001, 0x01);
+        set_vram_addr(&mut ppu, 0x3F00);
+        assert_eq!(ppu.read(0x2007) & 0x3F, 0x20);
+    }
+
+    #[test]
+    fn vblank_raises_nmi_until_status_is_read() {
+        let mut ppu = ppu(Mirroring::Horizontal);
+        run_to(&mut ppu, 241, 1);
+        assert_eq!(ppu.peek(0x2002) & 0x80, 0x00);
+
+        ppu.tick();
+        assert_eq!(ppu.peek(0x2002) & 0x80, 0x80);
+        assert_eq!(ppu.frame_count(), 1);
+        assert!(!ppu.nmi());
+
+        // Enabling NMI in the middle of vblank asserts it straight away
+        ppu.write(0x2000, 0x80);
+        assert!(ppu.nmi());
+
+        assert_eq!(ppu.read(0x2002) & 0x80, 0x80);
+        assert!(!ppu.nmi());
+        assert_eq!(ppu.read(0x2002) & 0x80, 0x00);
+
+        ppu.tick();
+        run_to(&mut ppu, 241, 2);
+        assert!(ppu.nmi());
+        run_to(&mut ppu, 261, 2);
+        assert!(!ppu.nmi());
+    }
+
+    #[test]
+    fn odd_frames_skip_a_dot_while_rendering() {
+        let mut ppu = ppu(Mirroring::Horizontal);
+        run_frames(&mut ppu, 1);
+
+        let mut frame_lengths = vec![];
+        for mask in [0x00, 0x00, 0x08, 0x08, 0x08] {
+            ppu.write(0x2001, mask);
+
+            let mut dots = 0;
+            let target = ppu.frame_count() + 1;
+            while ppu.frame_count() < target {
+                ppu.tick();
+                dots += 1;
+            }
+            frame_lengths.push(dots);
+        }
+
+        assert_eq!(frame_lengths, vec![DOTS_PER_FRAME, DOTS_PER_FRAME, DOTS_PER_FRAME, DOTS_PER_FRAME - 1, DOTS_PER_FRAME]);
+    }
+
+    #[test]
+    fn frame_layout_follows_the_region() {
+        for (region, frame_length, vblank_length) in [
+            (Region::Ntsc, DOTS_PER_FRAME, 341 * 20),
+            (Region::Pal, 341 * 312, 341 * 70),
+            (Region::Dendy, 341 * 312, 341 * 20),
+        ] {
+            let mut ppu = ppu(Mirroring::Horizontal);
+            ppu.set_region(region);
+            ppu.write(0x2001, 0x08);
+            run_frames(&mut ppu, 1);
+            assert_eq!(ppu.scanline(), region.vblank_scanline(), "{:?}", region);
+
+            // Two frames, so NTSC's short odd frame averages out.
+            let (mut dots, mut vblank) = (0, 0);
+            let target = ppu.frame_count() + 2;
+            while ppu.frame_count() < target {
+                vblank += (ppu.peek(0x2002) & 0x80 != 0) as u32;
+                ppu.tick();
+                dots += 1;
+            }
+
+            let skipped = region.skips_odd_frame_dot() as u32;
+            assert_eq!((dots, vblank), (2 * frame_length - skipped, 2 * vblank_length), "{:?}", region);
+        }
+    }
+
+    /// Tile 1 is 3 3 3 3 2 2 2 2 on every row, placed at the top left of
+    /// the first nametable with palette 1.
+    fn draw_test_tile(ppu: &mut Ppu) {
+        write_vram(ppu, 0x0010, &[0xF0; 8]);
+        write_vram(ppu, 0x0018, &[0xFF; 8]);
+        write_vram(ppu, 0x2000, &[0x01]);
+        write_vram(ppu, 0x23C0, &[0x01]);
+        write_vram(ppu, 0x3F00, &[0x0F, 0x21, 0x22, 0x23, 0x0F, 0x11, 0x12, 0x13]);
+    }
+
+    #[test]
+    fn renders_the_background() {
+        let mut ppu = ppu(Mirroring::Horizontal);
+        draw_test_tile(&mut ppu);
+
+        ppu.write(0x2000, 0x00);
+        ppu.write(0x2005, 0x00);
+        ppu.write(0x2005, 0x00);
+        ppu.write(0x2001, 0x0A);
+        run_frames(&mut ppu, 2);
+
+        let row = |y: usize| ppu.frame()[y * SCREEN_WIDTH..y * SCREEN_WIDTH + 10].to_vec();
+        let tile_row = vec![0x13, 0x13, 0x13, 0x13, 0x12, 0x12, 0x12, 0x12, 0x0F, 0x0F];
+        assert_eq!(row(0), tile_row);
+        assert_eq!(row(7), tile_row);
+        assert_eq!(row(8), vec![0x0F; 10]);
+    }
+
+    #[test]
+    fn fine_scroll_and_left_column_clipping() {
+        let mut ppu = ppu(Mirroring::Horizontal);
+        draw_test_tile(&mut ppu);
+
+        // Two pixels right and one line down
+        ppu.write(0x2000, 0x00);
+        ppu.write(0x2005, 0x02);
+        ppu.write(0x2005, 0x01);
+        ppu.write(0x2001, 0x0A);
+        run_frames(&mut ppu, 2);
+
+        let row = |ppu: &Ppu, y: usize| ppu.frame()[y * SCREEN_WIDTH..y * SCREEN_WIDTH + 8].to_vec();
+        assert_eq!(row(&ppu, 0), vec![0x13, 0x13, 0x12, 0x12, 0x12, 0x12, 0x0F, 0x0F]);
+        assert_eq!(row(&ppu, 6), row(&ppu, 0));
+        assert_eq!(row(&ppu, 7), vec![0x0F; 8]);
+
+        ppu.write(0x2001, 0x08);
+        run_frames(&mut ppu, 1);
+        assert_eq!(row(&ppu, 0), vec![0x0F; 8]);
+    }
+
+    #[test]
+    fn rendering_off_shows_the_backdrop_or_the_palette_entry_at_v() {
+        let mut ppu = ppu(Mirroring::Horizontal);
+        draw_test_tile(&mut ppu);
+        set_vram_addr(&mut ppu, 0x2000);
+        run_frames(&mut ppu, 1);
+        assert!(ppu.frame().iter().all(|&color| color == 0x0F));
+
+        set_vram_addr(&mut ppu, 0x3F06);
+        run_frames(&mut ppu, 1);
+        assert!(ppu.frame().iter().all(|&color| color == 0x12));
+    }
+
+    #[test]
+    fn runs_three_dots_per_cpu_cycle_on_the_bus() {
+        let ppu = Rc::new(RefCell::new(Ppu::new()));
+        let mut bus = Bus::new();
+        bus.connect_ppu(Box::new(ppu.clone()));
+
+        bus.clock();
+        bus.clock();
+        assert_eq!(ppu.borrow().dot(), 6);
+
+        bus.write(0x2000, 0x80);
+        run_to(&mut ppu.borrow_mut(), 241, 2);
+        assert!(bus.nmi());
+        bus.read(0x3FFA, false);
+        assert!(!bus.nmi());
+    }
+
+    #[test]
+    fn pal_runs_sixteen_dots_every_five_cpu_cycles() {
+        let mut ppu = Ppu::new();
+        ppu.set_region(Region::Pal);
+
+        let mut dots = vec![];
+        for _ in 0..10 {
+            ppu.clock();
+            dots.push(ppu.dot());
+        }
+
+        assert_eq!(dots, vec![3, 6, 9, 12, 16, 19, 22, 25, 28, 32]);
+    }
+
+    /// Sprite tile 2 is solid colour 1 and tile 3 solid colour 2, and the
+    /// sprite palettes are filled in.
+    fn draw_sprite_tiles(ppu: &mut Ppu) {
+        write_vram(ppu, 0x0020, &[0xFF; 8]);
+        write_vram(ppu, 0x0028, &[0x00; 8]);
+        write_vram(ppu, 0x0030, &[0x00; 8]);
+        write_vram(ppu, 0x0038, &[0xFF; 8]);
+        write_vram(ppu, 0x3F10, &[0x0F, 0x16, 0x17, 0x18]);
+    }
+
+    /// Every byte of OAM set to $F8, which keeps all sprites off screen
+    /// without tripping the overflow bug on any visible line, then `sprites`
+    /// written from the start.
+    fn write_oam(ppu: &mut Ppu, sprites: &[[u8; 4]]) {
+        ppu.write(0x2003, 0x00);
+        for _ in 0..256 {
+            ppu.write(0x2004, 0xF8);
+        }
+
+        ppu.write(0x2003, 0x00);
+        for &byte in sprites.iter().flatten() {
+            ppu.write(0x2004, byte);
+        }
+    }
+
+    fn start_rendering(ppu: &mut Ppu, ctrl: u8, mask: u8) {
+        ppu.write(0x2000, ctrl);
+        ppu.write(0x2005, 0x00);
+        ppu.write(0x2005, 0x00);
+        ppu.write(0x2001, mask);
+    }
+
+    fn row(ppu: &Ppu, y: usize, x: usize, len: usize) -> Vec<u8> {
+        ppu.frame()[y * SCREEN_WIDTH + x..y * SCREEN_WIDTH + x + len].to_vec()
+    }
+
+    #[test]
+    fn sprites_draw_in_front_of_or_behind_the_background() {
+        let mut ppu = ppu(Mirroring::Horizontal);
+        draw_test_tile(&mut ppu);
+        draw_sprite_tiles(&mut ppu);
+
+        // Y is one line above where the sprite shows up
+        write_oam(&mut ppu, &[[0x00, 0x02, 0x00, 0x04], [0x00, 0x03, 0x00, 0x08]]);
+        start_rendering(&mut ppu, 0x00, 0x1E);
+        run_frames(&mut ppu, 2);
+
+        assert_eq!(row(&ppu, 0, 0, 8), vec![0x13, 0x13, 0x13, 0x13, 0x12, 0x12, 0x12, 0x12]);
+        // The first sprite wins where it overlaps the second
+        assert_eq!(row(&ppu, 1, 0, 16), [&[0x13; 4][..], &[0x16; 8], &[0x17; 4]].concat());
+
+        ppu.write(0x2003, 0x02);
+        ppu.write(0x2004, 0x20);
+        run_frames(&mut ppu, 1);
+        assert_eq!(row(&ppu, 1, 0, 16), [&[0x13; 4][..], &[0x12; 4], &[0x16; 4], &[0x17; 4]].concat());
+
+        // Hiding sprites from the left column
+        ppu.write(0x2003, 0x03);
+        ppu.write(0x2004, 0x00);
+        ppu.write(0x2001, 0x1A);
+        run_frames(&mut ppu, 1);
+        assert_eq!(row(&ppu, 1, 0, 10), [&[0x13; 4][..], &[0x12; 4], &[0x17; 2]].concat());
+    }
+
+    #[test]
+    fn sprites_flip_and_stack_in_8x16_mode() {
+        let mut ppu = ppu(Mirroring::Horizontal);
+        draw_sprite_tiles(&mut ppu);
+        // A half-filled tile in the right pattern table for 8x16 tile $03
+        write_vram(&mut ppu, 0x1020, &[0xF0; 8]);
+        write_vram(&mut ppu, 0x1028, &[0x00; 8]);
+        write_vram(&mut ppu, 0x1030, &[0x00; 8]);
+        write_vram(&mut ppu, 0x1038, &[0xFF; 8]);
+
+        write_oam(&mut ppu, &[[0x13, 0x03, 0x00, 0x10], [0x13, 0x03, 0xC0, 0x20]]);
+        start_rendering(&mut ppu, 0x20, 0x1E);
+        run_frames(&mut ppu, 2);
+
+        let plain = |ppu: &Ppu, y| row(ppu, y, 0x10, 8);
+        let flipped = |ppu: &Ppu, y| row(ppu, y, 0x20, 8);
+        let half = [&[0x16; 4][..], &[0x0F; 4]].concat();
+
+        assert_eq!(plain(&ppu, 19), vec![0x0F; 8]);
+        assert_eq!(plain(&ppu, 20), half);
+        assert_eq!(plain(&ppu, 27), half);
+        assert_eq!(plain(&ppu, 28), vec![0x17; 8]);
+        assert_eq!(plain(&ppu, 35), vec![0x17; 8]);
+        assert_eq!(plain(&ppu, 36), vec![0x0F; 8]);
+
+        assert_eq!(flipped(&ppu, 20), vec![0x17; 8]);
+        assert_eq!(flipped(&ppu, 28), [&[0x0F; 4][..], &[0x16; 4]].concat());
+    }
+
+    #[test]
+    fn sprite_zero_hit() {
+        let mut ppu = ppu(Mirroring::Horizontal);
+        draw_test_tile(&mut ppu);
+        draw_sprite_tiles(&mut ppu);
+        write_vram(&mut ppu, 0x201F, &[0x01]);
+
+        // Sprite 0 over the tile's opaque pixels, from column 6
+        write_oam(&mut ppu, &[[0x02, 0x02, 0x00, 0x06]]);
+        start_rendering(&mut ppu, 0x00, 0x1E);
+        run_frames(&mut ppu, 1);
+
+        run_to(&mut ppu, 3, 7);
+        assert_eq!(ppu.peek(0x2002) & 0x40, 0x00);
+        ppu.tick();
+        assert_eq!(ppu.peek(0x2002) & 0x40, 0x40);
+
+        // Cleared at the start of the pre-render line, not by reading
+        ppu.read(0x2002);
+        assert_eq!(ppu.peek(0x2002) & 0x40, 0x40);
+        run_to(&mut ppu, 261, 2);
+        assert_eq!(ppu.peek(0x2002) & 0x40, 0x00);
+
+        let hits = |ppu: &mut Ppu, sprites: &[[u8; 4]], mask: u8| {
+            run_to(ppu, 241, 10);
+            write_oam(ppu, sprites);
+            ppu.write(0x2001, mask);
+            run_to(ppu, 261, 2);
+            run_to(ppu, 240, 0);
+            ppu.peek(0x2002) & 0x40 != 0
+        };
+
+        // Over the backdrop
+        assert!(!hits(&mut ppu, &[[0x20, 0x02, 0x00, 0x40]], 0x1E));
+        // Behind the background still counts
+        assert!(hits(&mut ppu, &[[0x02, 0x02, 0x20, 0x00]], 0x1E));
+        // Left column hidden
+        assert!(!hits(&mut ppu, &[[0x02, 0x02, 0x00, 0x00]], 0x1C));
+        assert!(!hits(&mut ppu, &[[0x02, 0x02, 0x00, 0x00]], 0x1A));
+        // Only column 255 overlaps
+        assert!(!hits(&mut ppu, &[[0x02, 0x02, 0x00, 0xFF]], 0x1E));
+        assert!(hits(&mut ppu, &[[0x02, 0x02, 0x00, 0xFE]], 0x1E));
+        // Only sprite 0 can hit
+        assert!(!hits(&mut ppu, &[[0x20, 0x02, 0x00, 0x40], [0x02, 0x02, 0x00, 0x00]], 0x1E));
+    }
+
+    fn overflow_on_line_10(sprites: &[[u8; 4]]) -> bool {
+        let mut ppu = ppu(Mirroring::Horizontal);
+        write_oam(&mut ppu, sprites);
+        start_rendering(&mut ppu, 0x00, 0x18);
+
+        run_to(&mut ppu, 10, 0);
+        assert_eq!(ppu.peek(0x2002) & 0x20, 0x00);
+        run_to(&mut ppu, 11, 0);
+        ppu.peek(0x2002) & 0x20 != 0
+    }
+
+    #[test]
+    fn sprite_overflow_and_its_hardware_bug() {
+        let on_line_10 = [0x0A, 0x00, 0x00, 0x00];
+        let off_screen = [0xF8; 4];
+
+        // Eight is fine, a ninth overflows
+        assert!(!overflow_on_line_10(&[on_line_10; 8]));
+        assert!(overflow_on_line_10(&[on_line_10; 9]));
+
+        // After eight, the search drifts through the bytes of each entry:
+        // sprite 9's tile number gets compared as if it were a Y...
+        let mut sprites = vec![on_line_10; 8];
+        sprites.extend([off_screen, [0xF8, 0x0A, 0xF8, 0xF8]]);
+        assert!(overflow_on_line_10(&sprites));
+
+        // ...and sprite 9's real Y is skipped over
+        let mut sprites = vec![on_line_10; 8];
+        sprites.extend([off_screen, [0x0A, 0xF8, 0xF8, 0xF8]]);
+        assert!(!overflow_on_line_10(&sprites));
+    }
+
+    #[test]
+    fn only_eight_sprites_show_on_a_line() {
+        let mut ppu = ppu(Mirroring::Horizontal);
+        draw_sprite_tiles(&mut ppu);
+
+        let sprites: Vec<[u8; 4]> = (0..9).map(|i| [0x0A, 0x02, 0x00, i * 8]).collect();
+        write_oam(&mut ppu, &sprites);
+        start_rendering(&mut ppu, 0x00, 0x1E);
+        run_frames(&mut ppu, 2);
+
+        assert_eq!(row(&ppu, 11, 0, 72), [&[0x16; 64][..], &[0x0F; 8]].concat());
+    }
+
+    #[test]
+    fn oam_data_port() {
+        let mut ppu = ppu(Mirroring::Horizontal);
+        write_oam(&mut ppu, &[[0x10, 0x20, 0xFF, 0x30]]);
+
+        ppu.write(0x2003, 0x02);
+        assert_eq!(ppu.read(0x2004), 0xE3);
+        assert_eq!(ppu.read(0x2004), 0xE3);
+
+        // Secondary OAM clear shows through at the start of a rendered line
+        start_rendering(&mut ppu, 0x00, 0x08);
+        ppu.write(0x2003, 0x00);
+        run_to(&mut ppu, 5, 10);
+        assert_eq!(ppu.read(0x2004), 0xFF);
+        run_to(&mut ppu, 5, 100);
+        assert_eq!(ppu.read(0x2004), 0x10);
+
+        // Sprite fetches leave OAMADDR at 0
+        ppu.write(0x2003, 0x40);
+        run_to(&mut ppu, 5, 321);
+        assert_eq!(ppu.oam_addr, 0x00);
+    }
+
+    #[test]
+    fn sprite_fetches_clock_the_mmc3_once_a_line() {
+        let mut rom = vec![b'N', b'E', b'S', 0x1A, 2, 1, 0x40, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+        rom.resize(rom.len() + 32 * 1024 + 8 * 1024, 0x00);
+        let cartridge = Rc::new(RefCell::new(Cartridge::from_bytes(&rom).unwrap()));
+
+        let mut ppu = Ppu::new();
+        ppu.connect_cartridge(cartridge.clone());
+
+        {
+            let mut cartridge = cartridge.borrow_mut();
+            cartridge.write(0xC000, 10);
+            cartridge.write(0xC001, 0x00);
+            cartridge.write(0xE001, 0x00);
+        }
+        // Backgrounds from $0000 and (no) sprites from $1000
+        start_rendering(&mut ppu, 0x08, 0x18);
+
+        let mut run_to_line = |line: u16| {
+            while ppu.scanline() != line {
+                ppu.clock();
+                cartridge.borrow_mut().clock();
+            }
+            cartridge.borrow().irq()
+        };
+
+        assert!(!run_to_line(10));
+        assert!(run_to_line(11));
+    }
+
+    /// Runs one of blargg's test ROMs on the full system until it reports
+    /// a result through $6000, returning the result code and message.
+    fn run_test_rom(path: &std::path::Path) -> (u8, String) {
+        use crate::cpu_6502::{Cpu, IrqSource};
+
+        let cartridge = Rc::new(RefCell::new(Cartridge::from_file(path).unwrap()));
+        let ppu = Rc::new(RefCell::new(Ppu::new()));
+        ppu.borrow_mut().connect_cartridge(cartridge.clone());
+
+        let mut bus = Bus::new();
+        bus.connect_ppu(Box::new(ppu.clone()));
+        bus.connect_cartridge(Box::new(cartridge));
+
+        let mut cpu = Cpu::new();
+        cpu.reset(&mut bus);
+
+        let started = |bus: &Bus| (0x6001..=0x6003).map(|addr| bus.peek(addr)).eq([0xDE, 0xB0, 0x61]);
+        while ppu.borrow().frame_count() < 60 * 30 {
+            cpu.set_nmi_line(bus.nmi());
+            cpu.set_irq(IrqSource::Mapper, bus.irq());
+            cpu.clock(&mut bus);
+            bus.clock();
+
+            if started(&bus) && bus.peek(0x6000) < 0x80 {
+                break;
+            }
+        }
+
+        let message = (0x6004..).map(|addr| bus.peek(addr)).take_while(|&byte| byte != 0).map(|byte| byte as char).collect();
+        (bus.peek(0x6000), message)
+    }
+
+    /// Needs blargg's ppu_sprite_hit and ppu_sprite_overflow singles, which
+    /// are not redistributed here: `cargo test -- --ignored` with both
+    /// archives unpacked in `roms/`.
+    #[test]
+    #[ignore]
+    fn blargg_sprite_hit_and_overflow_roms() {
+        let root = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("roms");
+        let roms = [
+            "ppu_sprite_hit/rom_singles/01-basics.nes",
+            "ppu_sprite_hit/rom_singles/02-alignment.nes",
+            "ppu_sprite_hit/rom_singles/03-corners.nes",
+            "ppu_sprite_hit/rom_singles/04-flip.nes",
+            "ppu_sprite_hit/rom_singles/05-left_clip.nes",
+            "ppu_sprite_hit/rom_singles/06-right_edge.nes",
+            "ppu_sprite_hit/rom_singles/07-screen_bottom.nes",
+            "ppu_sprite_hit/rom_singles/08-double_height.nes",
+            "ppu_sprite_overflow/rom_singles/01-basics.nes",
+            "ppu_sprite_overflow/rom_singles/02-details.nes",
+        ];
+
+        for rom in roms {
+            let (result, message) = run_test_rom(&root.join(rom));
+            assert_eq!(result, 0, "{}: {}", rom, message);
+        }
+    }
+}
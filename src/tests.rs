@@ -0,0 +1,30 @@
+#[cfg(test)]
+mod main_tests {
+    use crate::*;
+
+    fn audio_args(flags: &[&str]) -> Vec<String> {
+        ["song.nes", "60", "song.wav"].iter().chain(flags).map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn parses_the_audio_options() {
+        let options = parse_audio_options(&audio_args(&["--stems", "--sample-rate", "48000"])).unwrap();
+
+        assert_eq!(options.rom, "song.nes");
+        assert_eq!(options.frames, 60);
+        assert_eq!(options.output, "song.wav");
+        assert!(options.stems);
+        assert_eq!(options.sample_rate, Some(48_000));
+    }
+
+    #[test]
+    fn rejects_a_sample_rate_of_zero() {
+        assert!(parse_audio_options(&audio_args(&["--sample-rate", "0"])).is_none());
+    }
+
+    #[test]
+    fn rejects_unreasonably_high_sample_rates() {
+        assert!(parse_audio_options(&audio_args(&["--sample-rate", "1000000"])).is_none());
+        assert!(parse_audio_options(&audio_args(&["--sample-rate", "4294967295"])).is_none());
+    }
+}
@@ -0,0 +1,57 @@
+mod tests;
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+const WAVE_FORMAT_PCM: u16 = 1;
+const CHANNELS: u16 = 1;
+const BITS_PER_SAMPLE: u16 = 16;
+/// Everything before the sample data.
+const HEADER_SIZE: u32 = 44;
+
+/// Writes `samples` as a mono 16 bit PCM RIFF WAVE file. Fails with
+/// `InvalidInput` on a sample rate of 0, or when the rate or the length
+/// don't fit the header's 32 bit fields.
+pub fn write_wav(writer: &mut impl Write, sample_rate: u32, samples: &[i16]) -> io::Result<()> {
+    let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidInput, message.to_string());
+    let block_align = CHANNELS * BITS_PER_SAMPLE / 8;
+
+    if sample_rate == 0 {
+        return Err(invalid("sample rate of 0"));
+    }
+    let byte_rate = sample_rate.checked_mul(block_align as u32).ok_or_else(|| invalid("sample rate too high"))?;
+    let data_size = u32::try_from(samples.len())
+        .ok()
+        .and_then(|len| len.checked_mul(block_align as u32))
+        .filter(|size| size.checked_add(HEADER_SIZE - 8).is_some())
+        .ok_or_else(|| invalid("too many samples"))?;
+
+    writer.write_all(b"RIFF")?;
+    writer.write_all(&(HEADER_SIZE - 8 + data_size).to_le_bytes())?;
+    writer.write_all(b"WAVE")?;
+
+    writer.write_all(b"fmt ")?;
+    writer.write_all(&16u32.to_le_bytes())?;
+    writer.write_all(&WAVE_FORMAT_PCM.to_le_bytes())?;
+    writer.write_all(&CHANNELS.to_le_bytes())?;
+    writer.write_all(&sample_rate.to_le_bytes())?;
+    writer.write_all(&byte_rate.to_le_bytes())?;
+    writer.write_all(&block_align.to_le_bytes())?;
+    writer.write_all(&BITS_PER_SAMPLE.to_le_bytes())?;
+
+    writer.write_all(b"data")?;
+    writer.write_all(&data_size.to_le_bytes())?;
+    for sample in samples {
+        writer.write_all(&sample.to_le_bytes())?;
+    }
+
+    Ok(())
+}
+
+/// `write_wav` to a new file at `path`.
+pub fn save_wav(path: impl AsRef<Path>, sample_rate: u32, samples: &[i16]) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    write_wav(&mut writer, sample_rate, samples)?;
+    writer.flush()
+}
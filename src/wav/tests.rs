@@ -0,0 +1,41 @@
+#[cfg(test)]
+mod wav_tests {
+    use crate::wav::*;
+
+    #[test]
+    fn writes_a_mono_16_bit_pcm_file() {
+        let mut bytes = vec![];
+        write_wav(&mut bytes, 44_100, &[0, 1, -1, i16::MAX]).unwrap();
+
+        assert_eq!(bytes.len(), 44 + 8);
+        assert_eq!(&bytes[0..4], b"RIFF");
+        assert_eq!(u32::from_le_bytes(bytes[4..8].try_into().unwrap()), 36 + 8);
+        assert_eq!(&bytes[8..16], b"WAVEfmt ");
+        // PCM, mono, 44100Hz, 88200 bytes/s, 2 byte frames, 16 bits
+        assert_eq!(&bytes[20..24], &[1, 0, 1, 0]);
+        assert_eq!(u32::from_le_bytes(bytes[24..28].try_into().unwrap()), 44_100);
+        assert_eq!(u32::from_le_bytes(bytes[28..32].try_into().unwrap()), 88_200);
+        assert_eq!(&bytes[32..36], &[2, 0, 16, 0]);
+        assert_eq!(&bytes[36..40], b"data");
+        assert_eq!(u32::from_le_bytes(bytes[40..44].try_into().unwrap()), 8);
+        assert_eq!(&bytes[44..], &[0x00, 0x00, 0x01, 0x00, 0xFF, 0xFF, 0xFF, 0x7F]);
+    }
+
+    #[test]
+    fn rejects_a_sample_rate_of_zero() {
+        let mut bytes = vec![];
+        let error = write_wav(&mut bytes, 0, &[0]).unwrap_err();
+
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+        assert!(bytes.is_empty());
+    }
+
+    #[test]
+    fn rejects_a_sample_rate_the_byte_rate_cannot_hold() {
+        let mut bytes = vec![];
+        let error = write_wav(&mut bytes, 1 << 31, &[0]).unwrap_err();
+
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+        assert!(bytes.is_empty());
+    }
+}